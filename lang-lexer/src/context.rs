@@ -2,10 +2,64 @@ use std::cell::RefCell;
 use std::collections::{BTreeMap, HashSet};
 use std::rc::Rc;
 
-use lang_util::{FileId, SmolStr};
+use lang_util::{position::NodeSpan, FileId, SmolStr, TextRange, TextSize};
 
 use glsl_lang_types::ast;
 
+/// Shader stage a source is written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    /// Vertex shader
+    Vertex,
+    /// Tessellation control shader
+    TessControl,
+    /// Tessellation evaluation shader
+    TessEval,
+    /// Geometry shader
+    Geometry,
+    /// Fragment shader
+    Fragment,
+    /// Compute shader
+    Compute,
+    /// Mesh shader
+    Mesh,
+    /// Task shader
+    Task,
+    /// Ray generation shader
+    RayGen,
+    /// Ray intersection shader
+    Intersection,
+    /// Ray any-hit shader
+    AnyHit,
+    /// Ray closest-hit shader
+    ClosestHit,
+    /// Ray miss shader
+    Miss,
+    /// Ray callable shader
+    Callable,
+}
+
+impl std::fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::TessControl => "tessellation control",
+            ShaderStage::TessEval => "tessellation evaluation",
+            ShaderStage::Geometry => "geometry",
+            ShaderStage::Fragment => "fragment",
+            ShaderStage::Compute => "compute",
+            ShaderStage::Mesh => "mesh",
+            ShaderStage::Task => "task",
+            ShaderStage::RayGen => "ray generation",
+            ShaderStage::Intersection => "intersection",
+            ShaderStage::AnyHit => "any-hit",
+            ShaderStage::ClosestHit => "closest-hit",
+            ShaderStage::Miss => "miss",
+            ShaderStage::Callable => "callable",
+        })
+    }
+}
+
 /// Parsing options
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ParseOptions {
@@ -17,6 +71,16 @@ pub struct ParseOptions {
     pub source_id: FileId,
     /// Allow Rust quoting identifiers (`#(ident)`) in the source
     pub allow_rs_ident: bool,
+    /// Maximum length of identifiers, in bytes (`None` for no limit)
+    ///
+    /// The GLSL specification requires support for identifiers of at least 1024 characters,
+    /// but some drivers reject longer ones.
+    pub max_identifier_length: Option<usize>,
+    /// Shader stage the source is written for (`None` if unknown)
+    ///
+    /// The parser accepts the same syntax for all stages, this is used by stage-specific
+    /// validation passes.
+    pub stage: Option<ShaderStage>,
 }
 
 impl Default for ParseOptions {
@@ -26,6 +90,8 @@ impl Default for ParseOptions {
             target_vulkan: false,
             source_id: FileId::new(0),
             allow_rs_ident: false,
+            max_identifier_length: None,
+            stage: None,
         }
     }
 }
@@ -35,6 +101,22 @@ impl ParseOptions {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Set the source identifier recorded in token positions and node spans
+    ///
+    /// Use distinct identifiers when parsing multiple sources whose syntax trees may be
+    /// combined, so their spans do not collide.
+    pub fn with_source_id(self, source_id: FileId) -> Self {
+        Self { source_id, ..self }
+    }
+
+    /// Set the shader stage the source is written for
+    pub fn with_stage(self, stage: ShaderStage) -> Self {
+        Self {
+            stage: Some(stage),
+            ..self
+        }
+    }
 }
 
 /// Parsing context
@@ -198,8 +280,17 @@ impl PartialEq for ParseContextData {
 }
 
 // Begin type name stuff
+//
+// GLSL is not context-free: `a * b;` is either a declaration or an expression depending on
+// whether `a` names a type. The lexer therefore asks the parse context whether each identifier
+// is a known type name, and emits a TypeName token instead of an Identifier if it is. Struct
+// declarations register their name in the current scope, and the active TypeTablePolicy may
+// promote other identifiers.
 
 /// Context in which an identifier is seen for the first time
+///
+/// This is passed to the [TypeTablePolicy] to decide whether the identifier should be
+/// registered as a type name for the rest of the current scope.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IdentifierContext {
     /// The identifier is a function declaration name
@@ -211,6 +302,15 @@ pub trait TypeTablePolicy: std::fmt::Debug {
     /// Return `true` if the given identifier (in its context) should now be considered a type name
     /// or not.
     fn promote_to_type_name(&self, name: &ast::Identifier, ctx: IdentifierContext) -> bool;
+
+    /// Return `true` if `name` is a type name which was not declared in the parsed source
+    ///
+    /// This is checked in addition to the registered type names, and can be used to resolve
+    /// types from an external source. The default implementation accepts no names.
+    fn is_type_name(&self, name: &str) -> bool {
+        let _ = name;
+        false
+    }
 }
 
 /// Default GLSL type table policy: only struct declarations create new type names
@@ -230,10 +330,20 @@ impl ParseContext {
     }
 
     /// Register `name` as a new type name
+    ///
+    /// This can be used to declare struct names from previously parsed sources before parsing
+    /// code that depends on them.
     pub fn add_type_name(&self, name: ast::Identifier) -> ast::TypeName {
         self.data.borrow_mut().add_type_name(name)
     }
 
+    /// Unregister `name` as a type name, in all scopes
+    ///
+    /// Returns `true` if `name` was a registered type name.
+    pub fn remove_type_name(&self, name: &str) -> bool {
+        self.data.borrow_mut().remove_type_name(name)
+    }
+
     /// Enter a new nesting level for declarations
     pub fn push_scope(&self) {
         self.data.borrow_mut().push_scope();
@@ -253,7 +363,7 @@ impl ParseContext {
 impl ParseContextData {
     /// Return `true` if the given name is a type name
     pub fn is_type_name(&self, name: &str) -> bool {
-        self.names.iter().any(|level| level.contains(name))
+        self.names.iter().any(|level| level.contains(name)) || self.policy.is_type_name(name)
     }
 
     /// Register `name` as a new type name
@@ -263,6 +373,15 @@ impl ParseContextData {
         name.map(ast::TypeNameData::from)
     }
 
+    /// Unregister `name` as a type name, in all scopes
+    ///
+    /// Returns `true` if `name` was a registered type name.
+    pub fn remove_type_name(&mut self, name: &str) -> bool {
+        self.names
+            .iter_mut()
+            .fold(false, |removed, level| level.remove(name) || removed)
+    }
+
     /// Enter a new nesting level for declarations
     pub fn push_scope(&mut self) {
         self.names.push(HashSet::new());
@@ -288,6 +407,10 @@ impl ParseContextData {
 // Begin comment stuff
 
 /// A list of comments indexed by their position
+///
+/// Comments are ordered by source and then by position, and hold their text without the `//`
+/// or `/* */` delimiters. Use [ParseContextData::comments_before] and
+/// [ParseContextData::comments_after] to find the comments surrounding a node.
 pub type CommentList = BTreeMap<lang_util::position::NodeSpan, ast::Comment>;
 
 impl ParseContext {
@@ -310,4 +433,54 @@ impl ParseContextData {
             comments.insert(span.unwrap(), comment);
         }
     }
+
+    /// Return the comments which end before the given span starts, in source order
+    ///
+    /// Only comments from the same source as `span` are returned. The comment closest to the
+    /// span is the last one, so documentation comments can be extracted with
+    /// [DoubleEndedIterator::next_back].
+    ///
+    /// # Parameters
+    ///
+    /// * `span`: span of the node to look up comments for
+    pub fn comments_before(
+        &self,
+        span: NodeSpan,
+    ) -> impl DoubleEndedIterator<Item = &ast::Comment> {
+        let start = span.range().start();
+
+        self.comments
+            .iter()
+            .flat_map(move |comments| {
+                comments.range(
+                    NodeSpan::new_start(span.source_id())
+                        ..NodeSpan::new(span.source_id(), TextRange::empty(start)),
+                )
+            })
+            .map(|(_, comment)| comment)
+            .filter(move |comment| comment.span.unwrap().range().end() <= start)
+    }
+
+    /// Return the comments which start after the given span ends, in source order
+    ///
+    /// Only comments from the same source as `span` are returned. The comment closest to the
+    /// span is the first one.
+    ///
+    /// # Parameters
+    ///
+    /// * `span`: span of the node to look up comments for
+    pub fn comments_after(&self, span: NodeSpan) -> impl DoubleEndedIterator<Item = &ast::Comment> {
+        let end = span.range().end();
+        let source_end = TextSize::from(u32::MAX);
+
+        self.comments
+            .iter()
+            .flat_map(move |comments| {
+                comments.range(
+                    NodeSpan::new(span.source_id(), TextRange::empty(end))
+                        ..=NodeSpan::new(span.source_id(), TextRange::new(source_end, source_end)),
+                )
+            })
+            .map(|(_, comment)| comment)
+    }
 }
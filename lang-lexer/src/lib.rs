@@ -92,4 +92,196 @@ mod tests {
             },
         ));
     }
+
+    #[cfg(feature = "v2-full")]
+    #[test]
+    fn test_rewind_v2_full() {
+        let mut lexer =
+            v2_full::str::Lexer::new("#define X 1\nint x = X;\n", &ParseOptions::default())
+                .run(ParseContext::default());
+
+        let first: Vec<_> = lexer.by_ref().map(|item| item.unwrap().1).collect();
+        assert!(!first.is_empty());
+
+        lexer.rewind();
+        let second: Vec<_> = lexer.map(|item| item.unwrap().1).collect();
+        assert_eq!(first, second);
+    }
+
+    #[cfg(feature = "v2-full")]
+    #[test]
+    fn test_location_v2_full() {
+        let source_id = lang_util::FileId::new(3);
+        let mut lexer = v2_full::str::Lexer::new(
+            "int x;\n#line 42\nint y;\n",
+            &ParseOptions {
+                source_id,
+                ..Default::default()
+            },
+        )
+        .run(ParseContext::default());
+
+        assert_eq!(lexer.location().current_file(), source_id);
+        assert!(lexer.location().line_override().is_none());
+
+        lexer.by_ref().for_each(drop);
+        assert!(lexer.location().line_override().is_some());
+    }
+
+    #[cfg(feature = "v2-full")]
+    #[test]
+    fn test_max_identifier_length_v2_full() {
+        let source = format!("int {};", "a".repeat(1025));
+        let errors = |max_identifier_length| {
+            v2_full::str::Lexer::new(
+                &source,
+                &ParseOptions {
+                    max_identifier_length,
+                    ..Default::default()
+                },
+            )
+            .run(ParseContext::default())
+            .filter_map(Result::err)
+            .collect::<Vec<_>>()
+        };
+
+        assert!(matches!(
+            errors(Some(1024)).as_slice(),
+            [v2_full::LexicalError::IdentifierTooLong { length: 1025, .. }]
+        ));
+        assert!(errors(Some(1025)).is_empty());
+        assert!(errors(None).is_empty());
+    }
+
+    #[cfg(feature = "v2-min")]
+    #[test]
+    fn test_max_identifier_length_v2_min() {
+        let source = format!("int {};", "a".repeat(1025));
+        let errors = |max_identifier_length| {
+            v2_min::str::Lexer::new(
+                &source,
+                &ParseOptions {
+                    max_identifier_length,
+                    ..Default::default()
+                },
+            )
+            .run(ParseContext::default())
+            .filter_map(Result::err)
+            .collect::<Vec<_>>()
+        };
+
+        assert!(matches!(
+            errors(Some(1024)).as_slice(),
+            [v2_min::LexicalError::IdentifierTooLong { length: 1025, .. }]
+        ));
+        assert!(errors(Some(1025)).is_empty());
+        assert!(errors(None).is_empty());
+    }
+
+    #[cfg(feature = "v1")]
+    #[test]
+    fn test_max_identifier_length_v1() {
+        let source = format!("int {};", "a".repeat(1025));
+        let errors = |max_identifier_length| {
+            v1::Lexer::new(
+                &source,
+                &ParseOptions {
+                    max_identifier_length,
+                    ..Default::default()
+                },
+            )
+            .run(ParseContext::default())
+            .filter_map(Result::err)
+            .collect::<Vec<_>>()
+        };
+
+        assert!(matches!(
+            errors(Some(1024)).as_slice(),
+            [v1::LexicalError::IdentifierTooLong { length, .. }] if u32::from(*length) == 1025
+        ));
+        assert!(errors(Some(1025)).is_empty());
+        assert!(errors(None).is_empty());
+    }
+
+    #[cfg(feature = "v2-full")]
+    #[test]
+    fn test_control_flow_attribute_directives_v2_full() {
+        let lex = |attribute: &str| {
+            let source = format!(
+                "#extension GL_EXT_control_flow_attributes : enable\n[[unroll\n{}\n]] X;\n",
+                attribute
+            );
+
+            v2_full::str::Lexer::new(&source, &ParseOptions::default())
+                .run(ParseContext::default())
+                .map(|item| item.map(|(_, token, _)| token))
+                .collect::<Vec<_>>()
+        };
+
+        // Directives inside the attribute still apply to the following tokens
+        assert_eq!(
+            lex("#define X 1"),
+            [Ok(Token::IntConstant(1)), Ok(Token::Semicolon)]
+        );
+
+        let items = lex("#error oops");
+        assert_eq!(items.len(), 3);
+        assert!(items[0].is_err());
+    }
+
+    #[cfg(feature = "v2-full")]
+    #[test]
+    fn test_display_with_source_v2_full() {
+        use lang_util::error::LexicalError;
+
+        let source = "#version 460\nvoid main() {\n  int abcde;\n}\n";
+        let error = v2_full::str::Lexer::new(
+            source,
+            &ParseOptions {
+                max_identifier_length: Some(4),
+                ..Default::default()
+            },
+        )
+        .run(ParseContext::default())
+        .find_map(Result::err)
+        .unwrap();
+
+        assert_eq!(
+            error.display_with_source(source),
+            format!(
+                "3:7: error: {}\n1 | #version 460\n2 | void main() {{\n3 |   int abcde;\n  |       ^^^^^\n4 | }}\n",
+                error
+            )
+        );
+    }
+
+    #[test]
+    fn test_lalrpop_tokens() {
+        let lines: Vec<_> = Token::LALRPOP_TOKENS.lines().map(str::trim).collect();
+
+        assert_eq!(lines.first(), Some(&"enum Token {"));
+        assert_eq!(lines.last(), Some(&"}"));
+        assert!(lines.contains(&"ident => Token::Identifier(_),"));
+        assert!(lines.contains(&"\"const\" => Token::Const,"));
+        assert!(lines.contains(&"\"(\" => Token::LeftParen,"));
+    }
+
+    #[test]
+    fn test_keywords() {
+        let keywords = Token::keywords();
+
+        assert!(keywords.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(keywords.contains(&"const"));
+        assert!(keywords.contains(&"vec4"));
+        assert!(keywords.contains(&"("));
+    }
+
+    #[test]
+    fn test_token_display() {
+        assert_eq!(
+            Token::PpPathAbsolute("a.glsl".to_owned()).to_string(),
+            "include path `<a.glsl>`"
+        );
+        assert_eq!(Token::Identifier("x".into()).to_string(), "identifier `x`");
+    }
 }
@@ -11,6 +11,7 @@ use logos::Logos;
 #[derive(Debug, Clone, PartialEq, lang_util::Token)]
 #[cfg_attr(feature = "v1", derive(Logos))]
 #[cfg_attr(feature = "v1", logos(extras = (crate::ParseContext, crate::ParseOptions)))]
+#[lang_util(generate_lalrpop, generate_keywords_fn)]
 #[allow(missing_docs)]
 pub enum Token {
     #[cfg_attr(feature = "v1", token("const"))]
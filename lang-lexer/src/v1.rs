@@ -29,6 +29,7 @@ impl<'i> Lexer<'i> {
             inner: LexerStage::Source(Token::lexer_with_extras(self.source, (ctx, self.opts))),
             source: self.source,
             last_token: None,
+            max_identifier_length: self.opts.max_identifier_length,
         }
     }
 }
@@ -43,6 +44,7 @@ pub struct LexerIterator<'i> {
     inner: LexerStage<'i>,
     source: &'i str,
     last_token: Option<Token>,
+    max_identifier_length: Option<usize>,
 }
 
 impl<'i> LexerIterator<'i> {
@@ -328,7 +330,20 @@ impl<'i> Iterator for LexerIterator<'i> {
         };
 
         self.last_token = result.as_ref().map(|s| s.1.clone());
-        Ok(result).transpose()
+
+        match result {
+            Some((start, Token::Identifier(ident) | Token::TypeName(ident), end))
+                if self
+                    .max_identifier_length
+                    .is_some_and(|max| ident.len() > max) =>
+            {
+                Some(Err(LexicalError::IdentifierTooLong {
+                    location: start,
+                    length: end.offset - start.offset,
+                }))
+            }
+            result => Ok(result).transpose(),
+        }
     }
 }
 
@@ -400,6 +415,14 @@ pub enum LexicalError {
         /// Length of the token
         length: TextSize,
     },
+    /// Identifier longer than [crate::ParseOptions::max_identifier_length]
+    #[error("identifier too long: {} characters", u32::from(*.length))]
+    IdentifierTooLong {
+        /// Location for the error
+        location: LexerPosition,
+        /// Length of the identifier
+        length: TextSize,
+    },
 }
 
 impl lang_util::error::LexicalError for LexicalError {
@@ -411,7 +434,8 @@ impl lang_util::error::LexicalError for LexicalError {
             LexicalError::InvalidFloatLiteral {
                 location, length, ..
             } => (*location, *length),
-            LexicalError::ForbiddenRsQuote { location, length } => (*location, *length),
+            LexicalError::ForbiddenRsQuote { location, length }
+            | LexicalError::IdentifierTooLong { location, length } => (*location, *length),
         }
     }
 }
@@ -28,6 +28,13 @@ pub enum LexicalError<E: std::error::Error + 'static> {
         /// Location of the error
         pos: NodeSpan,
     },
+    /// Identifier longer than [crate::ParseOptions::max_identifier_length]
+    IdentifierTooLong {
+        /// Length of the identifier
+        length: usize,
+        /// Location of the identifier
+        pos: NodeSpan,
+    },
     /// Preprocessor error
     Processor(processor::event::Error),
     /// i/o error
@@ -44,6 +51,13 @@ impl<E: std::error::Error + 'static> std::cmp::PartialEq for LexicalError<E> {
                 } => kind == other_kind && pos == other_pos,
                 _ => false,
             },
+            LexicalError::IdentifierTooLong { length, pos } => match other {
+                LexicalError::IdentifierTooLong {
+                    length: other_length,
+                    pos: other_pos,
+                } => length == other_length && pos == other_pos,
+                _ => false,
+            },
             LexicalError::Processor(p) => match other {
                 LexicalError::Processor(other_p) => p == other_p,
                 _ => false,
@@ -57,6 +71,9 @@ impl<E: std::error::Error + 'static> std::fmt::Display for LexicalError<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LexicalError::Token { kind, .. } => write!(f, "{}", kind),
+            LexicalError::IdentifierTooLong { length, .. } => {
+                write!(f, "identifier too long: {} characters", length)
+            }
             LexicalError::Processor(error) => write!(f, "{}", error.inner()),
             LexicalError::Io(io) => write!(f, "{}", io.inner()),
         }
@@ -68,7 +85,9 @@ impl<E: std::error::Error + 'static> std::error::Error for LexicalError<E> {}
 impl<E: std::error::Error + 'static> lang_util::error::LexicalError for LexicalError<E> {
     fn location(&self) -> (LexerPosition, TextSize) {
         match self {
-            LexicalError::Token { pos, .. } => (pos.start(), pos.len()),
+            LexicalError::Token { pos, .. } | LexicalError::IdentifierTooLong { pos, .. } => {
+                (pos.start(), pos.len())
+            }
             LexicalError::Processor(err) => (
                 LexerPosition::new(err.current_file().unwrap(), err.pos().start()),
                 err.pos().len(),
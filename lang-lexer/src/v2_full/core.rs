@@ -1,8 +1,12 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 
 use glsl_lang_pp::{
+    exts::names::ExtNameAtom,
     last::{self, LocatedIterator, MaybeToken, TokenState, Tokenizer},
-    processor::event::{self, Error, EventDirective, OutputToken},
+    processor::{
+        event::{self, DirectiveKind, Error, EventDirective, OutputToken},
+        nodes::{ExtensionBehavior, ExtensionName},
+    },
     types,
 };
 
@@ -21,6 +25,8 @@ pub struct LexerCore {
     file_id: FileId,
     opts: ParseOptions,
     directives: Vec<EventDirective>,
+    /// Extensions enabled by the #extension directives seen so far
+    extensions_active: HashSet<ExtNameAtom>,
 }
 
 pub enum HandleTokenResult<E: std::error::Error + 'static> {
@@ -79,6 +85,26 @@ impl<E: std::error::Error + 'static> HandleTokenResult<E> {
         }
     }
 
+    /// Put back events which were read ahead, before the pending events
+    pub fn unread_events(&mut self, mut events: VecDeque<Result<last::Event, Located<E>>>) {
+        if events.is_empty() {
+            return;
+        }
+
+        match std::mem::take(self) {
+            HandleTokenResult::None => {
+                *self = HandleTokenResult::Pending(Default::default(), events);
+            }
+            HandleTokenResult::Item(old_item) => {
+                *self = HandleTokenResult::Pending(VecDeque::from([old_item]), events);
+            }
+            HandleTokenResult::Pending(items, old_events) => {
+                events.extend(old_events);
+                *self = HandleTokenResult::Pending(items, events);
+            }
+        }
+    }
+
     pub fn push_errors(&mut self, errors: impl IntoIterator<Item = Error>) {
         let items = errors.into_iter().map(|error| Err(error.into()));
 
@@ -114,6 +140,7 @@ impl LexerCore {
             file_id,
             opts: *opts,
             directives: Vec::with_capacity(2),
+            extensions_active: HashSet::new(),
         }
     }
 
@@ -131,6 +158,14 @@ impl LexerCore {
         )
     }
 
+    /// Reset the lexing state, keeping the parse context
+    pub fn reset(&mut self, opts: &ParseOptions) {
+        self.file_id = opts.source_id;
+        self.opts = *opts;
+        self.directives.clear();
+        self.extensions_active.clear();
+    }
+
     pub fn handle_file_id(&mut self, file_id: FileId) {
         self.file_id = file_id;
     }
@@ -148,6 +183,10 @@ impl LexerCore {
         <Tokenizer<'r, I> as Iterator>::Item: MaybeToken,
     {
         if state.active() {
+            if self.handle_extension_token(&token_kind, tokenizer, token_state) {
+                return;
+            }
+
             match self.lang_token(&source_token, token_kind) {
                 Ok(token) => {
                     // Try to get the next token when we encounter trivia
@@ -171,6 +210,17 @@ impl LexerCore {
                                 self.ctx.add_comment(comment);
                             }
                         }
+                        Token::Identifier(ref ident) | Token::TypeName(ref ident)
+                            if self
+                                .opts
+                                .max_identifier_length
+                                .is_some_and(|max| ident.len() > max) =>
+                        {
+                            token_state.push_item(Err(LexicalError::IdentifierTooLong {
+                                length: ident.len(),
+                                pos: source_token.text_range(),
+                            }));
+                        }
                         _ => {
                             if token.1 == Token::LeftBrace {
                                 self.ctx.push_scope();
@@ -275,6 +325,71 @@ impl LexerCore {
         }
     }
 
+    /// Return true if the given extension was enabled by an #extension directive
+    pub fn is_extension_active(&self, name: &ExtNameAtom) -> bool {
+        self.extensions_active.contains(name)
+    }
+
+    /// Apply the lexer rules which depend on the enabled extensions
+    ///
+    /// Returns true if the token was consumed by one of these rules, in which case it should not
+    /// be lexed any further. Tokens read ahead but not consumed are put back in `token_state`.
+    pub fn handle_extension_token<'r, I, E>(
+        &self,
+        token_kind: &types::Token,
+        tokenizer: &mut Tokenizer<'r, I>,
+        token_state: &mut HandleTokenResult<E>,
+    ) -> bool
+    where
+        E: std::error::Error + 'static,
+        I: Iterator<Item = Result<event::Event, Located<E>>> + LocatedIterator,
+        <Tokenizer<'r, I> as Iterator>::Item: MaybeToken,
+    {
+        // GL_EXT_control_flow_attributes: [[unroll]], [[dont_unroll]], etc. are optimization
+        // hints which are not part of the AST, skip them
+        if *token_kind != types::Token::LBRACKET
+            || !self.is_extension_active(&ExtNameAtom::from("GL_EXT_control_flow_attributes"))
+        {
+            return false;
+        }
+
+        let mut lookahead: VecDeque<Result<last::Event, Located<E>>> = VecDeque::new();
+        let mut level = 1;
+        let mut in_attribute = false;
+
+        while let Some(result) = token_state.pop_event().or_else(|| tokenizer.next()) {
+            match result.as_token_kind() {
+                Some(types::Token::WS) => {}
+                Some(types::Token::LBRACKET) => {
+                    in_attribute = true;
+                    level += 1;
+                }
+                Some(types::Token::RBRACKET) if in_attribute => {
+                    level -= 1;
+
+                    if level == 0 {
+                        // Directives and errors read along with the attribute are not part of
+                        // it, they still have to be processed
+                        lookahead.retain(|result| result.as_token_kind().is_none());
+                        token_state.unread_events(lookahead);
+                        return true;
+                    }
+                }
+                _ if in_attribute => {}
+                _ => {
+                    lookahead.push_back(result);
+                    break;
+                }
+            }
+
+            lookahead.push_back(result);
+        }
+
+        // Not an attribute, or an unterminated one: lex the tokens as usual
+        token_state.unread_events(lookahead);
+        false
+    }
+
     pub fn into_directives(self) -> Directives {
         self.directives.into()
     }
@@ -288,6 +403,23 @@ impl LexerCore {
             return Ok(());
         }
 
+        if let DirectiveKind::Extension(extension) = directive.kind() {
+            match &extension.name {
+                ExtensionName::All => {
+                    if extension.behavior == ExtensionBehavior::Disable {
+                        self.extensions_active.clear();
+                    }
+                }
+                ExtensionName::Specific(name) => {
+                    if extension.behavior.is_active() {
+                        self.extensions_active.insert(name.clone());
+                    } else {
+                        self.extensions_active.remove(name);
+                    }
+                }
+            }
+        }
+
         let errors = directive.errors().to_vec();
 
         self.directives.push(directive);
@@ -8,6 +8,7 @@ use glsl_lang_pp::{
     exts::{Registry, DEFAULT_REGISTRY},
     last::{self, Event},
     processor::{
+        expand::ExpandLocation,
         fs::{ExpandStack, ParsedFile, Processor},
         ProcessorState,
     },
@@ -62,6 +63,14 @@ impl<F: FileSystem> LexerIterator<'_, '_, F> {
     pub fn into_directives(self) -> Directives {
         self.core.into_directives()
     }
+
+    /// Return the location of the preprocessor in the input
+    ///
+    /// This gives the file currently being lexed and the line mapping used to resolve offsets,
+    /// including the effect of #line directives, without consuming any token.
+    pub fn location(&self) -> &ExpandLocation {
+        self.inner.location()
+    }
 }
 
 impl<'r, 'p, F: FileSystem> Iterator for LexerIterator<'r, 'p, F> {
@@ -107,10 +116,17 @@ impl<'r, 'p, F: FileSystem> Iterator for LexerIterator<'r, 'p, F> {
                             file_id,
                             path,
                             canonical_path: _,
+                            depth: _,
                         } => {
                             self.current_file = path;
                             self.core.handle_file_id(file_id);
                         }
+
+                        Event::ExitFile { .. }
+                        | Event::EnterMacro { .. }
+                        | Event::ExitMacro { .. } => {
+                            // Ignore
+                        }
                     },
 
                     Err(err) => {
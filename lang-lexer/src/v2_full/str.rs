@@ -1,12 +1,13 @@
 //! Memory based glsl-lang-pp preprocessing lexer
 
-use lang_util::{position::LexerPosition, FileId};
+use lang_util::position::LexerPosition;
 
 use glsl_lang_pp::{
     exts::{Registry, DEFAULT_REGISTRY},
     last::{self, Event},
     processor::{
         self,
+        expand::ExpandLocation,
         str::{ExpandStr, ProcessStrError},
         ProcessorState,
     },
@@ -24,6 +25,21 @@ pub struct Lexer<'i> {
     inner: last::Tokenizer<'i, ExpandStr>,
     handle_token: HandleTokenResult<ProcessStrError>,
     opts: ParseOptions,
+    source: Source<'i>,
+}
+
+/// Inputs of a memory lexer, kept to restart lexing
+struct Source<'i> {
+    text: &'i str,
+    registry: &'i Registry,
+    state: ProcessorState,
+}
+
+impl<'i> Source<'i> {
+    fn tokenize(&self, opts: &ParseOptions) -> last::Tokenizer<'i, ExpandStr> {
+        processor::str::process_with_file_id(self.text, self.state.clone(), opts.source_id)
+            .tokenize(opts.default_version, opts.target_vulkan, self.registry)
+    }
 }
 
 impl<'i> Lexer<'i> {
@@ -33,14 +49,17 @@ impl<'i> Lexer<'i> {
         opts: &ParseOptions,
         state: ProcessorState,
     ) -> Self {
+        let source = Source {
+            text: source,
+            registry,
+            state,
+        };
+
         Self {
-            inner: processor::str::process(source, state).tokenize(
-                opts.default_version,
-                opts.target_vulkan,
-                registry,
-            ),
+            inner: source.tokenize(opts),
             handle_token: Default::default(),
             opts: *opts,
+            source,
         }
     }
 
@@ -49,7 +68,8 @@ impl<'i> Lexer<'i> {
             inner: self.inner,
             core: LexerCore::new(&self.opts, ctx),
             handle_token: self.handle_token,
-            source_id: self.opts.source_id,
+            opts: self.opts,
+            source: self.source,
         }
     }
 }
@@ -59,13 +79,33 @@ pub struct LexerIterator<'i> {
     inner: last::Tokenizer<'i, ExpandStr>,
     core: LexerCore,
     handle_token: HandleTokenResult<ProcessStrError>,
-    source_id: FileId,
+    opts: ParseOptions,
+    source: Source<'i>,
 }
 
 impl LexerIterator<'_> {
     pub fn into_directives(self) -> Directives {
         self.core.into_directives()
     }
+
+    /// Return the location of the preprocessor in the input
+    ///
+    /// This gives the file currently being lexed and the line mapping used to resolve offsets,
+    /// including the effect of #line directives, without consuming any token.
+    pub fn location(&self) -> &ExpandLocation {
+        self.inner.location()
+    }
+
+    /// Restart lexing from the beginning of the source
+    ///
+    /// The source is preprocessed again from the initial processor state, and the directives
+    /// collected so far are discarded. The parse context is kept as-is, including the type
+    /// names declared before rewinding.
+    pub fn rewind(&mut self) {
+        self.inner = self.source.tokenize(&self.opts);
+        self.core.reset(&self.opts);
+        self.handle_token = Default::default();
+    }
 }
 
 impl<'i> Iterator for LexerIterator<'i> {
@@ -83,12 +123,15 @@ impl<'i> Iterator for LexerIterator<'i> {
                     Ok(event) => match event {
                         Event::Error { mut error, masked } => {
                             if !masked {
-                                error.set_current_file(self.source_id);
+                                error.set_current_file(self.opts.source_id);
                                 return Some(Err(error.into()));
                             }
                         }
 
-                        Event::EnterFile { .. } => {
+                        Event::EnterFile { .. }
+                        | Event::ExitFile { .. }
+                        | Event::EnterMacro { .. }
+                        | Event::ExitMacro { .. } => {
                             // Ignore
                         }
 
@@ -156,7 +199,7 @@ impl<'i> LangLexerIterator for LexerIterator<'i> {
 
         lang_util::error::ParseError::<Self::Error>::builder()
             .pos(lexer)
-            .current_file(self.source_id)
+            .current_file(self.opts.source_id)
             .resolve(location)
             .finish(err.into())
     }
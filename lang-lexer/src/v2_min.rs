@@ -19,12 +19,22 @@ pub enum LexicalError {
         /// Location of the error
         pos: NodeSpan,
     },
+    /// Identifier longer than [crate::ParseOptions::max_identifier_length]
+    IdentifierTooLong {
+        /// Length of the identifier
+        length: usize,
+        /// Location of the identifier
+        pos: NodeSpan,
+    },
 }
 
 impl std::fmt::Display for LexicalError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LexicalError::Token { kind, .. } => write!(f, "{}", kind),
+            LexicalError::IdentifierTooLong { length, .. } => {
+                write!(f, "identifier too long: {} characters", length)
+            }
         }
     }
 }
@@ -34,7 +44,9 @@ impl std::error::Error for LexicalError {}
 impl lang_util::error::LexicalError for LexicalError {
     fn location(&self) -> (LexerPosition, TextSize) {
         match self {
-            LexicalError::Token { pos, .. } => (pos.start(), pos.len()),
+            LexicalError::Token { pos, .. } | LexicalError::IdentifierTooLong { pos, .. } => {
+                (pos.start(), pos.len())
+            }
         }
     }
 }
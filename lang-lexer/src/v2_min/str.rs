@@ -277,6 +277,17 @@ impl<'i> Iterator for LexerIterator<'i> {
                                 self.ctx.add_comment(comment);
                             }
                         }
+                        Token::Identifier(ref ident) | Token::TypeName(ref ident)
+                            if self
+                                .opts
+                                .max_identifier_length
+                                .is_some_and(|max| ident.len() > max) =>
+                        {
+                            return Some(Err(LexicalError::IdentifierTooLong {
+                                length: ident.len(),
+                                pos,
+                            }));
+                        }
                         Token::Identifier(ref ident) | Token::TypeName(ref ident)
                             if self.flags == PpFlags::Version =>
                         {
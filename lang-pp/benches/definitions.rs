@@ -0,0 +1,72 @@
+use std::{collections::HashMap, hash::BuildHasher};
+
+use criterion::{
+    criterion_group, criterion_main, measurement::Measurement, BenchmarkGroup, Criterion,
+};
+
+use glsl_lang_pp::processor::{str::process, ProcessorState};
+use lang_util::SmolStr;
+
+const DEFINE_COUNT: usize = 500;
+
+fn names() -> Vec<SmolStr> {
+    (0..DEFINE_COUNT)
+        .map(|i| SmolStr::from(format!("DEFINE_{}", i)))
+        .collect()
+}
+
+fn source() -> String {
+    let names = names();
+    let mut source = String::new();
+
+    for (i, name) in names.iter().enumerate() {
+        source.push_str(&format!("#define {} {}\n", name, i));
+    }
+
+    for name in &names {
+        source.push_str(&format!("int v_{} = {};\n", name, name));
+    }
+
+    source
+}
+
+fn lookup_impl<S: BuildHasher + Default, M: Measurement>(
+    group: &mut BenchmarkGroup<'_, M>,
+    id: &str,
+    names: &[SmolStr],
+) {
+    let map: HashMap<SmolStr, usize, S> = names
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, name)| (name, i))
+        .collect();
+
+    group.bench_function(id, |b| {
+        b.iter(|| {
+            names
+                .iter()
+                .map(|name| map.get(name.as_str()).copied().unwrap_or(0))
+                .sum::<usize>()
+        })
+    });
+}
+
+fn lookup(c: &mut Criterion) {
+    let names = names();
+    let mut group = c.benchmark_group("lookup");
+    lookup_impl::<std::collections::hash_map::RandomState, _>(&mut group, "std", &names);
+    lookup_impl::<ahash::RandomState, _>(&mut group, "ahash", &names);
+    group.finish();
+}
+
+fn preprocess(c: &mut Criterion) {
+    let source = source();
+
+    c.bench_function("preprocess", |b| {
+        b.iter(|| process(&source, ProcessorState::default()).count())
+    });
+}
+
+criterion_group!(benches, lookup, preprocess);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use glsl_lang_pp::processor::{str::process, ProcessorState};
+
+const LINE_COUNT: usize = 10_000;
+
+fn source() -> String {
+    let mut source = String::from("#version 460 core\n#define SCALE(x) ((x) * 2.0)\n");
+
+    for i in 0..LINE_COUNT / 2 {
+        source.push_str(&format!("float v_{} = SCALE({}.0) + 1.0;\n", i, i));
+        source.push_str(&format!("vec4 c_{} = vec4(v_{}, 0.0, 0.0, 1.0);\n", i, i));
+    }
+
+    source
+}
+
+fn expand(c: &mut Criterion) {
+    let source = source();
+
+    let mut group = c.benchmark_group("expand");
+    group.throughput(Throughput::Bytes(source.len() as _));
+    group.bench_function("10k_lines", |b| {
+        b.iter(|| process(&source, ProcessorState::default()).count())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, expand);
+criterion_main!(benches);
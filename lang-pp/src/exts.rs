@@ -9,6 +9,15 @@ pub mod names;
 
 use names::ExtNameAtom;
 
+mod conflicts;
+pub use conflicts::ExtensionConflictRegistry;
+
+mod deps;
+pub use deps::ExtensionDependencyRegistry;
+
+mod promotion;
+pub use promotion::ExtensionPromotionTable;
+
 pub struct ExtensionSpec {
     name: ExtNameAtom,
     type_names: Vec<TypeNameAtom>,
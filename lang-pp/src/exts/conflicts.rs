@@ -0,0 +1,40 @@
+use std::collections::{HashMap, HashSet};
+
+use super::names::ExtNameAtom;
+
+/// Registry of extensions which can't be enabled at the same time
+///
+/// When an `#extension` directive would enable an extension which conflicts with an already
+/// active one in a [ProcessorState](crate::processor::ProcessorState) which has a conflict
+/// registry attached, a
+/// [ConflictingExtensions](crate::processor::event::ProcessingErrorKind::ConflictingExtensions)
+/// error is reported instead.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionConflictRegistry {
+    conflicts: HashMap<ExtNameAtom, HashSet<ExtNameAtom>>,
+}
+
+impl ExtensionConflictRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare that extensions `a` and `b` are mutually exclusive
+    pub fn conflict(mut self, a: impl Into<ExtNameAtom>, b: impl Into<ExtNameAtom>) -> Self {
+        let (a, b) = (a.into(), b.into());
+        self.conflicts
+            .entry(a.clone())
+            .or_default()
+            .insert(b.clone());
+        self.conflicts.entry(b).or_default().insert(a);
+        self
+    }
+
+    /// Return true if extensions `a` and `b` are mutually exclusive
+    pub fn conflicts(&self, a: &ExtNameAtom, b: &ExtNameAtom) -> bool {
+        self.conflicts
+            .get(a)
+            .map(|conflicts| conflicts.contains(b))
+            .unwrap_or(false)
+    }
+}
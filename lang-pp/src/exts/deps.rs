@@ -0,0 +1,58 @@
+use std::collections::{HashMap, HashSet};
+
+use super::names::ExtNameAtom;
+
+/// Registry of dependencies between extensions
+///
+/// When an extension is enabled in a [ProcessorState](crate::processor::ProcessorState) which
+/// has a dependency registry attached, all of its transitive dependencies are enabled as well.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionDependencyRegistry {
+    dependencies: HashMap<ExtNameAtom, Vec<ExtNameAtom>>,
+}
+
+impl ExtensionDependencyRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare that `name` depends on all extensions in `dependencies`
+    pub fn depends_on(
+        mut self,
+        name: impl Into<ExtNameAtom>,
+        dependencies: impl IntoIterator<Item = impl Into<ExtNameAtom>>,
+    ) -> Self {
+        self.dependencies
+            .entry(name.into())
+            .or_default()
+            .extend(dependencies.into_iter().map(Into::into));
+        self
+    }
+
+    /// Return the direct dependencies of an extension
+    pub fn dependencies(&self, name: &ExtNameAtom) -> &[ExtNameAtom] {
+        self.dependencies
+            .get(name)
+            .map(|deps| deps.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Return the transitive dependencies of an extension, in depth-first order
+    ///
+    /// The extension itself is not included in the result, even if dependencies are cyclic.
+    pub fn transitive_dependencies(&self, name: &ExtNameAtom) -> Vec<ExtNameAtom> {
+        let mut result = Vec::new();
+        let mut seen = HashSet::new();
+        seen.insert(name.clone());
+
+        let mut stack: Vec<_> = self.dependencies(name).iter().rev().collect();
+        while let Some(dependency) = stack.pop() {
+            if seen.insert(dependency.clone()) {
+                result.push(dependency.clone());
+                stack.extend(self.dependencies(dependency).iter().rev());
+            }
+        }
+
+        result
+    }
+}
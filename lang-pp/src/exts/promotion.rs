@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use super::names::ExtNameAtom;
+
+/// Table of GLSL versions in which extensions were promoted to core
+///
+/// When a [ProcessorState](crate::processor::ProcessorState) has a promotion table attached,
+/// `#extension` directives for extensions which are already part of the current (non-ES) version
+/// are not recorded as active extensions, and an
+/// [ExtensionRedundant](crate::processor::event::ErrorKind::ExtensionRedundant) warning is
+/// reported instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionPromotionTable {
+    versions: HashMap<ExtNameAtom, u16>,
+}
+
+impl ExtensionPromotionTable {
+    /// Create a new empty promotion table
+    pub fn new() -> Self {
+        Self {
+            versions: Default::default(),
+        }
+    }
+
+    /// Declare that extension `name` is part of the core language starting with `version`
+    pub fn promote(mut self, name: impl Into<ExtNameAtom>, version: u16) -> Self {
+        self.versions.insert(name.into(), version);
+        self
+    }
+
+    /// Return the version in which the given extension was promoted to core
+    pub fn core_version(&self, name: &ExtNameAtom) -> Option<u16> {
+        self.versions.get(name).copied()
+    }
+}
+
+impl Default for ExtensionPromotionTable {
+    fn default() -> Self {
+        [
+            ("GL_ARB_uniform_buffer_object", 140),
+            ("GL_ARB_explicit_attrib_location", 330),
+            ("GL_ARB_shader_bit_encoding", 330),
+            ("GL_ARB_gpu_shader5", 400),
+            ("GL_ARB_gpu_shader_fp64", 400),
+            ("GL_ARB_sample_shading", 400),
+            ("GL_ARB_tessellation_shader", 400),
+            ("GL_ARB_texture_cube_map_array", 400),
+            ("GL_ARB_texture_gather", 400),
+            ("GL_ARB_separate_shader_objects", 410),
+            ("GL_ARB_conservative_depth", 420),
+            ("GL_ARB_shader_atomic_counters", 420),
+            ("GL_ARB_shader_image_load_store", 420),
+            ("GL_ARB_shading_language_420pack", 420),
+            ("GL_ARB_arrays_of_arrays", 430),
+            ("GL_ARB_compute_shader", 430),
+            ("GL_ARB_explicit_uniform_location", 430),
+            ("GL_ARB_shader_storage_buffer_object", 430),
+            ("GL_ARB_texture_query_levels", 430),
+            ("GL_ARB_enhanced_layouts", 440),
+            ("GL_ARB_cull_distance", 450),
+            ("GL_ARB_derivative_control", 450),
+            ("GL_ARB_shader_draw_parameters", 460),
+            ("GL_ARB_shader_group_vote", 460),
+        ]
+        .into_iter()
+        .fold(Self::new(), |table, (name, version)| {
+            table.promote(name, version)
+        })
+    }
+}
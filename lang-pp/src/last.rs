@@ -2,7 +2,7 @@
 
 use std::{collections::HashMap, iter::FusedIterator, path::PathBuf};
 
-use lang_util::{located::FileIdResolver, FileId};
+use lang_util::{located::FileIdResolver, position::NodeSpan, FileId, SmolStr};
 
 use crate::{
     exts::{names::ExtNameAtom, ExtensionSpec, Registry},
@@ -29,6 +29,11 @@ pub enum Event {
         file_id: FileId,
         path: PathBuf,
         canonical_path: PathBuf,
+        depth: u32,
+    },
+    ExitFile {
+        file_id: FileId,
+        depth: u32,
     },
     Token {
         source_token: OutputToken,
@@ -39,6 +44,14 @@ pub enum Event {
         directive: EventDirective,
         masked: bool,
     },
+    EnterMacro {
+        name: SmolStr,
+        invocation_span: NodeSpan,
+        definition_file_id: FileId,
+    },
+    ExitMacro {
+        name: SmolStr,
+    },
 }
 
 pub trait LocatedIterator {
@@ -272,11 +285,14 @@ impl<'r, E, I: Iterator<Item = Result<event::Event, E>> + LocatedIterator> Itera
                     file_id,
                     path,
                     canonical_path,
+                    depth,
                 } => Event::EnterFile {
                     file_id,
                     path,
                     canonical_path,
+                    depth,
                 },
+                event::Event::ExitFile { file_id, depth } => Event::ExitFile { file_id, depth },
                 event::Event::Token { token, masked } => {
                     let (token_kind, state, error) = self.tokenize_single(&token);
 
@@ -316,6 +332,16 @@ impl<'r, E, I: Iterator<Item = Result<event::Event, E>> + LocatedIterator> Itera
 
                     Event::Directive { directive, masked }
                 }
+                event::Event::EnterMacro {
+                    name,
+                    invocation_span,
+                    definition_file_id,
+                } => Event::EnterMacro {
+                    name,
+                    invocation_span,
+                    definition_file_id,
+                },
+                event::Event::ExitMacro { name } => Event::ExitMacro { name },
             }),
             Err(err) => Err(err),
         })
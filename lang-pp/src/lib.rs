@@ -1,5 +1,6 @@
 pub mod lexer;
 mod util;
+pub use util::LineMap;
 #[macro_use]
 pub mod types;
 
@@ -1,3 +1,5 @@
+use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+
 use rowan::GreenNode;
 
 use crate::util::LineMap;
@@ -31,4 +33,23 @@ impl Ast {
     pub fn green_node(&self) -> &GreenNode {
         &self.green_node
     }
+
+    /// Return a hash of the source text of this file
+    ///
+    /// The hash is only meant for in-memory lookups: it is not stable across builds, and
+    /// different contents may collide, see [Ast::same_content].
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        SyntaxNode::new_root(self.green_node.clone())
+            .text()
+            .for_each_chunk(|chunk| hasher.write(chunk.as_bytes()));
+        hasher.finish()
+    }
+
+    /// Return true if this file has the same source text as `other`
+    pub fn same_content(&self, other: &Ast) -> bool {
+        self.green_node == other.green_node
+            || SyntaxNode::new_root(self.green_node.clone()).text()
+                == SyntaxNode::new_root(other.green_node.clone()).text()
+    }
 }
@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
+use ahash::AHashMap;
 use lang_util::{FileId, SmolStr};
+use thiserror::Error;
 
 mod definition;
-use definition::Definition;
+pub use definition::Definition;
 
 pub mod event;
 
@@ -14,15 +16,26 @@ mod expr;
 pub mod fs;
 
 pub mod nodes;
-use nodes::{Define, DefineObject, Version};
+use nodes::{Define, DefineFunction, DefineObject, Version, VersionProfile};
 
 use crate::{
-    exts::Registry,
-    processor::nodes::{ExtensionBehavior, ExtensionName},
+    exts::{
+        names::ExtNameAtom, ExtensionConflictRegistry, ExtensionDependencyRegistry,
+        ExtensionPromotionTable, Registry,
+    },
+    processor::nodes::{Extension, ExtensionBehavior, ExtensionName},
 };
 
+pub mod span_map;
+
 pub mod str;
 
+#[cfg(test)]
+mod tests;
+
+/// Default maximum number of tokens an expansion may produce
+pub const DEFAULT_MAX_OUTPUT_TOKENS: usize = 1_000_000;
+
 /// Operating mode for #include directives
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IncludeMode {
@@ -32,6 +45,9 @@ pub enum IncludeMode {
     ArbInclude { warn: bool },
     /// GL_GOOGLE_include_directive compile-time includes
     GoogleInclude { warn: bool },
+    /// Both extensions are enabled: `#include "..."` is a compile-time include, while `#include
+    /// <...>` is a run-time include
+    Both { arb_warn: bool, google_warn: bool },
 }
 
 impl IncludeMode {
@@ -39,6 +55,41 @@ impl IncludeMode {
         match self {
             IncludeMode::None => false,
             IncludeMode::ArbInclude { warn } | IncludeMode::GoogleInclude { warn } => warn,
+            IncludeMode::Both {
+                arb_warn,
+                google_warn,
+            } => arb_warn || google_warn,
+        }
+    }
+
+    /// Return the warn flag of GL_ARB_shading_language_include, if it is enabled
+    fn arb(self) -> Option<bool> {
+        match self {
+            IncludeMode::ArbInclude { warn } => Some(warn),
+            IncludeMode::Both { arb_warn, .. } => Some(arb_warn),
+            _ => None,
+        }
+    }
+
+    /// Return the warn flag of GL_GOOGLE_include_directive, if it is enabled
+    fn google(self) -> Option<bool> {
+        match self {
+            IncludeMode::GoogleInclude { warn } => Some(warn),
+            IncludeMode::Both { google_warn, .. } => Some(google_warn),
+            _ => None,
+        }
+    }
+
+    /// Build the include mode from the state of both include extensions
+    fn from_extensions(arb: Option<bool>, google: Option<bool>) -> Self {
+        match (arb, google) {
+            (None, None) => IncludeMode::None,
+            (Some(warn), None) => IncludeMode::ArbInclude { warn },
+            (None, Some(warn)) => IncludeMode::GoogleInclude { warn },
+            (Some(arb_warn), Some(google_warn)) => IncludeMode::Both {
+                arb_warn,
+                google_warn,
+            },
         }
     }
 }
@@ -49,26 +100,538 @@ impl Default for IncludeMode {
     }
 }
 
+/// Accepted forms for the source string argument of #line directives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineDirectiveMode {
+    /// `#line 42 1`: the source string is an integer
+    Integer,
+    /// `#line 42 "file.glsl"`: the source string may also be a quoted path
+    /// (GL_GOOGLE_cpp_style_line_directive)
+    String,
+}
+
+impl Default for LineDirectiveMode {
+    fn default() -> Self {
+        Self::Integer
+    }
+}
+
+/// GLSL profile targeted by the processed source, as returned by [ProcessorState::current_version]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlslProfile {
+    /// Desktop GLSL core profile. Also used when no profile is specified.
+    Core,
+    /// Desktop GLSL compatibility profile
+    Compatibility,
+    /// OpenGL ES Shading Language
+    Es,
+}
+
+impl From<VersionProfile> for GlslProfile {
+    fn from(profile: VersionProfile) -> Self {
+        match profile {
+            VersionProfile::None | VersionProfile::Core => Self::Core,
+            VersionProfile::Compatibility => Self::Compatibility,
+            VersionProfile::Es => Self::Es,
+        }
+    }
+}
+
+/// Error returned when merging processor states with incompatible definitions
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("conflicting definitions for {}", names.join(", "))]
+pub struct MergeConflict {
+    /// Names of the macros which are defined differently in both states
+    pub names: Vec<SmolStr>,
+}
+
+/// Error returned when defining a macro through the [ProcessorState] API
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MacroDefinitionError {
+    /// The macro name is not a valid identifier
+    #[error("{name} is not a valid macro name")]
+    InvalidName {
+        /// Name of the macro that could not be defined
+        name: SmolStr,
+    },
+    /// The macro name starts with `GL_`, or is a built-in or otherwise protected definition
+    #[error("{name} is a protected macro name")]
+    ProtectedName {
+        /// Name of the macro that could not be defined
+        name: SmolStr,
+    },
+    /// The parameter list or the replacement list could not be parsed
+    #[error("invalid parameters or replacement list for macro {name}")]
+    InvalidDefinition {
+        /// Name of the macro that could not be defined
+        name: SmolStr,
+    },
+}
+
+/// Differences between two processor states, as returned by [ProcessorState::diff]
+///
+/// All lists are sorted by name.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct ProcessorStateDiff {
+    /// Macros which are only defined in the second state
+    pub added: Vec<SmolStr>,
+    /// Macros which are only defined in the first state
+    pub removed: Vec<SmolStr>,
+    /// Macros which are defined in both states with different replacement lists
+    pub changed: Vec<SmolStr>,
+    /// Extensions which are only active in the second state
+    pub extensions_added: Vec<ExtNameAtom>,
+    /// Extensions which are only active in the first state
+    pub extensions_removed: Vec<ExtNameAtom>,
+    /// Extensions which are active in both states with a different behavior
+    pub extensions_changed: Vec<ExtNameAtom>,
+}
+
+impl ProcessorStateDiff {
+    /// Return true if both states have the same definitions and active extensions
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.extensions_added.is_empty()
+            && self.extensions_removed.is_empty()
+            && self.extensions_changed.is_empty()
+    }
+}
+
 /// Current state of the preprocessor
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ProcessorState {
     include_mode: IncludeMode,
-    definitions: HashMap<SmolStr, Definition>,
+    definitions: AHashMap<SmolStr, Definition>,
     version: Version,
-    cpp_style_line: bool,
+    line_directive_mode: LineDirectiveMode,
+    extension_stack: Vec<Extension>,
+    dependency_registry: Option<Arc<ExtensionDependencyRegistry>>,
+    conflict_registry: Option<Arc<ExtensionConflictRegistry>>,
+    promotion_table: Option<Arc<ExtensionPromotionTable>>,
+    macro_events: bool,
+    extension_warnings: bool,
+    version_checks: bool,
+    max_output_tokens: usize,
 }
 
+// Processor states are shared between threads when processing files in parallel
+static_assertions::assert_impl_all!(ProcessorState: Send, Sync);
+
 impl ProcessorState {
     pub fn builder() -> ProcessorStateBuilder<'static> {
         ProcessorStateBuilder::default()
     }
 
-    fn get_definition(&self, name: &str) -> Option<&Definition> {
-        self.definitions.get(name)
+    /// Attach an extension dependency registry to this state
+    ///
+    /// Extensions enabled after the registry is attached also enable their transitive
+    /// dependencies.
+    pub fn with_dependency_registry(self, registry: Arc<ExtensionDependencyRegistry>) -> Self {
+        Self {
+            dependency_registry: Some(registry),
+            ..self
+        }
+    }
+
+    /// Attach an extension conflict registry to this state
+    ///
+    /// `#extension` directives which would enable an extension conflicting with an already
+    /// active one are reported as errors.
+    pub fn with_conflict_registry(self, registry: Arc<ExtensionConflictRegistry>) -> Self {
+        Self {
+            conflict_registry: Some(registry),
+            ..self
+        }
+    }
+
+    /// Attach an extension promotion table to this state
+    ///
+    /// `#extension` directives for extensions which are part of the current version are then
+    /// ignored and reported as redundant.
+    pub fn with_promotion_table(self, table: Arc<ExtensionPromotionTable>) -> Self {
+        Self {
+            promotion_table: Some(table),
+            ..self
+        }
+    }
+
+    /// Emit [Event::EnterMacro](event::Event::EnterMacro) and
+    /// [Event::ExitMacro](event::Event::ExitMacro) events around the tokens resulting from
+    /// macro invocations
+    pub fn with_macro_events(self, macro_events: bool) -> Self {
+        Self {
+            macro_events,
+            ..self
+        }
+    }
+
+    /// Report `#extension NAME : warn` directives as
+    /// [ExtensionWarn](event::ProcessingErrorKind::ExtensionWarn) errors
+    ///
+    /// The preprocessor does not know which constructs are gated by an extension, so the
+    /// warning is emitted once, at the directive itself.
+    pub fn with_extension_warnings(self, extension_warnings: bool) -> Self {
+        Self {
+            extension_warnings,
+            ..self
+        }
+    }
+
+    /// Report #version directives which are not the first thing in a file
+    /// ([VersionNotFirst](event::ProcessingErrorKind::VersionNotFirst)) or which occur more
+    /// than once ([DuplicateVersion](event::ProcessingErrorKind::DuplicateVersion))
+    pub fn with_version_checks(self, version_checks: bool) -> Self {
+        Self {
+            version_checks,
+            ..self
+        }
+    }
+
+    /// Set the maximum number of tokens an expansion may produce
+    ///
+    /// The limit applies to the total output, including the contents of included files. Once it
+    /// is reached, a [MacroExpansionLimit](event::ProcessingErrorKind::MacroExpansionLimit) error
+    /// is reported and macros are no longer expanded in the rest of the output. Defaults to
+    /// [DEFAULT_MAX_OUTPUT_TOKENS].
+    pub fn with_max_output_tokens(self, max_output_tokens: usize) -> Self {
+        Self {
+            max_output_tokens,
+            ..self
+        }
+    }
+
+    /// Return the version in which the given extension became core, if the current version
+    /// already includes it
+    pub fn promoted_version(&self, name: &ExtNameAtom) -> Option<u16> {
+        if self.version.profile == VersionProfile::Es {
+            return None;
+        }
+
+        self.promotion_table
+            .as_ref()?
+            .core_version(name)
+            .filter(|core_version| self.version.number >= *core_version)
+    }
+
+    /// Return the active extension which conflicts with the given extension, if any
+    pub fn conflicting_extension(&self, name: &ExtNameAtom) -> Option<&ExtNameAtom> {
+        let registry = self.conflict_registry.as_ref()?;
+
+        self.active_extensions()
+            .find_map(|extension| match &extension.name {
+                ExtensionName::Specific(other) if registry.conflicts(name, other) => Some(other),
+                _ => None,
+            })
+    }
+
+    /// Return the currently active extensions, in the order they were enabled
+    ///
+    /// If an extension was specified multiple times, only the last directive is taken into
+    /// account.
+    pub fn active_extensions(&self) -> impl Iterator<Item = &Extension> {
+        let mut active: Vec<&Extension> = Vec::new();
+
+        for extension in &self.extension_stack {
+            match &extension.name {
+                ExtensionName::All => {
+                    if extension.behavior == ExtensionBehavior::Disable {
+                        active.clear();
+                    }
+                }
+                ExtensionName::Specific(_) => {
+                    active.retain(|other| other.name != extension.name);
+
+                    if extension.behavior.is_active() {
+                        active.push(extension);
+                    }
+                }
+            }
+        }
+
+        active.into_iter()
+    }
+
+    /// Return the extensions currently marked as `require`, in the order they were required
+    ///
+    /// If an extension was specified multiple times, only the last directive is taken into
+    /// account.
+    pub fn required_extensions(&self) -> impl Iterator<Item = &ExtNameAtom> {
+        self.active_extensions()
+            .filter(|extension| extension.behavior == ExtensionBehavior::Require)
+            .filter_map(|extension| match &extension.name {
+                ExtensionName::Specific(name) => Some(name),
+                ExtensionName::All => None,
+            })
+    }
+
+    /// Return the `#extension` directives for the currently active extensions, in the order
+    /// they were enabled
+    pub fn extension_directives(&self) -> String {
+        self.active_extensions()
+            .map(Extension::to_directive_string)
+            .collect()
+    }
+
+    /// Return true if the given extension is currently active
+    pub fn is_extension_active(&self, name: &ExtNameAtom) -> bool {
+        for extension in self.extension_stack.iter().rev() {
+            match &extension.name {
+                ExtensionName::All if extension.behavior == ExtensionBehavior::Disable => {
+                    return false;
+                }
+                ExtensionName::All => {}
+                ExtensionName::Specific(other) => {
+                    if other == name {
+                        return extension.behavior.is_active();
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Combine this state with another independent state
+    ///
+    /// Definitions from both states are merged, and extensions from `other` are applied after
+    /// the ones from `self`. The highest version is kept, and include extensions enabled in
+    /// either state stay enabled.
+    /// Macros which are defined in both states with different replacement lists are reported
+    /// as a [MergeConflict].
+    pub fn merge(mut self, other: ProcessorState) -> Result<ProcessorState, MergeConflict> {
+        let mut names = Vec::new();
+
+        for (name, definition) in other.definitions {
+            match self.definitions.entry(name) {
+                std::collections::hash_map::Entry::Occupied(occupied) => {
+                    if !occupied.get().same_tokens(&definition) {
+                        names.push(occupied.key().clone());
+                    }
+                }
+                std::collections::hash_map::Entry::Vacant(vacant) => {
+                    vacant.insert(definition);
+                }
+            }
+        }
+
+        if !names.is_empty() {
+            names.sort();
+            return Err(MergeConflict { names });
+        }
+
+        self.extension_stack.extend(other.extension_stack);
+
+        if other.version.number > self.version.number {
+            self.version = other.version;
+        }
+
+        self.include_mode = IncludeMode::from_extensions(
+            self.include_mode.arb().or(other.include_mode.arb()),
+            self.include_mode.google().or(other.include_mode.google()),
+        );
+
+        if other.line_directive_mode == LineDirectiveMode::String {
+            self.line_directive_mode = LineDirectiveMode::String;
+        }
+        self.macro_events |= other.macro_events;
+        self.extension_warnings |= other.extension_warnings;
+        self.version_checks |= other.version_checks;
+        self.max_output_tokens = self.max_output_tokens.min(other.max_output_tokens);
+        self.dependency_registry = self.dependency_registry.or(other.dependency_registry);
+        self.conflict_registry = self.conflict_registry.or(other.conflict_registry);
+        self.promotion_table = self.promotion_table.or(other.promotion_table);
+
+        Ok(self)
+    }
+
+    /// Compute the definitions and extensions which changed between two states
+    pub fn diff(before: &ProcessorState, after: &ProcessorState) -> ProcessorStateDiff {
+        let mut diff = ProcessorStateDiff::default();
+
+        for (name, definition) in &after.definitions {
+            match before.definitions.get(name) {
+                Some(previous) if !previous.same_tokens(definition) => {
+                    diff.changed.push(name.clone())
+                }
+                Some(_) => {}
+                None => diff.added.push(name.clone()),
+            }
+        }
+
+        diff.removed.extend(
+            before
+                .definitions
+                .keys()
+                .filter(|name| !after.definitions.contains_key(*name))
+                .cloned(),
+        );
+
+        let extensions = |state: &ProcessorState| -> AHashMap<ExtNameAtom, ExtensionBehavior> {
+            state
+                .active_extensions()
+                .filter_map(|extension| match &extension.name {
+                    ExtensionName::Specific(name) => Some((name.clone(), extension.behavior)),
+                    ExtensionName::All => None,
+                })
+                .collect()
+        };
+
+        let (before_extensions, after_extensions) = (extensions(before), extensions(after));
+
+        for (name, behavior) in &after_extensions {
+            match before_extensions.get(name) {
+                Some(previous) if previous != behavior => {
+                    diff.extensions_changed.push(name.clone())
+                }
+                Some(_) => {}
+                None => diff.extensions_added.push(name.clone()),
+            }
+        }
+
+        diff.extensions_removed.extend(
+            before_extensions
+                .keys()
+                .filter(|name| !after_extensions.contains_key(*name))
+                .cloned(),
+        );
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.changed.sort();
+        diff.extensions_added.sort();
+        diff.extensions_removed.sort();
+        diff.extensions_changed.sort();
+
+        diff
+    }
+
+    /// Export the user definitions as name and replacement text pairs
+    ///
+    /// Function-like macros are keyed by their name followed by their parameter list
+    /// (`F(x, y)`), as in `-D` compiler options. Replacement lists are rebuilt by joining their
+    /// tokens with single spaces. Protected definitions are not exported.
+    pub fn into_defines_map(self) -> HashMap<SmolStr, String> {
+        self.definitions
+            .into_values()
+            .filter_map(|definition| match definition {
+                Definition::Regular(define, _) if !define.protected() => {
+                    Some((define.signature(), define.replacement_text()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Return the built-in definitions, keyed by name
+    ///
+    /// These are the protected definitions: `__LINE__`, `__FILE__`, `__VERSION__`, the profile
+    /// macros (e.g. `GL_core_profile`) and the extension macros, which cannot be redefined or
+    /// undefined.
+    pub fn predefined_macros_map(&self) -> HashMap<&str, &Definition> {
+        self.definitions
+            .values()
+            .filter(|definition| definition.protected())
+            .chain(Definition::builtins())
+            .map(|definition| (definition.name(), definition))
+            .collect()
+    }
+
+    /// Return the user definitions, keyed by name
+    ///
+    /// These are all the definitions which are not returned by
+    /// [ProcessorState::predefined_macros_map].
+    pub fn user_macros_map(&self) -> HashMap<&str, &Definition> {
+        self.definitions
+            .values()
+            .filter(|definition| !definition.protected())
+            .map(|definition| (definition.name(), definition))
+            .collect()
+    }
+
+    /// Remove all user definitions, leaving only the built-in ones
+    pub fn clear_user_macros(&mut self) {
+        self.definitions
+            .retain(|_, definition| definition.protected());
+    }
+
+    /// Return the definition `name` currently resolves to, including built-in macros
+    pub fn get_definition(&self, name: &str) -> Option<&Definition> {
+        Definition::lookup(name, &self.definitions)
+    }
+
+    /// Check that `name` can be defined through the [ProcessorState] API
+    fn check_macro_name(&self, name: &str) -> Result<(), MacroDefinitionError> {
+        if !nodes::is_identifier(name) {
+            Err(MacroDefinitionError::InvalidName { name: name.into() })
+        } else if name.starts_with("GL_")
+            || self
+                .get_definition(name)
+                .is_some_and(|definition| definition.protected())
+        {
+            Err(MacroDefinitionError::ProtectedName { name: name.into() })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Define a function-like macro, as `#define name(params...) body` would
+    ///
+    /// Returns an error if `name` is not a valid identifier, if it starts with `GL_` or is a
+    /// protected definition, or if the parameters or body cannot be parsed.
+    pub fn define_function(
+        &mut self,
+        name: &str,
+        params: &[&str],
+        body: &str,
+    ) -> Result<(), MacroDefinitionError> {
+        self.check_macro_name(name)?;
+
+        let function = DefineFunction::parse(params, body)
+            .ok_or_else(|| MacroDefinitionError::InvalidDefinition { name: name.into() })?;
+
+        self.definition(
+            Define::function(name.into(), function, false),
+            FileId::default(),
+        );
+
+        Ok(())
+    }
+
+    /// Define an object-like macro expanding to an integer, as `-DNAME=value` would
+    ///
+    /// The body is built directly as an integer constant token, without parsing any text. Returns
+    /// an error if `name` is not a valid identifier, or if it starts with `GL_` or is a protected
+    /// definition.
+    pub fn define_object_macro(
+        &mut self,
+        name: &str,
+        value: i64,
+    ) -> Result<(), MacroDefinitionError> {
+        self.check_macro_name(name)?;
+
+        self.definition(
+            Define::object(name.into(), DefineObject::from_int(value), false),
+            FileId::default(),
+        );
+
+        Ok(())
+    }
+
+    /// Define an object-like macro expanding to `1`, as `-DNAME` would
+    ///
+    /// See [ProcessorState::define_object_macro].
+    pub fn define_bool_macro(&mut self, name: &str) -> Result<(), MacroDefinitionError> {
+        self.define_object_macro(name, 1)
     }
 
     // TODO: Return a proper error type?
     pub fn definition(&mut self, definition: Define, file_id: FileId) -> bool {
+        if Definition::builtin(definition.name()).is_some() {
+            return false;
+        }
+
         let entry = self.definitions.entry(definition.name().into());
 
         match entry {
@@ -88,41 +651,57 @@ impl ProcessorState {
     }
 
     fn add_extension(&mut self, name: &ExtensionName, behavior: ExtensionBehavior) {
+        self.set_extension_behavior(name, behavior);
+
+        // Enable dependencies
+        if let (ExtensionName::Specific(name), Some(registry)) =
+            (name, self.dependency_registry.clone())
+        {
+            if matches!(
+                behavior,
+                ExtensionBehavior::Require | ExtensionBehavior::Enable
+            ) {
+                for dependency in registry.transitive_dependencies(name) {
+                    if !self.is_extension_active(&dependency) {
+                        self.set_extension_behavior(
+                            &ExtensionName::Specific(dependency),
+                            ExtensionBehavior::Enable,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_extension_behavior(&mut self, name: &ExtensionName, behavior: ExtensionBehavior) {
+        self.extension_stack.push(Extension {
+            name: name.clone(),
+            behavior,
+        });
+
         // Process include extensions
-        let target_include_mode = if *name == ext_name!("GL_ARB_shading_language_include") {
-            Some(IncludeMode::ArbInclude {
-                warn: behavior == ExtensionBehavior::Warn,
-            })
+        let target = behavior
+            .is_active()
+            .then_some(behavior == ExtensionBehavior::Warn);
+        if *name == ext_name!("GL_ARB_shading_language_include") {
+            self.include_mode = IncludeMode::from_extensions(target, self.include_mode.google());
         } else if *name == ext_name!("GL_GOOGLE_include_directive") {
-            Some(IncludeMode::GoogleInclude {
-                warn: behavior == ExtensionBehavior::Warn,
-            })
-        } else {
-            None
-        };
-
-        if let Some(target) = target_include_mode {
-            if behavior.is_active() {
-                self.include_mode = target;
+            self.include_mode = IncludeMode::from_extensions(self.include_mode.arb(), target);
 
-                // GL_GOOGLE_include_directive enable GL_GOOGLE_cpp_style_line
-                if let IncludeMode::GoogleInclude { .. } = target {
-                    self.cpp_style_line = true;
-                }
-            } else {
-                // TODO: Implement current mode as a stack?
-                self.include_mode = IncludeMode::None;
+            // GL_GOOGLE_include_directive enable GL_GOOGLE_cpp_style_line
+            if target.is_some() {
+                self.line_directive_mode = LineDirectiveMode::String;
             }
         }
 
         // Process others
         if *name == ext_name!("GL_GOOGLE_cpp_style_line_directive") {
             if behavior.is_active() {
-                self.cpp_style_line = true;
+                self.line_directive_mode = LineDirectiveMode::String;
             } else {
                 // TODO: Notify instead of silently ignoring?
-                if !matches!(self.include_mode, IncludeMode::GoogleInclude { .. }) {
-                    self.cpp_style_line = false;
+                if self.include_mode.google().is_none() {
+                    self.line_directive_mode = LineDirectiveMode::Integer;
                 }
             }
         }
@@ -132,8 +711,34 @@ impl ProcessorState {
         self.add_extension(&extension.name, extension.behavior);
     }
 
-    fn cpp_style_line(&self) -> bool {
-        self.cpp_style_line
+    /// Disable all extensions, as `#extension all : disable` would
+    pub fn disable_all_extensions(&mut self) {
+        self.add_extension(&ExtensionName::All, ExtensionBehavior::Disable);
+    }
+
+    /// Set the behavior of an extension, as `#extension name : behavior` would
+    ///
+    /// Returns false if the extension is not known to the default registry, in which case the
+    /// state is left unchanged.
+    pub fn enable_extension(&mut self, name: ExtNameAtom, behavior: ExtensionBehavior) -> bool {
+        if crate::exts::DEFAULT_REGISTRY.get(&name).is_none() {
+            return false;
+        }
+
+        self.add_extension(&ExtensionName::Specific(name), behavior);
+        true
+    }
+
+    /// Return the accepted forms for #line directives
+    pub fn line_directive_mode(&self) -> LineDirectiveMode {
+        self.line_directive_mode
+    }
+
+    /// Return the GLSL version number and profile selected by the last #version directive
+    ///
+    /// Without a #version directive, this is version 110 of the core profile.
+    pub fn current_version(&self) -> (u16, GlslProfile) {
+        (self.version.number, self.version.profile.into())
     }
 }
 
@@ -228,7 +833,6 @@ impl<'r> ProcessorStateBuilder<'r> {
                     )
                     .chain(self.definitions)
                     .map(|definition| Definition::Regular(definition.into(), FileId::default()))
-                    .chain([Definition::Line, Definition::File, Definition::Version])
                     .chain(self.registry.all().map(|spec| {
                         Definition::Regular(
                             Define::object(spec.name().as_ref().into(), one.clone(), true).into(),
@@ -238,7 +842,15 @@ impl<'r> ProcessorStateBuilder<'r> {
                     .map(|definition| (definition.name().into(), definition))
                     .collect(),
                 version: Version::default(),
-                cpp_style_line: false,
+                line_directive_mode: LineDirectiveMode::default(),
+                extension_stack: Vec::new(),
+                dependency_registry: None,
+                conflict_registry: None,
+                promotion_table: None,
+                macro_events: false,
+                extension_warnings: false,
+                version_checks: false,
+                max_output_tokens: DEFAULT_MAX_OUTPUT_TOKENS,
             };
 
         for (name, behavior) in self.extensions {
@@ -1,8 +1,9 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    rc::Rc,
+    collections::{HashSet, VecDeque},
+    sync::Arc,
 };
 
+use ahash::AHashMap;
 use itertools::Itertools;
 use rowan::NodeOrToken;
 
@@ -23,15 +24,79 @@ use super::{
     ProcessorState,
 };
 
+/// A macro definition, as resolved by the preprocessor
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Definition {
-    Regular(Rc<Define>, FileId),
+    /// User-defined macro, and the file it was defined in
+    Regular(Arc<Define>, FileId),
+    /// `__LINE__` built-in macro
     Line,
+    /// `__FILE__` built-in macro
     File,
+    /// `__VERSION__` built-in macro
     Version,
 }
 
+/// Definitions which are always present, regardless of the processor configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BuiltinDefinition {
+    Line,
+    File,
+    Version,
+}
+
+impl BuiltinDefinition {
+    fn definition(self) -> &'static Definition {
+        match self {
+            BuiltinDefinition::Line => &Definition::Line,
+            BuiltinDefinition::File => &Definition::File,
+            BuiltinDefinition::Version => &Definition::Version,
+        }
+    }
+}
+
+static BUILTIN_DEFINITIONS: phf::Map<&'static str, BuiltinDefinition> = phf::phf_map! {
+    "__LINE__" => BuiltinDefinition::Line,
+    "__FILE__" => BuiltinDefinition::File,
+    "__VERSION__" => BuiltinDefinition::Version,
+};
+
 impl Definition {
+    /// Return the built-in definition for `name`, if any
+    pub fn builtin(name: &str) -> Option<&'static Definition> {
+        BUILTIN_DEFINITIONS
+            .get(name)
+            .map(|builtin| builtin.definition())
+    }
+
+    /// Return the definitions which are always present: `__LINE__`, `__FILE__` and `__VERSION__`
+    pub(crate) fn builtins<'d>() -> impl Iterator<Item = &'d Definition> {
+        BUILTIN_DEFINITIONS
+            .values()
+            .map(|builtin| -> &'d Definition { builtin.definition() })
+    }
+
+    /// Return true if both definitions expand to the same tokens, regardless of where they were
+    /// defined
+    pub fn same_tokens(&self, other: &Definition) -> bool {
+        match (self, other) {
+            (Definition::Regular(this, _), Definition::Regular(other, _)) => {
+                this.same_tokens(other)
+            }
+            (this, other) => this == other,
+        }
+    }
+
+    /// Look up the definition for `name`, checking built-in definitions before user-defined
+    /// ones
+    pub(crate) fn lookup<'d>(
+        name: &str,
+        definitions: &'d AHashMap<SmolStr, Definition>,
+    ) -> Option<&'d Definition> {
+        Self::builtin(name).or_else(|| definitions.get(name))
+    }
+
+    /// Return the file this macro was defined in
     pub fn file_id(&self) -> FileId {
         match self {
             Definition::Regular(_, file_id) => *file_id,
@@ -39,6 +104,7 @@ impl Definition {
         }
     }
 
+    /// Return the name of this macro
     pub fn name(&self) -> &str {
         match self {
             Definition::Regular(d, _) => d.name(),
@@ -48,6 +114,7 @@ impl Definition {
         }
     }
 
+    /// Return true if this macro cannot be redefined or undefined
     pub fn protected(&self) -> bool {
         match self {
             Definition::Regular(d, _) => d.protected(),
@@ -57,6 +124,7 @@ impl Definition {
         }
     }
 
+    /// Return true if this is an object-like macro
     pub fn object_like(&self) -> bool {
         match self {
             Definition::Regular(d, _) => matches!(d.kind(), DefineKind::Object(_)),
@@ -66,6 +134,7 @@ impl Definition {
         }
     }
 
+    /// Return the number of arguments of this macro, 0 for object-like macros
     pub fn arg_count(&self) -> usize {
         match self {
             Definition::Regular(d, _) => match d.kind() {
@@ -93,7 +162,7 @@ impl Definition {
     fn concat_node_to_tokens<T: TokenLike>(
         definition_file_id: FileId,
         node: SyntaxNode,
-        args: Option<&HashMap<&str, &[T]>>,
+        args: Option<&AHashMap<&str, &[T]>>,
         entire_range: NodeSpan,
     ) -> impl IntoIterator<Item = OutputToken> {
         // Find the first non-trivial token
@@ -350,7 +419,7 @@ impl Definition {
         location: &ExpandLocation,
     ) -> Vec<Event> {
         // Put the arguments into a hashmap
-        let args: HashMap<_, _> = args
+        let args: AHashMap<_, _> = args
             .iter()
             .zip(function.arg_names())
             .map(|(tokens, arg_name)| {
@@ -537,6 +606,36 @@ pub(crate) fn trim_ws<T: TokenLike>(tokens: &[T]) -> &[T] {
     }
 }
 
+/// Number of tokens a macro substitution is still allowed to produce
+pub(crate) struct TokenBudget {
+    remaining: usize,
+    exceeded: bool,
+}
+
+impl TokenBudget {
+    pub fn new(remaining: usize) -> Self {
+        Self {
+            remaining,
+            exceeded: false,
+        }
+    }
+
+    /// true if the substitution was cut short because it ran out of tokens
+    pub fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    fn take(&mut self) -> bool {
+        if self.remaining == 0 {
+            self.exceeded = true;
+            false
+        } else {
+            self.remaining -= 1;
+            true
+        }
+    }
+}
+
 pub struct MacroInvocation<'d> {
     definition: &'d Definition,
     tokens: MacroCall,
@@ -713,13 +812,30 @@ impl<'d> MacroInvocation<'d> {
         )))
     }
 
+    pub fn definition(&self) -> &'d Definition {
+        self.definition
+    }
+
+    pub fn range(&self) -> NodeSpan {
+        self.range
+    }
+
     pub fn substitute_vec(
         current_state: &ProcessorState,
         tokens: Vec<impl TokenLike>,
         location: &ExpandLocation,
     ) -> Vec<Event> {
+        // Directive arguments are truncated if they expand past the token limit
         let mut subs_stack = HashSet::new();
-        Self::substitute_vec_inner(current_state, tokens, location, &mut subs_stack, None)
+        let mut budget = TokenBudget::new(current_state.max_output_tokens);
+        Self::substitute_vec_inner(
+            current_state,
+            tokens,
+            location,
+            &mut subs_stack,
+            &mut budget,
+            None,
+        )
     }
 
     fn substitute_vec_inner(
@@ -727,6 +843,7 @@ impl<'d> MacroInvocation<'d> {
         tokens: Vec<impl TokenLike>,
         location: &ExpandLocation,
         subs_stack: &mut HashSet<SmolStr>,
+        budget: &mut TokenBudget,
         range: Option<NodeSpan>,
     ) -> Vec<Event> {
         // Macros are recursive, so we need to scan again for further substitutions
@@ -735,6 +852,10 @@ impl<'d> MacroInvocation<'d> {
         let mut seen_defined_recently = false;
 
         while let Some(node_or_token) = iterator.next() {
+            if budget.exceeded() {
+                break;
+            }
+
             // Just a regular token
             match node_or_token {
                 NodeOrToken::Node(_) => unreachable!(),
@@ -753,7 +874,7 @@ impl<'d> MacroInvocation<'d> {
                             Some(ident)
                         }
                     })
-                    .and_then(|ident| current_state.definitions.get(ident.as_ref()))
+                    .and_then(|ident| current_state.get_definition(ident.as_ref()))
                     {
                         match MacroInvocation::parse_nested(
                             definition,
@@ -768,18 +889,21 @@ impl<'d> MacroInvocation<'d> {
                                     current_state,
                                     location,
                                     subs_stack,
+                                    budget,
                                 ));
 
                                 iterator = new_iterator;
                             }
                             Ok(None) => {
-                                result.push(Event::token(token, false));
+                                if budget.take() {
+                                    result.push(Event::token(token, false));
+                                }
                             }
                             Err(err) => {
                                 result.push(Event::map_error(err, false));
                             }
                         }
-                    } else {
+                    } else if budget.take() {
                         result.push(Event::token(token, false));
                     }
 
@@ -802,13 +926,15 @@ impl<'d> MacroInvocation<'d> {
         result
     }
 
+    /// Substitute this invocation, producing at most as many tokens as allowed by `budget`
     pub fn substitute(
         self,
         current_state: &ProcessorState,
         location: &ExpandLocation,
+        budget: &mut TokenBudget,
     ) -> Vec<Event> {
         let mut subs_stack = HashSet::new();
-        self.substitute_inner(current_state, location, &mut subs_stack)
+        self.substitute_inner(current_state, location, &mut subs_stack, budget)
     }
 
     fn substitute_inner(
@@ -816,6 +942,7 @@ impl<'d> MacroInvocation<'d> {
         current_state: &ProcessorState,
         location: &ExpandLocation,
         subs_stack: &mut HashSet<SmolStr>,
+        budget: &mut TokenBudget,
     ) -> Vec<Event> {
         let events = match self.tokens {
             MacroCall::Object => {
@@ -852,6 +979,7 @@ impl<'d> MacroInvocation<'d> {
                             .collect::<Vec<_>>(),
                         location,
                         subs_stack,
+                        budget,
                         range,
                     )
                 } else {
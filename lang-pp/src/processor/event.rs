@@ -53,6 +53,18 @@ pub enum ProcessingErrorKind {
         token: Option<SmolStr>,
     },
     CppStyleLineNotSupported,
+    ConflictingExtensions {
+        enabled: ExtNameAtom,
+        conflicting: ExtNameAtom,
+    },
+    ExtensionWarn {
+        name: ExtNameAtom,
+    },
+    VersionNotFirst,
+    DuplicateVersion,
+    MacroExpansionLimit {
+        limit: usize,
+    },
     DirectiveVersion(nodes::VersionError),
     DirectiveExtension(nodes::ExtensionError),
     DirectiveDefine(nodes::DefineError),
@@ -138,6 +150,32 @@ impl std::fmt::Display for ProcessingErrorKind {
             ProcessingErrorKind::CppStyleLineNotSupported => {
                 write!(f, "'#line' : required extension not requested: GL_GOOGLE_cpp_style_line_directive")
             }
+            ProcessingErrorKind::ConflictingExtensions {
+                enabled,
+                conflicting,
+            } => {
+                write!(
+                    f,
+                    "'#extension' : {} conflicts with already enabled extension {}",
+                    enabled, conflicting
+                )
+            }
+            ProcessingErrorKind::ExtensionWarn { name } => {
+                write!(f, "'#extension' : {} enabled with warn behavior", name)
+            }
+            ProcessingErrorKind::VersionNotFirst => {
+                write!(f, "'#version' : must occur first in shader")
+            }
+            ProcessingErrorKind::DuplicateVersion => {
+                write!(f, "'#version' : must occur only once")
+            }
+            ProcessingErrorKind::MacroExpansionLimit { limit } => {
+                write!(
+                    f,
+                    "'macro expansion' : output exceeds {} tokens, macro expansion disabled",
+                    limit
+                )
+            }
             ProcessingErrorKind::DirectiveVersion(inner) => {
                 write!(f, "'#version' : {}", inner)
             }
@@ -201,6 +239,10 @@ pub enum ErrorKind {
         raw_line: u32,
         pos: NodeSpan,
     },
+    ExtensionRedundant {
+        extension: ExtNameAtom,
+        core_version: u16,
+    },
 }
 
 impl std::error::Error for ErrorKind {}
@@ -214,6 +256,16 @@ impl std::fmt::Display for ErrorKind {
             ErrorKind::UnsupportedExt { extension, .. } => {
                 write!(f, "extension not supported: {}", extension)
             }
+            ErrorKind::ExtensionRedundant {
+                extension,
+                core_version,
+            } => {
+                write!(
+                    f,
+                    "'#extension' : {} is already part of the core language since version {}",
+                    extension, core_version
+                )
+            }
         }
     }
 }
@@ -302,6 +354,10 @@ impl TokenLike for (&parser::SyntaxToken, FileId) {
     }
 }
 
+/// Token emitted by the preprocessor
+///
+/// The token text is stored inline (or in a shared buffer for long tokens), so output tokens do
+/// not keep the syntax tree of the source file alive.
 #[derive(Clone, PartialEq, Eq)]
 pub struct OutputToken {
     kind: SyntaxKind,
@@ -341,6 +397,14 @@ impl OutputToken {
     pub fn text_range(&self) -> NodeSpan {
         self.source_range
     }
+
+    /// Return the file this token comes from
+    ///
+    /// Tokens produced by a macro expansion are attributed to the file containing the macro
+    /// invocation.
+    pub fn file_id(&self) -> FileId {
+        self.source_range.source_id()
+    }
 }
 
 impl From<(parser::SyntaxToken, FileId)> for OutputToken {
@@ -433,6 +497,15 @@ pub enum Event {
         file_id: FileId,
         path: PathBuf,
         canonical_path: PathBuf,
+        /// Include nesting depth, 0 for the top-level file
+        depth: u32,
+    },
+    /// End of a file, before resuming the including file if any
+    #[from(skip)]
+    ExitFile {
+        file_id: FileId,
+        /// Include nesting depth, matching the corresponding [Event::EnterFile]
+        depth: u32,
     },
     Token {
         token: OutputToken,
@@ -442,17 +515,40 @@ pub enum Event {
         directive: EventDirective,
         masked: bool,
     },
+    /// Start of the tokens resulting from a macro invocation
+    ///
+    /// Only emitted if enabled with [crate::processor::ProcessorState::with_macro_events].
+    #[from(skip)]
+    EnterMacro {
+        /// Name of the invoked macro
+        name: SmolStr,
+        /// Range of the macro invocation, including arguments
+        invocation_span: NodeSpan,
+        /// File which contains the macro definition
+        definition_file_id: FileId,
+    },
+    /// End of the tokens resulting from a macro invocation
+    #[from(skip)]
+    ExitMacro {
+        /// Name of the invoked macro
+        name: SmolStr,
+    },
 }
 
 impl Event {
-    pub fn enter_file(file_id: FileId) -> Self {
+    pub fn enter_file(file_id: FileId, depth: u32) -> Self {
         Self::EnterFile {
             file_id,
             path: Default::default(),
             canonical_path: Default::default(),
+            depth,
         }
     }
 
+    pub fn exit_file(file_id: FileId, depth: u32) -> Self {
+        Self::ExitFile { file_id, depth }
+    }
+
     pub fn token<T: TokenLike>(token: T, masked: bool) -> Self {
         Self::Token {
             token: OutputToken::from_token(&token),
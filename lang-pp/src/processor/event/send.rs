@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use rowan::TextRange;
 
-use lang_util::FileId;
+use lang_util::{position::NodeSpan, FileId, SmolStr};
 
 use super::{parser::SyntaxNode, Error, Event, OutputToken};
 
@@ -53,6 +53,11 @@ pub enum SendEvent {
         file_id: FileId,
         path: PathBuf,
         canonical_path: PathBuf,
+        depth: u32,
+    },
+    ExitFile {
+        file_id: FileId,
+        depth: u32,
     },
     Token {
         token: OutputToken,
@@ -63,6 +68,14 @@ pub enum SendEvent {
         masked: bool,
         errors: Vec<Error>,
     },
+    EnterMacro {
+        name: SmolStr,
+        invocation_span: NodeSpan,
+        definition_file_id: FileId,
+    },
+    ExitMacro {
+        name: SmolStr,
+    },
 }
 
 impl From<Event> for SendEvent {
@@ -73,17 +86,30 @@ impl From<Event> for SendEvent {
                 file_id,
                 path,
                 canonical_path,
+                depth,
             } => Self::EnterFile {
                 file_id,
                 path,
                 canonical_path,
+                depth,
             },
+            Event::ExitFile { file_id, depth } => Self::ExitFile { file_id, depth },
             Event::Token { token, masked } => Self::Token { token, masked },
             Event::Directive { directive, masked } => Self::Directive {
                 node: (&directive.node).into(),
                 masked,
                 errors: directive.errors,
             },
+            Event::EnterMacro {
+                name,
+                invocation_span,
+                definition_file_id,
+            } => Self::EnterMacro {
+                name,
+                invocation_span,
+                definition_file_id,
+            },
+            Event::ExitMacro { name } => Self::ExitMacro { name },
         }
     }
 }
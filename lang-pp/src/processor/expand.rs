@@ -11,23 +11,24 @@ use rowan::{NodeOrToken, SyntaxElementChildren, TextSize};
 use lang_util::{
     located::{HasFileNumber, Resolver},
     position::NodeSpan,
-    FileId,
+    FileId, SmolStr,
 };
 
 use crate::{
     parser::{self, Ast, PreprocessorLang, SyntaxKind::*, SyntaxNode, SyntaxToken},
-    types::path::ParsedPath,
+    types::path::{ParsedPath, PathType},
     util::{LineMap, Unescaped},
 };
 
 use super::{
-    definition::{Definition, MacroInvocation},
+    definition::{Definition, MacroInvocation, TokenBudget},
     event::{ErrorKind, Event, ProcessingErrorKind},
     nodes::{
         Define, Directive, DirectiveResult, Elif, Else, Empty, EndIf, Error as ErrorDirective,
-        Extension, If, IfDef, IfNDef, Include, Invalid, Line, ParsedLine, Pragma, Undef, Version,
+        Extension, ExtensionBehavior, ExtensionName, If, IfDef, IfNDef, Include, Invalid, Line,
+        ParsedLine, Pragma, Undef, Version,
     },
-    IncludeMode, ProcessorState,
+    IncludeMode, LineDirectiveMode, ProcessorState,
 };
 
 mod if_stack;
@@ -142,12 +143,54 @@ impl ExpandLocation {
 
         LocationString::Number(self.current_file.number())
     }
+
+    /// Return the file name set by the last `#line` directive, if it used a string argument
+    ///
+    /// String arguments are only accepted when `GL_GOOGLE_cpp_style_line_directive` (or
+    /// `GL_GOOGLE_include_directive`) is enabled. Subsequent `#line` directives which only
+    /// specify a line number keep the current file name.
+    pub fn current_file_name(&self) -> Option<&str> {
+        match &self.line_override {
+            Some((_, ParsedLine::LineAndPath(_, path))) => Some(path.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Number of tokens produced by an expansion
+///
+/// This is carried over from a file to the files it includes and back, so the output limit of
+/// [ProcessorState::with_max_output_tokens] applies to the total output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OutputCount {
+    /// Number of active tokens produced so far
+    tokens: usize,
+    /// true once the output token limit was reached, which disables macro expansion
+    limit_reached: bool,
+}
+
+/// Placement of the `#version` directive in an expansion
+///
+/// Like [OutputCount], this is carried over from a file to the files it includes and back, so a
+/// `#version` directive in an included file is checked against the whole preceding output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct VersionPlacement {
+    /// true once an active token or directive other than whitespace, comments or `#version`
+    /// was seen
+    first_token_seen: bool,
+    /// true once an active #version directive was seen
+    version_seen: bool,
 }
 
 pub(crate) struct ExpandOne {
     if_stack: IfStack,
     location: ExpandLocation,
     state: ExpandState,
+    depth: u32,
+    /// Placement of the #version directive, including in the enclosing and previous files
+    version: VersionPlacement,
+    /// Number of tokens produced so far, including by the enclosing and previously included files
+    output: OutputCount,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -167,6 +210,7 @@ enum ExpandState {
         current_state: ProcessorState,
         path: ParsedPath,
         node: SyntaxNode,
+        named_string: bool,
     },
     PendingOne {
         iterator: SyntaxElementChildren<PreprocessorLang>,
@@ -177,7 +221,9 @@ enum ExpandState {
     PendingEvents {
         iterator: SyntaxElementChildren<PreprocessorLang>,
         errors: Vec<parser::Error>,
-        events: ArrayVec<Event, 3>,
+        /// Events to return before resuming iteration. At most 2 events are queued here: the
+        /// error from an invalid macro invocation, followed by the identifier token itself.
+        events: ArrayVec<Event, 2>,
         current_state: ProcessorState,
     },
     ExpandedTokens {
@@ -186,6 +232,9 @@ enum ExpandState {
         events: VecDeque<Event>,
         current_state: ProcessorState,
     },
+    Exit {
+        current_state: ProcessorState,
+    },
     Complete,
 }
 
@@ -193,6 +242,8 @@ enum ExpandState {
 enum HandleNodeResult {
     Event(Event),
     EnterFile(Event, SyntaxNode, ParsedPath),
+    #[from(ignore)]
+    EnterNamedString(Event, SyntaxNode, ParsedPath),
 }
 
 impl ExpandOne {
@@ -203,9 +254,47 @@ impl ExpandOne {
             if_stack: IfStack::new(),
             location: ExpandLocation::new(file_id),
             state: ExpandState::Init { ast, current_state },
+            depth: 0,
+            version: VersionPlacement::default(),
+            output: OutputCount::default(),
         }
     }
 
+    /// Set the include nesting depth of the file being expanded
+    pub fn with_depth(self, depth: u32) -> Self {
+        Self { depth, ..self }
+    }
+
+    /// Set the number of tokens already produced before this file
+    pub fn with_output(self, output: OutputCount) -> Self {
+        Self { output, ..self }
+    }
+
+    /// Return the number of tokens produced so far
+    pub fn output(&self) -> OutputCount {
+        self.output
+    }
+
+    /// Update the number of tokens produced so far, e.g. after an included file was expanded
+    pub fn set_output(&mut self, output: OutputCount) {
+        self.output = output;
+    }
+
+    /// Set the placement of the #version directive before this file
+    pub fn with_version_placement(self, version: VersionPlacement) -> Self {
+        Self { version, ..self }
+    }
+
+    /// Return the placement of the #version directive so far
+    pub fn version_placement(&self) -> VersionPlacement {
+        self.version
+    }
+
+    /// Update the placement of the #version directive, e.g. after an included file was expanded
+    pub fn set_version_placement(&mut self, version: VersionPlacement) {
+        self.version = version;
+    }
+
     pub fn state(&self) -> Option<&ProcessorState> {
         match &self.state {
             ExpandState::Init { current_state, .. }
@@ -213,7 +302,8 @@ impl ExpandOne {
             | ExpandState::EnterNewFile { current_state, .. }
             | ExpandState::PendingOne { current_state, .. }
             | ExpandState::PendingEvents { current_state, .. }
-            | ExpandState::ExpandedTokens { current_state, .. } => Some(current_state),
+            | ExpandState::ExpandedTokens { current_state, .. }
+            | ExpandState::Exit { current_state } => Some(current_state),
             ExpandState::Complete => None,
         }
     }
@@ -225,7 +315,8 @@ impl ExpandOne {
             | ExpandState::EnterNewFile { current_state, .. }
             | ExpandState::PendingOne { current_state, .. }
             | ExpandState::PendingEvents { current_state, .. }
-            | ExpandState::ExpandedTokens { current_state, .. } => {
+            | ExpandState::ExpandedTokens { current_state, .. }
+            | ExpandState::Exit { current_state } => {
                 *current_state = new_state;
             }
             ExpandState::Complete => {
@@ -243,6 +334,11 @@ impl ExpandOne {
         current_state: &mut ProcessorState,
         node: SyntaxNode,
     ) -> HandleNodeResult {
+        // Only comments and whitespace may precede #version, not other directives
+        if node.kind() != PP_VERSION && self.if_stack.active() {
+            self.version.first_token_seen = true;
+        }
+
         match node.kind() {
             PP_EMPTY => Event::directive(
                 Directive::new(self.location.current_file(), node, Empty),
@@ -255,12 +351,26 @@ impl ExpandOne {
 
                 match directive {
                     Ok(directive) => {
-                        // TODO: Check that the version is the first thing in the file?
-                        if active {
+                        let error = if active {
                             current_state.version = *directive;
-                        }
 
-                        Event::directive(directive, !active)
+                            let error = if !current_state.version_checks {
+                                None
+                            } else if self.version.version_seen {
+                                Some(ProcessingErrorKind::DuplicateVersion)
+                            } else if self.version.first_token_seen {
+                                Some(ProcessingErrorKind::VersionNotFirst)
+                            } else {
+                                None
+                            };
+
+                            self.version.version_seen = true;
+                            error
+                        } else {
+                            None
+                        };
+
+                        Event::directive_errors(directive, !active, error, &self.location)
                     }
                     Err(error) => Event::directive_error(error, &self.location, !active),
                 }
@@ -272,11 +382,55 @@ impl ExpandOne {
 
                 match directive {
                     Ok(directive) => {
-                        if active {
-                            current_state.extension(&directive);
-                        }
+                        let error = if active {
+                            let error = match &directive.name {
+                                ExtensionName::Specific(name) if directive.behavior.is_active() => {
+                                    if let Some(core_version) = current_state.promoted_version(name)
+                                    {
+                                        Some(ErrorKind::ExtensionRedundant {
+                                            extension: name.clone(),
+                                            core_version,
+                                        })
+                                    } else {
+                                        current_state.conflicting_extension(name).map(
+                                            |conflicting| {
+                                                ProcessingErrorKind::ConflictingExtensions {
+                                                    enabled: name.clone(),
+                                                    conflicting: conflicting.clone(),
+                                                }
+                                                .into()
+                                            },
+                                        )
+                                    }
+                                }
+                                _ => None,
+                            };
+
+                            if error.is_none() {
+                                current_state.extension(&directive);
+
+                                match &directive.name {
+                                    ExtensionName::Specific(name)
+                                        if directive.behavior == ExtensionBehavior::Warn
+                                            && current_state.extension_warnings =>
+                                    {
+                                        Some(
+                                            ProcessingErrorKind::ExtensionWarn {
+                                                name: name.clone(),
+                                            }
+                                            .into(),
+                                        )
+                                    }
+                                    _ => None,
+                                }
+                            } else {
+                                error
+                            }
+                        } else {
+                            None
+                        };
 
-                        Event::directive(directive, !active)
+                        Event::directive_errors(directive, !active, error, &self.location)
                     }
                     Err(error) => Event::directive_error(error, &self.location, !active),
                 }
@@ -289,7 +443,9 @@ impl ExpandOne {
                 match directive {
                     Ok(define) => {
                         let error = if active {
-                            if define.name().starts_with("GL_") {
+                            if define.name().starts_with("GL_")
+                                || Definition::builtin(define.name()).is_some()
+                            {
                                 Some(ProcessingErrorKind::ProtectedDefine {
                                     ident: define.name().into(),
                                     is_undef: false,
@@ -336,7 +492,7 @@ impl ExpandOne {
 
                 let (result, ret) = match directive {
                     Ok(ifdef) => {
-                        let is_defined = current_state.definitions.contains_key(&ifdef.ident);
+                        let is_defined = current_state.get_definition(&ifdef.ident).is_some();
                         (is_defined, Event::directive(ifdef, !active))
                     }
                     Err(error) => (true, Event::directive_error(error, &self.location, !active)),
@@ -353,7 +509,7 @@ impl ExpandOne {
                 let (result, ret) = match directive {
                     Ok(ifndef) => {
                         // Update masking state
-                        let is_defined = current_state.definitions.contains_key(&ifndef.ident);
+                        let is_defined = current_state.get_definition(&ifndef.ident).is_some();
                         (!is_defined, Event::directive(ifndef, !active))
                     }
                     Err((error, node)) => (
@@ -476,7 +632,7 @@ impl ExpandOne {
                         let protected_ident = if active {
                             if undef.ident.starts_with("GL_") {
                                 Some(undef.ident.clone())
-                            } else if let Some(def) = current_state.definitions.get(&undef.ident) {
+                            } else if let Some(def) = current_state.get_definition(&undef.ident) {
                                 if def.protected() {
                                     Some(undef.ident.clone())
                                 } else {
@@ -546,7 +702,23 @@ impl ExpandOne {
 
                 match directive {
                     Ok(include) => {
-                        let error = match (path, current_state.include_mode) {
+                        // With both include extensions enabled, the path style selects the
+                        // include kind
+                        let include_mode = match (&path, current_state.include_mode) {
+                            (
+                                Some(path),
+                                IncludeMode::Both {
+                                    arb_warn,
+                                    google_warn,
+                                },
+                            ) => match path.ty {
+                                PathType::Quote => IncludeMode::GoogleInclude { warn: google_warn },
+                                PathType::Angle => IncludeMode::ArbInclude { warn: arb_warn },
+                            },
+                            (_, other) => other,
+                        };
+
+                        let error = match (path, include_mode) {
                             (_, IncludeMode::None) if active => {
                                 // No include mode requested, thus we are not expecting include
                                 // directives and this is a parsing error
@@ -580,6 +752,35 @@ impl ExpandOne {
                                     path,
                                 );
                             }
+                            (Some(path), IncludeMode::ArbInclude { warn })
+                                if active && path.ty == PathType::Angle =>
+                            {
+                                // Named string include, resolved by the processor if the name
+                                // is registered, left to the run-time otherwise
+                                let node = include.node().clone();
+                                return HandleNodeResult::EnterNamedString(
+                                    Event::directive_errors(
+                                        include,
+                                        !active,
+                                        if warn {
+                                            Some(ErrorKind::warn_ext_use(
+                                                ext_name!("GL_ARB_shading_language_include"),
+                                                None,
+                                                NodeSpan::new(
+                                                    self.location.current_file(),
+                                                    node.text_range(),
+                                                ),
+                                                &self.location,
+                                            ))
+                                        } else {
+                                            None
+                                        },
+                                        &self.location,
+                                    ),
+                                    node,
+                                    path,
+                                );
+                            }
                             // Run-time ArbInclude or inactive if group
                             (_, other) => {
                                 if other.warn() {
@@ -655,15 +856,15 @@ impl ExpandOne {
                                     ParsedLine::Line(_) | ParsedLine::LineAndFileNumber(_, _) => {
                                         (line, None)
                                     }
-                                    ParsedLine::LineAndPath(_, _)
-                                        if current_state.cpp_style_line() =>
-                                    {
-                                        (line, None)
+                                    ParsedLine::LineAndPath(line_number, _) => {
+                                        match current_state.line_directive_mode() {
+                                            LineDirectiveMode::String => (line, None),
+                                            LineDirectiveMode::Integer => (
+                                                ParsedLine::Line(line_number),
+                                                Some(ProcessingErrorKind::CppStyleLineNotSupported),
+                                            ),
+                                        }
                                     }
-                                    ParsedLine::LineAndPath(line, _) => (
-                                        ParsedLine::Line(line),
-                                        Some(ProcessingErrorKind::CppStyleLineNotSupported),
-                                    ),
                                 };
 
                                 self.location
@@ -706,6 +907,7 @@ impl ExpandOne {
         .into()
     }
 
+    #[inline]
     fn handle_token(
         &mut self,
         current_state: ProcessorState,
@@ -713,13 +915,15 @@ impl ExpandOne {
         iterator: SyntaxElementChildren<PreprocessorLang>,
         errors: Vec<parser::Error>,
     ) -> Option<Event> {
-        // Look for macro substitutions unless the current group is masked
-        if let Some(definition) = (if self.if_stack.active() && token.kind() == IDENT_KW {
-            Some(Unescaped::new(token.text()).to_string())
-        } else {
-            None
-        })
-        .and_then(|ident| current_state.definitions.get(ident.as_ref()))
+        // Look for macro substitutions unless the current group is masked, or the output token
+        // limit has been reached
+        if let Some(definition) =
+            (if self.if_stack.active() && !self.output.limit_reached && token.kind() == IDENT_KW {
+                Some(Unescaped::new(token.text()).to_string())
+            } else {
+                None
+            })
+            .and_then(|ident| current_state.get_definition(ident.as_ref()))
         {
             // We matched a defined identifier
 
@@ -731,10 +935,64 @@ impl ExpandOne {
             ) {
                 Ok(Some((invocation, new_iterator))) => {
                     // We successfully parsed a macro invocation
+                    let name = SmolStr::from(invocation.definition().name());
+                    let invocation_span = invocation.range();
+                    let definition_file_id = invocation.definition().file_id();
+
+                    let mut budget = TokenBudget::new(
+                        current_state
+                            .max_output_tokens
+                            .saturating_sub(self.output.tokens),
+                    );
+                    let substituted =
+                        invocation.substitute(&current_state, &self.location, &mut budget);
+
+                    if budget.exceeded() {
+                        // Report the error once, and pass the invocation through unexpanded
+                        self.output.limit_reached = true;
+
+                        let mut events = ArrayVec::new();
+                        events.push(Event::error(
+                            ProcessingErrorKind::MacroExpansionLimit {
+                                limit: current_state.max_output_tokens,
+                            },
+                            token.text_range(),
+                            &self.location,
+                            false,
+                        ));
+                        events.push(Event::token((token, self.location.current_file()), false));
+
+                        self.state = ExpandState::PendingEvents {
+                            iterator,
+                            errors,
+                            events,
+                            current_state,
+                        };
+
+                        return None;
+                    }
+
+                    self.output.tokens += substituted.iter().filter(|e| e.is_token()).count();
+
+                    let events = if current_state.macro_events {
+                        let enter = Event::EnterMacro {
+                            name: name.clone(),
+                            invocation_span,
+                            definition_file_id,
+                        };
+
+                        std::iter::once(enter)
+                            .chain(substituted)
+                            .chain(std::iter::once(Event::ExitMacro { name }))
+                            .collect()
+                    } else {
+                        substituted.into()
+                    };
+
                     self.state = ExpandState::ExpandedTokens {
                         iterator: new_iterator,
                         errors,
-                        events: invocation.substitute(&current_state, &self.location).into(),
+                        events,
                         current_state,
                     };
                 }
@@ -747,6 +1005,8 @@ impl ExpandOne {
                         current_state,
                     };
 
+                    self.output.tokens += 1;
+
                     return Some(Event::token((token, self.location.current_file()), false));
                 }
                 Err(err) => {
@@ -773,6 +1033,10 @@ impl ExpandOne {
                 current_state,
             };
 
+            if self.if_stack.active() {
+                self.output.tokens += 1;
+            }
+
             Some(Event::token(
                 (token, self.location.current_file()),
                 !self.if_stack.active(),
@@ -780,6 +1044,36 @@ impl ExpandOne {
         }
     }
 
+    /// Return the pending parser error located before `node_or_token`, and process
+    /// `node_or_token` on the next iteration
+    #[cold]
+    fn parser_error(
+        &mut self,
+        iterator: SyntaxElementChildren<PreprocessorLang>,
+        mut errors: Vec<parser::Error>,
+        node_or_token: NodeOrToken<SyntaxNode, SyntaxToken>,
+        current_state: ProcessorState,
+    ) -> ExpandEvent {
+        let error = errors.pop().unwrap();
+        let pos = node_or_token.text_range();
+
+        self.state = ExpandState::PendingOne {
+            iterator,
+            errors,
+            node_or_token,
+            current_state,
+        };
+
+        Event::error(
+            error.into_inner(),
+            pos,
+            &self.location,
+            !self.if_stack.active(),
+        )
+        .into()
+    }
+
+    #[inline]
     fn handle_node_or_token(
         &mut self,
         mut current_state: ProcessorState,
@@ -806,12 +1100,30 @@ impl ExpandOne {
                         current_state,
                         path,
                         node,
+                        named_string: false,
+                    };
+
+                    Some(event)
+                }
+
+                HandleNodeResult::EnterNamedString(event, node, path) => {
+                    self.state = ExpandState::EnterNewFile {
+                        iterator,
+                        errors,
+                        current_state,
+                        path,
+                        node,
+                        named_string: true,
                     };
 
                     Some(event)
                 }
             },
             rowan::NodeOrToken::Token(token) => {
+                if !token.kind().is_whitespace() && self.if_stack.active() {
+                    self.version.first_token_seen = true;
+                }
+
                 self.handle_token(current_state, token, iterator, errors)
             }
         }
@@ -821,6 +1133,9 @@ impl ExpandOne {
 pub(crate) enum ExpandEvent {
     Event(Event),
     EnterFile(SyntaxNode, ParsedPath),
+    /// An `#include <name>` directive in `GL_ARB_shading_language_include` mode. The directive
+    /// has already been forwarded, so unknown names can be left to the run-time.
+    EnterNamedString(SyntaxNode, ParsedPath),
     Completed(ProcessorState),
 }
 
@@ -848,35 +1163,22 @@ impl Iterator for ExpandOne {
                         current_state,
                     };
 
-                    return Some(Event::enter_file(self.location.current_file).into());
+                    return Some(Event::enter_file(self.location.current_file, self.depth).into());
                 }
                 ExpandState::Iterate {
                     mut iterator,
-                    mut errors,
+                    errors,
                     current_state,
                 } => {
                     if let Some(node_or_token) = iterator.next() {
                         if let Some(first) = errors.first() {
                             if node_or_token.text_range().end() >= first.pos().start() {
-                                let error = errors.pop().unwrap();
-                                let pos = node_or_token.text_range();
-
-                                self.state = ExpandState::PendingOne {
+                                return Some(self.parser_error(
                                     iterator,
                                     errors,
                                     node_or_token,
                                     current_state,
-                                };
-
-                                return Some(
-                                    Event::error(
-                                        error.into_inner(),
-                                        pos,
-                                        &self.location,
-                                        !self.if_stack.active(),
-                                    )
-                                    .into(),
-                                );
+                                ));
                             }
                         }
 
@@ -889,8 +1191,13 @@ impl Iterator for ExpandOne {
                             return Some(result.into());
                         }
                     } else {
-                        // Iteration completed, return the updated state
-                        return Some(ExpandEvent::Completed(current_state));
+                        // Iteration completed, notify the end of the file before returning the
+                        // updated state
+                        self.state = ExpandState::Exit { current_state };
+
+                        return Some(
+                            Event::exit_file(self.location.current_file, self.depth).into(),
+                        );
                     }
                 }
 
@@ -900,6 +1207,7 @@ impl Iterator for ExpandOne {
                     current_state,
                     path,
                     node,
+                    named_string,
                 } => {
                     self.state = ExpandState::Iterate {
                         iterator,
@@ -907,7 +1215,11 @@ impl Iterator for ExpandOne {
                         current_state,
                     };
 
-                    return Some(ExpandEvent::EnterFile(node, path));
+                    return Some(if named_string {
+                        ExpandEvent::EnterNamedString(node, path)
+                    } else {
+                        ExpandEvent::EnterFile(node, path)
+                    });
                 }
 
                 ExpandState::PendingOne {
@@ -928,7 +1240,7 @@ impl Iterator for ExpandOne {
                     mut events,
                     current_state,
                 } => {
-                    if let Some(event) = events.swap_pop(0) {
+                    if let Some(event) = events.pop_at(0) {
                         self.state = ExpandState::PendingEvents {
                             iterator,
                             errors,
@@ -970,6 +1282,10 @@ impl Iterator for ExpandOne {
                     }
                 }
 
+                ExpandState::Exit { current_state } => {
+                    return Some(ExpandEvent::Completed(current_state));
+                }
+
                 ExpandState::Complete => {
                     return None;
                 }
@@ -141,7 +141,7 @@ impl IfStack {
         if top.else_seen() {
             // If that's the case, just ignore the next block
             self.stack.push(IfState::One { else_seen: true });
-            return Err(IfError::ExtraElif);
+            return Err(IfError::ExtraElse);
         }
 
         // The next block will be active if no other block before was active
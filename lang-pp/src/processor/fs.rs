@@ -1,13 +1,15 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, VecDeque},
     path::{Path, PathBuf},
 };
 
+use ahash::AHashMap;
 use bimap::BiHashMap;
 
 use lang_util::{
+    error::MultiError,
     located::{FileIdResolver, Located, LocatedBuilder},
-    FileId,
+    FileId, FileIdNamespace,
 };
 
 use crate::{
@@ -17,17 +19,119 @@ use crate::{
 };
 
 use super::{
-    event::{Event, ProcessingErrorKind},
+    event::{DirectiveKind, Error, Event, ProcessingErrorKind},
     expand::{ExpandEvent, ExpandOne},
+    nodes::ParsedPragma,
     ProcessorState,
 };
 
+mod caching;
+pub use caching::*;
+
+#[cfg(feature = "wasm")]
+mod fetch;
+#[cfg(feature = "wasm")]
+pub use fetch::*;
+
+mod normalizing;
+pub use normalizing::*;
+
+mod overlay;
+pub use overlay::*;
+
+mod remap;
+pub use remap::*;
+
+mod sandbox;
+pub use sandbox::*;
+
+mod search_path;
+pub use search_path::*;
+
+mod shared_cache;
+pub use shared_cache::*;
+
+mod stats;
+pub use stats::*;
+
+#[cfg(test)]
+mod tests;
+
+/// Classification of [FileSystem] errors, as returned by [FileSystem::error_kind]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSystemErrorKind {
+    /// The requested file does not exist. Looking it up somewhere else may succeed.
+    NotFound,
+    /// Access to the requested file was denied
+    PermissionDenied,
+    /// The file exists but its contents could not be decoded
+    Encoding,
+    /// Any other error, e.g. a transient IO error
+    Other,
+}
+
 pub trait FileSystem {
     type Error: std::error::Error + 'static;
 
     fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error>;
     fn exists(&self, path: &Path) -> bool;
     fn read(&self, path: &Path) -> Result<std::borrow::Cow<'_, str>, Self::Error>;
+
+    /// Return true if the given error indicates that the requested file does not exist
+    ///
+    /// This is used by filesystem wrappers to distinguish missing files from other IO errors.
+    fn is_not_found(&self, _error: &Self::Error) -> bool {
+        false
+    }
+
+    /// Classify the given error
+    ///
+    /// The default implementation only detects missing files, using [FileSystem::is_not_found].
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        if self.is_not_found(error) {
+            FileSystemErrorKind::NotFound
+        } else {
+            FileSystemErrorKind::Other
+        }
+    }
+
+    /// Resolve an #include path using filesystem-specific rules
+    ///
+    /// This is called by the [Processor] when an #include path could not be found relative to the
+    /// including file (for `"quoted"` paths) or in the processor's system paths (for `<angled>`
+    /// paths).
+    fn resolve_include(&self, _path: &ParsedPath) -> Option<PathBuf> {
+        None
+    }
+
+    /// Read the file at the given path, replacing CRLF line endings with LF
+    fn read_normalized(&self, path: &Path) -> Result<std::borrow::Cow<'_, str>, Self::Error> {
+        let content = self.read(path)?;
+
+        if content.contains("\r\n") {
+            Ok(content.replace("\r\n", "\n").into())
+        } else {
+            Ok(content)
+        }
+    }
+
+    /// Read the file at the given path, removing the leading UTF-8 byte order mark if any
+    fn read_stripping_bom(&self, path: &Path) -> Result<std::borrow::Cow<'_, str>, Self::Error> {
+        const BOM: char = '\u{feff}';
+
+        Ok(match self.read(path)? {
+            std::borrow::Cow::Borrowed(content) => {
+                content.strip_prefix(BOM).unwrap_or(content).into()
+            }
+            std::borrow::Cow::Owned(mut content) => {
+                if content.starts_with(BOM) {
+                    content.drain(..BOM.len_utf8());
+                }
+
+                content.into()
+            }
+        })
+    }
 }
 
 #[derive(Default, Debug, Clone, Copy)]
@@ -47,14 +151,69 @@ impl FileSystem for Std {
     fn read(&self, path: &Path) -> Result<std::borrow::Cow<'_, str>, Self::Error> {
         std::fs::read_to_string(path).map(Into::into)
     }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        error.kind() == std::io::ErrorKind::NotFound
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => FileSystemErrorKind::NotFound,
+            std::io::ErrorKind::PermissionDenied => FileSystemErrorKind::PermissionDenied,
+            // Returned by read_to_string for non UTF-8 contents
+            std::io::ErrorKind::InvalidData => FileSystemErrorKind::Encoding,
+            _ => FileSystemErrorKind::Other,
+        }
+    }
 }
 
 pub type StdProcessor = Processor<Std>;
 
+/// Set of the files containing an active `#pragma once` directive which have been entered
+///
+/// Files are identified by their contents, so they are only included once even if they are
+/// reachable through different paths. Contents are bucketed by hash and compared in full, so
+/// unrelated files are never confused by a hash collision.
+#[derive(Default)]
+struct PragmaOnce {
+    files: AHashMap<u64, Vec<Ast>>,
+}
+
+impl PragmaOnce {
+    /// Return true if a file with the same contents as `ast` was marked with `#pragma once`,
+    /// i.e. if it should be skipped
+    fn contains(&self, ast: &Ast) -> bool {
+        self.contains_hashed(ast.content_hash(), ast)
+    }
+
+    fn contains_hashed(&self, hash: u64, ast: &Ast) -> bool {
+        self.files
+            .get(&hash)
+            .is_some_and(|files| files.iter().any(|file| file.same_content(ast)))
+    }
+
+    /// Record that the file `ast` contains an active `#pragma once` directive
+    fn insert(&mut self, ast: &Ast) {
+        self.insert_hashed(ast.content_hash(), ast)
+    }
+
+    fn insert_hashed(&mut self, hash: u64, ast: &Ast) {
+        if !self.contains_hashed(hash, ast) {
+            self.files.entry(hash).or_default().push(ast.clone());
+        }
+    }
+}
+
 pub struct ExpandStack<'p, F: FileSystem> {
     processor: &'p mut Processor<F>,
     stack: Vec<ExpandOne>,
     state: Option<ProcessorState>,
+    /// Files containing an active `#pragma once` directive which have been entered
+    once: PragmaOnce,
+    /// Files currently being expanded, outermost first
+    include_stack: Vec<(FileId, PathBuf)>,
+    /// Entry files to expand after the current one, see [Processor::process_multiple]
+    pending: VecDeque<FileId>,
 }
 
 impl<'p, F: FileSystem> ExpandStack<'p, F> {
@@ -67,9 +226,20 @@ impl<'p, F: FileSystem> ExpandStack<'p, F> {
         crate::last::Tokenizer::new(self, current_version, target_vulkan, registry)
     }
 
+    /// Return the final processor state, once the expansion has run to completion
     pub fn into_state(self) -> Option<ProcessorState> {
         self.state
     }
+
+    /// Return the chain of files currently being expanded, from the top-level file to the
+    /// innermost include
+    ///
+    /// The stack is updated as [Event::EnterFile] and [Event::ExitFile] events are returned, so
+    /// inspecting it when receiving an error gives the include chain that led to it. Source
+    /// blocks are reported with an empty path.
+    pub fn include_stack(&self) -> &[(FileId, PathBuf)] {
+        &self.include_stack
+    }
 }
 
 impl<'p, F: FileSystem> Iterator for ExpandStack<'p, F> {
@@ -87,29 +257,60 @@ impl<'p, F: FileSystem> Iterator for ExpandStack<'p, F> {
                             self.stack.push(expand);
 
                             return Some(match event {
-                                Event::EnterFile { file_id, .. } => {
+                                Event::EnterFile { file_id, depth, .. } => {
                                     if let Some((canonical_path, input_path)) =
                                         self.processor.get_paths(file_id)
                                     {
+                                        self.include_stack.push((file_id, input_path.to_owned()));
+
                                         Ok(Event::EnterFile {
                                             file_id,
                                             path: input_path.to_owned(),
                                             canonical_path: canonical_path.to_owned(),
+                                            depth,
                                         })
                                     } else {
                                         // Source block, no file path available
-                                        Ok(Event::EnterFile {
-                                            file_id,
-                                            path: Default::default(),
-                                            canonical_path: Default::default(),
-                                        })
+                                        self.include_stack.push((file_id, PathBuf::new()));
+
+                                        Ok(Event::enter_file(file_id, depth))
+                                    }
+                                }
+                                Event::ExitFile { file_id, depth } => {
+                                    self.include_stack.pop();
+                                    Ok(Event::ExitFile { file_id, depth })
+                                }
+                                Event::Directive {
+                                    directive,
+                                    masked: false,
+                                } if matches!(
+                                    directive.kind(),
+                                    DirectiveKind::Pragma(pragma)
+                                        if matches!(pragma.value(), ParsedPragma::Once)
+                                ) =>
+                                {
+                                    // Only active directives count, so #pragma once in a masked
+                                    // conditional group does not make the file include-once
+                                    if let Some(ast) = self
+                                        .processor
+                                        .file_cache
+                                        .get(&directive.text_range().source_id())
+                                    {
+                                        self.once.insert(ast);
                                     }
+
+                                    Ok(Event::Directive {
+                                        directive,
+                                        masked: false,
+                                    })
                                 }
                                 other => Ok(other),
                             });
                         }
                         ExpandEvent::EnterFile(node, path) => {
                             let state = expand.state().unwrap().clone();
+                            let output = expand.output();
+                            let version = expand.version_placement();
 
                             // Put it back on the stack
                             self.stack.push(expand);
@@ -119,14 +320,60 @@ impl<'p, F: FileSystem> Iterator for ExpandStack<'p, F> {
 
                             // We are supposed to enter a new file
                             // First, parse it using the preprocessor
-                            if let Some(resolved_path) = self
+                            if let Some(mut resolved_path) = self
                                 .processor
                                 .resolve_relative_to_id(location.current_file(), &path)
                             {
                                 // TODO: Allow passing an encoding from somewhere
-                                match self.processor.parse(&resolved_path) {
-                                    Ok(parsed) => {
-                                        self.stack.push(parsed.expand_one(state));
+                                let mut result = self
+                                    .processor
+                                    .parse(&resolved_path)
+                                    .map(|parsed| parsed.file_id());
+
+                                // The file may have disappeared or been reported as existing
+                                // while it can't be read: give the filesystem a chance to find
+                                // it elsewhere
+                                if let Err(error) = &result {
+                                    if self.processor.error_kind(error)
+                                        == FileSystemErrorKind::NotFound
+                                    {
+                                        if let Some(alternate_path) = self
+                                            .processor
+                                            .fs
+                                            .resolve_include(&path)
+                                            .filter(|alternate| *alternate != resolved_path)
+                                        {
+                                            result = self
+                                                .processor
+                                                .parse(&alternate_path)
+                                                .map(|parsed| parsed.file_id());
+                                            resolved_path = alternate_path;
+                                        }
+                                    }
+                                }
+
+                                match result {
+                                    Ok(file_id) => {
+                                        let parsed = ParsedFile {
+                                            processor: &mut *self.processor,
+                                            file_id,
+                                        };
+
+                                        // Files are identified by their contents for #pragma
+                                        // once, so they are only included once even if they are
+                                        // reachable through different paths
+                                        if self.once.contains(&parsed.ast()) {
+                                            continue;
+                                        }
+
+                                        let depth = self.stack.len() as u32;
+                                        self.stack.push(
+                                            parsed
+                                                .expand_one(state)
+                                                .with_depth(depth)
+                                                .with_output(output)
+                                                .with_version_placement(version),
+                                        );
                                     }
                                     Err(error) => {
                                         // Just return the error, we'll keep iterating on the lower
@@ -149,10 +396,59 @@ impl<'p, F: FileSystem> Iterator for ExpandStack<'p, F> {
                                 )));
                             }
                         }
+                        ExpandEvent::EnterNamedString(_node, path) => {
+                            let state = expand.state().unwrap().clone();
+                            let output = expand.output();
+                            let version = expand.version_placement();
+
+                            // Put it back on the stack
+                            self.stack.push(expand);
+
+                            // Names which are not registered are left to the run-time, the
+                            // directive has already been forwarded
+                            let Some(&file_id) = self.processor.arb_named_strings.get(&path.path)
+                            else {
+                                continue;
+                            };
+
+                            let parsed = ParsedFile {
+                                processor: &mut *self.processor,
+                                file_id,
+                            };
+
+                            if self.once.contains(&parsed.ast()) {
+                                continue;
+                            }
+
+                            let depth = self.stack.len() as u32;
+                            self.stack.push(
+                                parsed
+                                    .expand_one(state)
+                                    .with_depth(depth)
+                                    .with_output(output)
+                                    .with_version_placement(version),
+                            );
+                        }
                         ExpandEvent::Completed(state) => {
                             if let Some(last) = self.stack.last_mut() {
-                                // Propagate the updated state upwards in the stack
+                                // Propagate the updated state, output count and #version
+                                // placement upwards in the stack
                                 last.set_state(state);
+                                last.set_output(expand.output());
+                                last.set_version_placement(expand.version_placement());
+                            } else if let Some(file_id) = self.pending.pop_front() {
+                                // Continue with the next entry, starting from the current state
+                                let parsed = ParsedFile {
+                                    processor: &mut *self.processor,
+                                    file_id,
+                                };
+
+                                self.stack.push(
+                                    parsed
+                                        .expand_one(state)
+                                        .with_output(expand.output())
+                                        .with_version_placement(expand.version_placement()),
+                                );
                             } else {
                                 // No more, store the final state
                                 self.state = Some(state);
@@ -184,6 +480,11 @@ impl<'p, F: FileSystem> ParsedFile<'p, F> {
         self.file_id
     }
 
+    /// Start expanding this file from the given processor state
+    ///
+    /// The state is owned by the returned expansion, so processing several files (e.g. the
+    /// stages of a program) from a cloned initial state does not leak definitions between them.
+    /// The final state can be recovered with [ExpandStack::into_state].
     pub fn process(self, initial_state: ProcessorState) -> ExpandStack<'p, F> {
         let ast = self.ast();
 
@@ -191,6 +492,9 @@ impl<'p, F: FileSystem> ParsedFile<'p, F> {
             processor: self.processor,
             stack: vec![ExpandOne::new((self.file_id, ast), initial_state)],
             state: None,
+            once: PragmaOnce::default(),
+            include_stack: Vec::new(),
+            pending: VecDeque::new(),
         }
     }
 
@@ -223,9 +527,31 @@ impl<'p, F: FileSystem> IntoIterator for ParsedFile<'p, F> {
     }
 }
 
+/// Result of running a [Processor] to completion, as returned by [Processor::process_all]
+#[derive(Debug)]
+pub struct PreprocessorOutput<E> {
+    /// Events returned by the expansion, in order
+    pub events: Vec<Result<Event, Located<E>>>,
+    /// Processor state after the last event
+    pub final_state: ProcessorState,
+    /// Errors raised outside of inactive conditional blocks, including directive errors
+    pub diagnostics: Vec<Error>,
+}
+
+impl<E> PreprocessorOutput<E> {
+    /// Return this output if no diagnostics were raised, or all the diagnostics otherwise
+    pub fn into_result(self) -> Result<Self, MultiError<Error>> {
+        if self.diagnostics.is_empty() {
+            Ok(self)
+        } else {
+            Err(self.diagnostics.into())
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, derive_more::From)]
 enum PathOrSource {
-    Source(usize, PathBuf),
+    Source(FileId, PathBuf),
     Path(PathBuf),
 }
 
@@ -242,13 +568,22 @@ impl PathOrSource {
 #[derive(Debug)]
 pub struct Processor<F: FileSystem> {
     /// Cache of parsed files (preprocessor token sequences)
-    file_cache: HashMap<FileId, Ast>,
+    file_cache: AHashMap<FileId, Ast>,
     /// Mapping from canonical paths to FileIds
     file_ids: BiHashMap<PathOrSource, FileId>,
     /// Mapping from #include/input paths to canonical paths
     canonical_paths: BiHashMap<PathBuf, PathBuf>,
     /// List of include paths in resolution order
     system_paths: Vec<PathBuf>,
+    /// Cache of parsed files shared with other processors
+    shared_cache: Option<SharedFileCache>,
+    /// Namespace for allocated file identifiers
+    namespace: Option<FileIdNamespace>,
+    /// Raw value of the next allocated file identifier, kept across resets so identifiers are
+    /// never reused
+    next_file_id: u32,
+    /// Parsed named strings for `GL_ARB_shading_language_include`, by name
+    arb_named_strings: AHashMap<String, FileId>,
     /// Filesystem abstraction
     fs: F,
 }
@@ -256,14 +591,107 @@ pub struct Processor<F: FileSystem> {
 impl<F: FileSystem> Processor<F> {
     pub fn new_with_fs(fs: F) -> Self {
         Self {
-            file_cache: HashMap::with_capacity(1),
+            file_cache: AHashMap::with_capacity(1),
             file_ids: BiHashMap::with_capacity(1),
             canonical_paths: BiHashMap::with_capacity(1),
             system_paths: Vec::new(),
+            shared_cache: None,
+            namespace: None,
+            next_file_id: 0,
+            arb_named_strings: AHashMap::new(),
             fs,
         }
     }
 
+    /// Create a processor which looks up parsed files in the given shared cache before parsing
+    /// them
+    pub fn new_with_shared_cache(fs: F, cache: SharedFileCache) -> Self {
+        Self {
+            shared_cache: Some(cache),
+            ..Self::new_with_fs(fs)
+        }
+    }
+
+    pub fn shared_cache(&self) -> Option<&SharedFileCache> {
+        self.shared_cache.as_ref()
+    }
+
+    /// Allocate file identifiers for this processor in the given namespace
+    ///
+    /// This should be set before parsing any file. Use [FileIdNamespace::unique] to make sure
+    /// the results of multiple processors can be merged without identifier collisions.
+    pub fn with_namespace(self, namespace: FileIdNamespace) -> Self {
+        Self {
+            namespace: Some(namespace),
+            ..self
+        }
+    }
+
+    pub fn namespace(&self) -> Option<FileIdNamespace> {
+        self.namespace
+    }
+
+    /// Forget all parsed files and allocated file identifiers
+    ///
+    /// The filesystem, system paths, namespace and shared cache are left untouched. Files
+    /// are read and parsed again the next time they are requested. Named strings are parsed
+    /// sources, so they are removed as well. File identifiers allocated after a reset are
+    /// distinct from the ones allocated before it.
+    pub fn reset(&mut self) {
+        self.arb_named_strings.clear();
+        self.file_cache.clear();
+        self.file_ids.clear();
+        self.canonical_paths.clear();
+    }
+
+    /// Register a named string for `GL_ARB_shading_language_include`
+    ///
+    /// When the ARB include extension is enabled, `#include <name>` directives are resolved
+    /// using the named strings registered here instead of the filesystem. Includes of unknown
+    /// names are forwarded as-is, to be resolved at run-time.
+    ///
+    /// The source is parsed immediately and gets its own file identifier, which is returned.
+    /// Registering a name again replaces the previous named string.
+    ///
+    /// # Parameters
+    ///
+    /// * `name`: name of the string, e.g. `/shaders/common.glsl`
+    /// * `source`: GLSL source of the named string
+    pub fn add_named_string(&mut self, name: &str, source: &str) -> FileId {
+        // Quoted includes in the named string are resolved relative to its name
+        let parent = Path::new(name).parent().unwrap_or(Path::new("/"));
+        let file_id = self.parse_source(source, parent).file_id();
+
+        if let Some(previous) = self.arb_named_strings.insert(name.to_owned(), file_id) {
+            self.file_cache.remove(&previous);
+        }
+
+        file_id
+    }
+
+    /// Remove a named string registered with [Processor::add_named_string]
+    ///
+    /// Returns `true` if a named string was registered under this name.
+    pub fn remove_named_string(&mut self, name: &str) -> bool {
+        if let Some(file_id) = self.arb_named_strings.remove(name) {
+            self.file_cache.remove(&file_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next_file_id(&mut self) -> FileId {
+        let file_id = FileId::new(self.next_file_id);
+        self.next_file_id += 1;
+
+        if let Some(namespace) = self.namespace {
+            file_id.with_namespace(namespace)
+        } else {
+            file_id
+        }
+    }
+
     fn get_paths(&self, file_id: FileId) -> Option<(&PathBuf, &PathBuf)> {
         // Find the canonical path for the current file identifier
         let canonical_path = self.file_ids.get_by_right(&file_id)?;
@@ -281,6 +709,33 @@ impl<F: FileSystem> Processor<F> {
         Some((canonical_path, input_path))
     }
 
+    /// Return the number of file identifiers allocated by this processor
+    ///
+    /// This includes parsed files and source blocks.
+    pub fn file_count(&self) -> usize {
+        self.file_ids.len()
+    }
+
+    /// Return the identifiers of all files and source blocks known to this processor
+    ///
+    /// Combined with [Processor::path_for_file], this gives the set of files read when
+    /// processing, e.g. to watch them for changes.
+    pub fn parsed_file_ids(&self) -> impl Iterator<Item = FileId> + '_ {
+        self.file_ids.right_values().copied()
+    }
+
+    /// Return the path of the file with the given identifier, as it was requested
+    ///
+    /// Returns `None` for unknown identifiers and for source blocks.
+    pub fn path_for_file(&self, file_id: FileId) -> Option<&Path> {
+        <Self as FileIdResolver>::resolve(self, file_id)
+    }
+
+    /// Classify an error returned by this processor's filesystem
+    pub fn error_kind(&self, error: &F::Error) -> FileSystemErrorKind {
+        self.fs.error_kind(error)
+    }
+
     pub fn system_paths(&self) -> &Vec<PathBuf> {
         &self.system_paths
     }
@@ -319,15 +774,29 @@ impl<F: FileSystem> Processor<F> {
 
         // Else, try to resolve it
         match path.ty {
-            PathType::Angle => self.system_paths.iter().find_map(|system_path| {
-                let full_path = system_path.join(&path_as_pathbuf);
-                self.fs.exists(&full_path).then_some(full_path)
-            }),
-            PathType::Quote => Some(parent.as_ref().join(path_as_pathbuf)),
+            PathType::Angle => self
+                .system_paths
+                .iter()
+                .find_map(|system_path| {
+                    let full_path = system_path.join(&path_as_pathbuf);
+                    self.fs.exists(&full_path).then_some(full_path)
+                })
+                .or_else(|| self.fs.resolve_include(path)),
+            PathType::Quote => {
+                let full_path = parent.as_ref().join(path_as_pathbuf);
+
+                if !self.fs.exists(&full_path) {
+                    if let Some(resolved) = self.fs.resolve_include(path) {
+                        return Some(resolved);
+                    }
+                }
+
+                Some(full_path)
+            }
         }
     }
 
-    pub fn parse(&mut self, path: &Path) -> Result<ParsedFile<F>, F::Error> {
+    fn allocate_file_id(&mut self, path: &Path) -> Result<FileId, F::Error> {
         // Find the canonical path. Not using the entry API because cloning a path is expensive.
         let canonical_path = if let Some(canonical_path) = self.canonical_paths.get_by_left(path) {
             canonical_path
@@ -342,25 +811,58 @@ impl<F: FileSystem> Processor<F> {
         let file_id = if let Some(file_id) = self.file_ids.get_by_left(&key) {
             *file_id
         } else {
-            let file_id = FileId::new(self.file_ids.len() as _);
+            let file_id = self.next_file_id();
             self.file_ids.insert(key, file_id);
             file_id
         };
 
+        Ok(file_id)
+    }
+
+    fn read_and_parse(
+        fs: &F,
+        shared_cache: Option<&SharedFileCache>,
+        canonical_path: &Path,
+    ) -> Result<Ast, F::Error> {
+        // Read the file
+        let input = fs.read_stripping_bom(canonical_path)?;
+        Ok(Self::parse_input(shared_cache, &input))
+    }
+
+    fn parse_input(shared_cache: Option<&SharedFileCache>, input: &str) -> Ast {
+        // Parse it, or fetch it from the shared cache
+        let ast = if let Some(shared_cache) = shared_cache {
+            shared_cache.get_or_parse(input)
+        } else {
+            Parser::new(input).parse()
+        };
+
+        // Check that the root node covers the entire range
+        debug_assert_eq!(u32::from(ast.green_node().text_len()), input.len() as u32);
+
+        ast
+    }
+
+    pub fn parse(&mut self, path: &Path) -> Result<ParsedFile<F>, F::Error> {
+        let file_id = self.allocate_file_id(path)?;
+
         match self.file_cache.entry(file_id) {
             Entry::Occupied(_) => Ok(ParsedFile {
                 processor: self,
                 file_id,
             }),
             Entry::Vacant(entry) => {
-                // Read the file
-                let input = self.fs.read(canonical_path)?;
-                // Parse it
-                let ast = Parser::new(&input).parse();
-                // Check that the root node covers the entire range
-                debug_assert_eq!(u32::from(ast.green_node().text_len()), input.len() as u32);
-                // Insert it
-                entry.insert(ast);
+                let canonical_path = self
+                    .file_ids
+                    .get_by_right(&file_id)
+                    .and_then(PathOrSource::as_path)
+                    .unwrap();
+
+                entry.insert(Self::read_and_parse(
+                    &self.fs,
+                    self.shared_cache.as_ref(),
+                    canonical_path,
+                )?);
 
                 Ok(ParsedFile {
                     processor: self,
@@ -370,6 +872,173 @@ impl<F: FileSystem> Processor<F> {
         }
     }
 
+    /// Process the file at `entry` to completion, collecting the resulting events
+    ///
+    /// This is equivalent to draining the [ExpandStack] returned by [ParsedFile::process] and
+    /// then calling [ExpandStack::into_state]. Errors raised while reading included files are
+    /// part of the returned events.
+    pub fn process_all(
+        &mut self,
+        entry: &Path,
+        initial_state: ProcessorState,
+    ) -> Result<PreprocessorOutput<F::Error>, F::Error> {
+        let mut expand = self.parse(entry)?.process(initial_state);
+
+        let mut events = Vec::new();
+        let mut diagnostics = Vec::new();
+        for event in expand.by_ref() {
+            match &event {
+                Ok(Event::Error {
+                    error,
+                    masked: false,
+                }) => {
+                    diagnostics.push(error.clone());
+                }
+                Ok(Event::Directive {
+                    directive,
+                    masked: false,
+                }) => {
+                    diagnostics.extend(directive.errors().iter().cloned());
+                }
+                _ => {}
+            }
+
+            events.push(event);
+        }
+
+        Ok(PreprocessorOutput {
+            events,
+            final_state: expand
+                .into_state()
+                .expect("the expansion has run to completion"),
+            diagnostics,
+        })
+    }
+
+    /// Process the files at `entries` in order, sharing the processor state between them
+    ///
+    /// Each entry starts with the state at the end of the previous one, so macros defined by an
+    /// entry are visible in the following ones, as are files included with `#pragma once`. Every
+    /// entry is reported by its own [Event::EnterFile] and [Event::ExitFile] events, and
+    /// [ExpandStack::into_state] returns the state at the end of the last entry.
+    ///
+    /// All entries are parsed before the expansion starts, so failing to read one of them is
+    /// reported immediately.
+    pub fn process_multiple(
+        &mut self,
+        entries: &[&Path],
+        initial_state: ProcessorState,
+    ) -> Result<ExpandStack<'_, F>, F::Error> {
+        let mut file_ids = entries
+            .iter()
+            .map(|entry| self.parse(entry).map(|parsed| parsed.file_id()))
+            .collect::<Result<VecDeque<_>, _>>()?;
+
+        Ok(match file_ids.pop_front() {
+            Some(first) => {
+                let mut expand = ParsedFile {
+                    processor: self,
+                    file_id: first,
+                }
+                .process(initial_state);
+
+                expand.pending = file_ids;
+                expand
+            }
+            None => ExpandStack {
+                processor: self,
+                stack: Vec::new(),
+                state: Some(initial_state),
+                once: PragmaOnce::default(),
+                include_stack: Vec::new(),
+                pending: VecDeque::new(),
+            },
+        })
+    }
+
+    /// Parse all the given files concurrently, and return their file identifiers
+    ///
+    /// Files which were already parsed are not parsed again. Parsed files are stored in the
+    /// cache, so subsequent calls to [Processor::parse] for these paths (including when
+    /// expanding #include directives) do not parse them again.
+    #[cfg(feature = "parallel")]
+    pub fn parse_all<P: AsRef<Path>>(
+        &mut self,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<Vec<FileId>, F::Error>
+    where
+        F: Sync,
+        F::Error: Send,
+    {
+        use rayon::prelude::*;
+
+        let mut file_ids = Vec::new();
+        let mut pending = Vec::new();
+
+        // Allocating file ids requires mutable access, do it sequentially
+        for path in paths {
+            let file_id = self.allocate_file_id(path.as_ref())?;
+
+            if !self.file_cache.contains_key(&file_id) && !pending.contains(&file_id) {
+                pending.push(file_id);
+            }
+
+            file_ids.push(file_id);
+        }
+
+        let (fs, shared_cache, keys) = (&self.fs, self.shared_cache.as_ref(), &self.file_ids);
+        let parsed: Vec<_> = pending
+            .into_par_iter()
+            .map(|file_id| {
+                let canonical_path = keys
+                    .get_by_right(&file_id)
+                    .and_then(PathOrSource::as_path)
+                    .unwrap();
+
+                Self::read_and_parse(fs, shared_cache, canonical_path).map(|ast| (file_id, ast))
+            })
+            .collect::<Result<_, _>>()?;
+
+        self.file_cache.extend(parsed);
+
+        Ok(file_ids)
+    }
+
+    /// Process a source string as if it was the contents of the file at `virtual_path`
+    ///
+    /// The filesystem is not used to read the source string, but #include directives are
+    /// resolved relative to `virtual_path` and read from the filesystem.
+    pub fn process_str(
+        &mut self,
+        source: &str,
+        virtual_path: impl Into<PathBuf>,
+        initial_state: ProcessorState,
+    ) -> ExpandStack<F> {
+        let virtual_path = virtual_path.into();
+
+        // The virtual path is its own canonical path
+        self.canonical_paths
+            .insert(virtual_path.clone(), virtual_path.clone());
+
+        let key = PathOrSource::Path(virtual_path);
+        let file_id = if let Some(file_id) = self.file_ids.get_by_left(&key) {
+            *file_id
+        } else {
+            let file_id = self.next_file_id();
+            self.file_ids.insert(key, file_id);
+            file_id
+        };
+
+        let ast = Self::parse_input(self.shared_cache.as_ref(), source);
+        self.file_cache.insert(file_id, ast);
+
+        ParsedFile {
+            processor: self,
+            file_id,
+        }
+        .process(initial_state)
+    }
+
     /// Parse a given source block as if it belonged in a specific directory
     ///
     /// # Parameters
@@ -377,18 +1046,13 @@ impl<F: FileSystem> Processor<F> {
     /// * `source`: GLSL source block to parse
     /// * `path`: path to the directory that (virtually) contains this GLSL source block
     pub fn parse_source(&mut self, source: &str, path: &Path) -> ParsedFile<F> {
-        // Create key for this source block
-        let key = PathOrSource::Source(self.file_ids.len(), path.to_owned());
-
-        // Register file id
-        let file_id = FileId::new(self.file_ids.len() as _);
+        // Register file id, using it as the key for this source block
+        let file_id = self.next_file_id();
+        let key = PathOrSource::Source(file_id, path.to_owned());
         self.file_ids.insert(key, file_id);
 
-        // Parse the source
-        let ast = Parser::new(source).parse();
-        // Check that the root node covers the entire range
-        debug_assert_eq!(u32::from(ast.green_node().text_len()), source.len() as u32);
-        // Insert into the cache
+        // Parse the source and insert it into the cache
+        let ast = Self::parse_input(self.shared_cache.as_ref(), source);
         self.file_cache.insert(file_id, ast);
 
         ParsedFile {
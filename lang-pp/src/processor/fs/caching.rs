@@ -0,0 +1,206 @@
+use std::{
+    borrow::Cow,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::types::path::ParsedPath;
+
+use super::{FileSystem, FileSystemErrorKind};
+
+/// Source of file change notifications for a [CachingFileSystem]
+pub trait FileWatcher {
+    /// Return true if the file at the given canonical path changed since it was last read
+    fn has_changed(&self, path: &Path) -> bool;
+}
+
+/// Watcher that never reports changes, cache entries are only invalidated explicitly
+impl FileWatcher for () {
+    fn has_changed(&self, _path: &Path) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    content: Arc<str>,
+    hash: u64,
+    /// Time of the last read from the inner filesystem, only tracked with a maximum age
+    read_at: Option<Instant>,
+}
+
+impl CacheEntry {
+    fn new(content: &str, max_age: Option<Duration>) -> Self {
+        Self {
+            content: content.into(),
+            hash: Self::hash(content),
+            read_at: max_age.map(|_| Instant::now()),
+        }
+    }
+
+    fn hash(content: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn is_expired(&self, max_age: Option<Duration>) -> bool {
+        match (self.read_at, max_age) {
+            (Some(read_at), Some(max_age)) => read_at.elapsed() >= max_age,
+            _ => false,
+        }
+    }
+}
+
+/// Filesystem that caches file contents read from an inner filesystem
+///
+/// Cached contents are read again when the [FileWatcher] reports a change, when the path is
+/// explicitly invalidated, or when they are older than the maximum age set with
+/// [CachingFileSystem::with_max_age]. Without a watcher or a maximum age, cached contents are
+/// served until they are invalidated. The cache is behind a lock, so this filesystem can be
+/// shared between threads, e.g. by [Processor::parse_all](super::Processor::parse_all).
+///
+/// Contents are stored as shared strings: [CachingFileSystem::read_shared] returns them without
+/// copying, while [FileSystem::read] returns an owned copy.
+#[derive(Default, Debug)]
+pub struct CachingFileSystem<F: FileSystem, W: FileWatcher = ()> {
+    inner: F,
+    watcher: W,
+    max_age: Option<Duration>,
+    cache: RwLock<HashMap<PathBuf, CacheEntry>>,
+}
+
+static_assertions::assert_impl_all!(CachingFileSystem<super::Std>: Send, Sync);
+
+impl<F: FileSystem> CachingFileSystem<F> {
+    pub fn new(inner: F) -> Self {
+        Self::with_watcher(inner, ())
+    }
+}
+
+impl<F: FileSystem, W: FileWatcher> CachingFileSystem<F, W> {
+    pub fn with_watcher(inner: F, watcher: W) -> Self {
+        Self {
+            inner,
+            watcher,
+            max_age: None,
+            cache: Default::default(),
+        }
+    }
+
+    /// Revalidate cached contents which were read from the inner filesystem more than `max_age`
+    /// ago
+    ///
+    /// Revalidating reads the file again: if its hash did not change, the cached contents are
+    /// kept and only their age is reset.
+    pub fn with_max_age(self, max_age: Duration) -> Self {
+        Self {
+            max_age: Some(max_age),
+            ..self
+        }
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn watcher(&self) -> &W {
+        &self.watcher
+    }
+
+    /// Remove the cached contents for the given path
+    pub fn invalidate(&self, path: &Path) {
+        self.cache.write().unwrap().remove(path);
+    }
+
+    /// Remove all cached contents
+    pub fn clear(&self) {
+        self.cache.write().unwrap().clear();
+    }
+
+    /// Read the file at the given path again, updating the cache
+    ///
+    /// # Returns
+    ///
+    /// `true` if the file contents changed (as determined by their hash) or were not cached yet.
+    pub fn refresh(&self, path: &Path) -> Result<bool, F::Error> {
+        self.reload(path).map(|(_, changed)| changed)
+    }
+
+    /// Return the contents of the file at the given path, sharing them with the cache
+    pub fn read_shared(&self, path: &Path) -> Result<Arc<str>, F::Error> {
+        if self.watcher.has_changed(path) {
+            self.invalidate(path);
+        }
+
+        if let Some(entry) = self.cache.read().unwrap().get(path) {
+            if !entry.is_expired(self.max_age) {
+                return Ok(entry.content.clone());
+            }
+        }
+
+        self.reload(path).map(|(content, _)| content)
+    }
+
+    /// Read the file at the given path from the inner filesystem and update the cache, keeping
+    /// the cached contents if they did not change
+    fn reload(&self, path: &Path) -> Result<(Arc<str>, bool), F::Error> {
+        let content = self.inner.read(path)?;
+        let mut entry = CacheEntry::new(&content, self.max_age);
+
+        let mut cache = self.cache.write().unwrap();
+        let changed = match cache.get(path) {
+            Some(previous) if previous.hash == entry.hash && *previous.content == *content => {
+                entry.content = previous.content.clone();
+                false
+            }
+            _ => true,
+        };
+
+        let content = entry.content.clone();
+        cache.insert(path.to_owned(), entry);
+        Ok((content, changed))
+    }
+
+    /// Return true if the contents of the given path are cached
+    pub fn is_cached(&self, path: &Path) -> bool {
+        self.cache.read().unwrap().contains_key(path)
+    }
+}
+
+impl<F: FileSystem, W: FileWatcher> FileSystem for CachingFileSystem<F, W> {
+    type Error = F::Error;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        self.inner.canonicalize(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.is_cached(path) || self.inner.exists(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        // The contents cannot be borrowed past the cache lock, so return a copy
+        self.read_shared(path)
+            .map(|content| Cow::Owned(content.as_ref().to_owned()))
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        self.inner.is_not_found(error)
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        self.inner.error_kind(error)
+    }
+
+    fn resolve_include(&self, path: &ParsedPath) -> Option<PathBuf> {
+        self.inner.resolve_include(path)
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use thiserror::Error;
+use web_sys::XmlHttpRequest;
+
+use super::{FileSystem, FileSystemErrorKind};
+
+/// Error raised by a [FetchFileSystem]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FetchError {
+    /// The request could not be performed
+    #[error("request for {url} failed: {message}")]
+    Request {
+        /// URL of the failed request
+        url: String,
+        /// Description of the error raised by the browser
+        message: String,
+    },
+    /// The server answered with a non-success status code
+    #[error("request for {url} failed with status {status}")]
+    Status {
+        /// URL of the failed request
+        url: String,
+        /// HTTP status code of the response
+        status: u16,
+    },
+}
+
+/// Filesystem that fetches files over HTTP, for use in browser environments
+///
+/// Since the [FileSystem] trait is synchronous, requests are performed using synchronous
+/// `XMLHttpRequest`s. Browsers only allow those outside of the main thread without deprecation
+/// warnings, so this filesystem should preferably be used from a web worker. Fetched contents and
+/// missing files (404 responses) are cached by URL, so each file is requested at most once. Other
+/// errors may be transient, so they are not cached and the request is retried on the next read.
+#[derive(Debug)]
+pub struct FetchFileSystem {
+    base_url: String,
+    cache: RefCell<HashMap<String, Result<String, FetchError>>>,
+}
+
+impl FetchFileSystem {
+    /// Create a new filesystem resolving paths relative to the given base URL
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            cache: Default::default(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Remove all cached contents
+    pub fn clear(&self) {
+        self.cache.borrow_mut().clear();
+    }
+
+    /// Return the URL a canonical path is fetched from
+    pub fn url(&self, path: &Path) -> String {
+        let path = path.to_string_lossy();
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    fn fetch(&self, path: &Path) -> Result<String, FetchError> {
+        let url = self.url(path);
+
+        if let Some(result) = self.cache.borrow().get(&url) {
+            return result.clone();
+        }
+
+        let result = Self::request("GET", &url).and_then(|xhr| {
+            Ok(xhr
+                .response_text()
+                .map_err(|error| Self::request_error(&url, error))?
+                .unwrap_or_default())
+        });
+
+        self.cache_result(url, &result);
+        result
+    }
+
+    /// Cache the result of a request, unless it failed for another reason than a missing file
+    fn cache_result(&self, url: String, result: &Result<String, FetchError>) {
+        if matches!(result, Ok(_) | Err(FetchError::Status { status: 404, .. })) {
+            self.cache.borrow_mut().insert(url, result.clone());
+        }
+    }
+
+    fn request(method: &str, url: &str) -> Result<XmlHttpRequest, FetchError> {
+        let request_error = |error| Self::request_error(url, error);
+
+        let xhr = XmlHttpRequest::new().map_err(request_error)?;
+        xhr.open_with_async(method, url, false)
+            .map_err(request_error)?;
+        xhr.send().map_err(request_error)?;
+
+        let status = xhr.status().map_err(request_error)?;
+        if !(200..300).contains(&status) {
+            return Err(FetchError::Status {
+                url: url.to_owned(),
+                status,
+            });
+        }
+
+        Ok(xhr)
+    }
+
+    fn request_error(url: &str, error: wasm_bindgen::JsValue) -> FetchError {
+        FetchError::Request {
+            url: url.to_owned(),
+            message: format!("{:?}", error),
+        }
+    }
+}
+
+impl FileSystem for FetchFileSystem {
+    type Error = FetchError;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        // There is no filesystem to query, so normalize the path lexically
+        let mut canonical = PathBuf::from("/");
+
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => canonical.push(part),
+                Component::ParentDir => {
+                    canonical.pop();
+                }
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            }
+        }
+
+        Ok(canonical)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        let Ok(path) = self.canonicalize(path) else {
+            return false;
+        };
+
+        let url = self.url(&path);
+        if let Some(result) = self.cache.borrow().get(&url) {
+            return result.is_ok();
+        }
+
+        // Only query the headers, the contents are fetched when the file is read
+        match Self::request("HEAD", &url) {
+            Ok(_) => true,
+            Err(error) => {
+                self.cache_result(url, &Err(error));
+                false
+            }
+        }
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        self.fetch(&self.canonicalize(path)?).map(Cow::Owned)
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        matches!(error, FetchError::Status { status: 404, .. })
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        match error {
+            FetchError::Status { status: 404, .. } => FileSystemErrorKind::NotFound,
+            FetchError::Status {
+                status: 401 | 403, ..
+            } => FileSystemErrorKind::PermissionDenied,
+            _ => FileSystemErrorKind::Other,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use crate::types::path::ParsedPath;
+
+use super::{FileSystem, FileSystemErrorKind};
+
+/// Filesystem that normalizes CRLF line endings to LF when reading files
+///
+/// See [FileSystem::read_normalized].
+#[derive(Default, Debug, Clone, Copy)]
+pub struct NormalizingFileSystem<F: FileSystem> {
+    inner: F,
+}
+
+impl<F: FileSystem> NormalizingFileSystem<F> {
+    pub fn new(inner: F) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: FileSystem> FileSystem for NormalizingFileSystem<F> {
+    type Error = F::Error;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        self.inner.canonicalize(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        self.inner.read_normalized(path)
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        self.inner.is_not_found(error)
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        self.inner.error_kind(error)
+    }
+
+    fn resolve_include(&self, path: &ParsedPath) -> Option<PathBuf> {
+        self.inner.resolve_include(path)
+    }
+}
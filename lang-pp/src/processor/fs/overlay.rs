@@ -0,0 +1,97 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::types::path::ParsedPath;
+
+use super::{FileSystem, FileSystemErrorKind};
+
+/// Error raised by an [OverlayFileSystem]
+#[derive(Debug, Error)]
+pub enum OverlayError<P: std::error::Error + 'static, F: std::error::Error + 'static> {
+    /// Error from the primary filesystem
+    #[error(transparent)]
+    Primary(P),
+    /// Error from the fallback filesystem
+    #[error(transparent)]
+    Fallback(F),
+}
+
+/// Filesystem that looks up files in a primary filesystem first, and then in a fallback
+/// filesystem if they were not found in the primary one.
+///
+/// Only "not found" errors (as reported by [FileSystem::is_not_found]) from the primary
+/// filesystem trigger the fallback, other errors are returned as-is.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct OverlayFileSystem<P: FileSystem, F: FileSystem> {
+    primary: P,
+    fallback: F,
+}
+
+impl<P: FileSystem, F: FileSystem> OverlayFileSystem<P, F> {
+    pub fn new(primary: P, fallback: F) -> Self {
+        Self { primary, fallback }
+    }
+
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    pub fn fallback(&self) -> &F {
+        &self.fallback
+    }
+
+    pub fn into_inner(self) -> (P, F) {
+        (self.primary, self.fallback)
+    }
+}
+
+impl<P: FileSystem, F: FileSystem> FileSystem for OverlayFileSystem<P, F> {
+    type Error = OverlayError<P::Error, F::Error>;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        match self.primary.canonicalize(path) {
+            Err(error) if self.primary.is_not_found(&error) => self
+                .fallback
+                .canonicalize(path)
+                .map_err(OverlayError::Fallback),
+            other => other.map_err(OverlayError::Primary),
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.primary.exists(path) || self.fallback.exists(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        match self.primary.read(path) {
+            Err(error) if self.primary.is_not_found(&error) => {
+                self.fallback.read(path).map_err(OverlayError::Fallback)
+            }
+            other => other.map_err(OverlayError::Primary),
+        }
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        match error {
+            OverlayError::Primary(error) => self.primary.is_not_found(error),
+            OverlayError::Fallback(error) => self.fallback.is_not_found(error),
+        }
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        match error {
+            OverlayError::Primary(error) => self.primary.error_kind(error),
+            OverlayError::Fallback(error) => self.fallback.error_kind(error),
+        }
+    }
+
+    fn resolve_include(&self, path: &ParsedPath) -> Option<PathBuf> {
+        self.primary
+            .resolve_include(path)
+            .or_else(|| self.fallback.resolve_include(path))
+    }
+}
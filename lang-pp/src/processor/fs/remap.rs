@@ -0,0 +1,89 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use crate::types::path::ParsedPath;
+
+use super::{FileSystem, FileSystemErrorKind};
+
+/// Filesystem that rewrites path prefixes before delegating to an inner filesystem
+///
+/// Rules are tried in insertion order and only the first matching rule is applied. Remapping
+/// applies to the raw paths given to the filesystem, before canonicalization.
+#[derive(Default, Debug, Clone)]
+pub struct PathRemappingFileSystem<F: FileSystem> {
+    inner: F,
+    rules: Vec<(PathBuf, PathBuf)>,
+}
+
+impl<F: FileSystem> PathRemappingFileSystem<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Add a remapping rule, replacing the `from` prefix with `to`
+    pub fn remap(mut self, from: impl Into<PathBuf>, to: impl Into<PathBuf>) -> Self {
+        self.rules.push((from.into(), to.into()));
+        self
+    }
+
+    pub fn rules(&self) -> &Vec<(PathBuf, PathBuf)> {
+        &self.rules
+    }
+
+    pub fn rules_mut(&mut self) -> &mut Vec<(PathBuf, PathBuf)> {
+        &mut self.rules
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    /// Apply the first matching remapping rule to the given path
+    pub fn apply<'p>(&self, path: &'p Path) -> Cow<'p, Path> {
+        self.rules
+            .iter()
+            .find_map(|(from, to)| {
+                path.strip_prefix(from)
+                    .ok()
+                    .map(|rest| Cow::Owned(to.join(rest)))
+            })
+            .unwrap_or(Cow::Borrowed(path))
+    }
+}
+
+impl<F: FileSystem> FileSystem for PathRemappingFileSystem<F> {
+    type Error = F::Error;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        self.inner.canonicalize(&self.apply(path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(&self.apply(path))
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        self.inner.read(&self.apply(path))
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        self.inner.is_not_found(error)
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        self.inner.error_kind(error)
+    }
+
+    fn resolve_include(&self, path: &ParsedPath) -> Option<PathBuf> {
+        self.inner.resolve_include(path)
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::types::path::ParsedPath;
+
+use super::{FileSystem, FileSystemErrorKind};
+
+/// Error raised by filesystem wrappers which add their own failure modes
+#[derive(Debug, Error)]
+pub enum FileSystemError<E: std::error::Error + 'static> {
+    /// The requested path is outside of the allowed directories
+    #[error("access denied: {}", .0.display())]
+    AccessDenied(PathBuf),
+    /// Error from the inner filesystem
+    #[error(transparent)]
+    Inner(E),
+}
+
+/// Filesystem that only allows access to files located in a set of allowed root directories
+///
+/// Paths are canonicalized by the inner filesystem before being checked, so `..` components
+/// and symbolic links can't be used to escape the allowed directories. The allowed roots should
+/// themselves be canonical paths.
+#[derive(Default, Debug, Clone)]
+pub struct SandboxFileSystem<F: FileSystem> {
+    inner: F,
+    allowed_roots: Vec<PathBuf>,
+}
+
+impl<F: FileSystem> SandboxFileSystem<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            allowed_roots: Vec::new(),
+        }
+    }
+
+    /// Add a directory to the set of allowed roots
+    pub fn allow(mut self, root: impl Into<PathBuf>) -> Self {
+        self.allowed_roots.push(root.into());
+        self
+    }
+
+    pub fn allowed_roots(&self) -> &Vec<PathBuf> {
+        &self.allowed_roots
+    }
+
+    pub fn allowed_roots_mut(&mut self) -> &mut Vec<PathBuf> {
+        &mut self.allowed_roots
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    /// Return true if the given canonical path is located in one of the allowed roots
+    pub fn is_allowed(&self, canonical_path: &Path) -> bool {
+        self.allowed_roots
+            .iter()
+            .any(|root| canonical_path.starts_with(root))
+    }
+
+    fn check(&self, canonical_path: PathBuf) -> Result<PathBuf, FileSystemError<F::Error>> {
+        if self.is_allowed(&canonical_path) {
+            Ok(canonical_path)
+        } else {
+            Err(FileSystemError::AccessDenied(canonical_path))
+        }
+    }
+}
+
+impl<F: FileSystem> FileSystem for SandboxFileSystem<F> {
+    type Error = FileSystemError<F::Error>;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        self.inner
+            .canonicalize(path)
+            .map_err(FileSystemError::Inner)
+            .and_then(|canonical_path| self.check(canonical_path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.canonicalize(path).is_ok() && self.inner.exists(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        let canonical_path = self.canonicalize(path)?;
+        self.inner
+            .read(&canonical_path)
+            .map_err(FileSystemError::Inner)
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        match error {
+            FileSystemError::AccessDenied(_) => false,
+            FileSystemError::Inner(error) => self.inner.is_not_found(error),
+        }
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        match error {
+            FileSystemError::AccessDenied(_) => FileSystemErrorKind::PermissionDenied,
+            FileSystemError::Inner(error) => self.inner.error_kind(error),
+        }
+    }
+
+    fn resolve_include(&self, path: &ParsedPath) -> Option<PathBuf> {
+        self.inner.resolve_include(path)
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use crate::types::path::{ParsedPath, PathType};
+
+use super::{FileSystem, FileSystemErrorKind};
+
+/// Filesystem that resolves relative #include paths against ordered lists of search directories
+///
+/// `"quoted"` includes are looked up in the user search paths first, then in the system search
+/// paths. `<angled>` includes are only looked up in the system search paths. The first candidate
+/// that the inner filesystem can canonicalize is used.
+#[derive(Default, Debug, Clone)]
+pub struct SearchPathFileSystem<F: FileSystem> {
+    inner: F,
+    user_paths: Vec<PathBuf>,
+    system_paths: Vec<PathBuf>,
+}
+
+impl<F: FileSystem> SearchPathFileSystem<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            user_paths: Vec::new(),
+            system_paths: Vec::new(),
+        }
+    }
+
+    /// Add a directory to the user search paths, used for `"quoted"` includes
+    pub fn user_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.user_paths.push(path.into());
+        self
+    }
+
+    /// Add a directory to the system search paths, used for both `"quoted"` and `<angled>`
+    /// includes
+    pub fn system_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.system_paths.push(path.into());
+        self
+    }
+
+    pub fn user_paths(&self) -> &Vec<PathBuf> {
+        &self.user_paths
+    }
+
+    pub fn user_paths_mut(&mut self) -> &mut Vec<PathBuf> {
+        &mut self.user_paths
+    }
+
+    pub fn system_paths(&self) -> &Vec<PathBuf> {
+        &self.system_paths
+    }
+
+    pub fn system_paths_mut(&mut self) -> &mut Vec<PathBuf> {
+        &mut self.system_paths
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn search_paths(&self, ty: PathType) -> impl Iterator<Item = &PathBuf> {
+        let user_paths = match ty {
+            PathType::Angle => &[][..],
+            PathType::Quote => &self.user_paths[..],
+        };
+
+        user_paths.iter().chain(self.system_paths.iter())
+    }
+
+    fn search(&self, path: &Path, ty: PathType) -> Option<PathBuf> {
+        self.search_paths(ty)
+            .find_map(|search_path| self.inner.canonicalize(&search_path.join(path)).ok())
+    }
+}
+
+impl<F: FileSystem> FileSystem for SearchPathFileSystem<F> {
+    type Error = F::Error;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        match self.inner.canonicalize(path) {
+            Err(error) if path.is_relative() && self.inner.is_not_found(&error) => {
+                self.search(path, PathType::Quote).ok_or(error)
+            }
+            other => other,
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        self.inner.read(path)
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        self.inner.is_not_found(error)
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        self.inner.error_kind(error)
+    }
+
+    fn resolve_include(&self, path: &ParsedPath) -> Option<PathBuf> {
+        self.search(Path::new(&path.path), path.ty)
+            .or_else(|| self.inner.resolve_include(path))
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use crate::parser::{Ast, Parser};
+
+/// Default number of parsed files kept in a [SharedFileCache]
+pub const DEFAULT_SHARED_CACHE_CAPACITY: usize = 1024;
+
+/// Cache of parsed files which can be shared between multiple [Processor](super::Processor)
+/// instances
+///
+/// Entries are keyed on the file contents, so a file which changed on disk is parsed again, and
+/// identical files at different paths are only parsed once. Cloning a [SharedFileCache] returns a
+/// handle to the same cache.
+///
+/// The cache holds at most [SharedFileCache::capacity] entries: when it is full, the least
+/// recently used entry is evicted, so stale versions of edited files do not accumulate.
+#[derive(Debug, Clone)]
+pub struct SharedFileCache {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    entries: RwLock<Entries>,
+    /// Logical clock used to track the last use of each entry
+    clock: AtomicU64,
+    capacity: usize,
+}
+
+#[derive(Default, Debug)]
+struct Entries {
+    /// Entries bucketed by the hash of their source
+    buckets: HashMap<u64, Vec<Entry>>,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    source: Arc<str>,
+    ast: Ast,
+    last_used: AtomicU64,
+}
+
+impl Default for SharedFileCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_SHARED_CACHE_CAPACITY)
+    }
+}
+
+impl SharedFileCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a cache which holds at most `capacity` parsed files
+    ///
+    /// # Panics
+    ///
+    /// panics if `capacity` is zero
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0, "shared file cache capacity must not be zero");
+
+        Self {
+            inner: Arc::new(Inner {
+                entries: Default::default(),
+                clock: AtomicU64::new(0),
+                capacity,
+            }),
+        }
+    }
+
+    /// Return the maximum number of parsed files held by this cache
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity
+    }
+
+    /// Return the parsed AST for the given source, parsing it if it is not in the cache yet
+    pub fn get_or_parse(&self, source: &str) -> Ast {
+        self.get_or_parse_hashed(Self::hash(source), source)
+    }
+
+    pub(super) fn get_or_parse_hashed(&self, hash: u64, source: &str) -> Ast {
+        if let Some(ast) = self
+            .inner
+            .entries
+            .read()
+            .unwrap()
+            .get(hash, source, self.tick())
+        {
+            return ast;
+        }
+
+        let ast = Parser::new(source).parse();
+
+        let mut entries = self.inner.entries.write().unwrap();
+        // Another thread may have parsed the same source in the meantime
+        if let Some(ast) = entries.get(hash, source, self.tick()) {
+            return ast;
+        }
+
+        if entries.len >= self.inner.capacity {
+            entries.evict_least_recently_used();
+        }
+
+        entries.buckets.entry(hash).or_default().push(Entry {
+            source: source.into(),
+            ast: ast.clone(),
+            last_used: AtomicU64::new(self.tick()),
+        });
+        entries.len += 1;
+
+        ast
+    }
+
+    /// Return true if the given source has already been parsed into this cache
+    pub fn contains(&self, source: &str) -> bool {
+        self.inner
+            .entries
+            .read()
+            .unwrap()
+            .buckets
+            .get(&Self::hash(source))
+            .is_some_and(|bucket| bucket.iter().any(|entry| &*entry.source == source))
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.entries.read().unwrap().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the cached entries
+    pub fn clear(&self) {
+        *self.inner.entries.write().unwrap() = Default::default();
+    }
+
+    fn tick(&self) -> u64 {
+        self.inner.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn hash(source: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl Entries {
+    fn get(&self, hash: u64, source: &str, now: u64) -> Option<Ast> {
+        // Compare the full contents, different sources may have the same hash
+        let entry = self
+            .buckets
+            .get(&hash)?
+            .iter()
+            .find(|entry| &*entry.source == source)?;
+
+        entry.last_used.store(now, Ordering::Relaxed);
+        Some(entry.ast.clone())
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let Some((hash, index)) = self
+            .buckets
+            .iter()
+            .flat_map(|(hash, bucket)| {
+                bucket.iter().enumerate().map(move |(index, entry)| {
+                    (entry.last_used.load(Ordering::Relaxed), *hash, index)
+                })
+            })
+            .min()
+            .map(|(_, hash, index)| (hash, index))
+        else {
+            return;
+        };
+
+        let bucket = self.buckets.get_mut(&hash).unwrap();
+        bucket.swap_remove(index);
+        if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+
+        self.len -= 1;
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::types::path::ParsedPath;
+
+use super::{FileSystem, FileSystemErrorKind};
+
+/// IO statistics recorded by a [StatisticsFileSystem]
+///
+/// Counters are atomic, so statistics are also recorded when the filesystem is shared between
+/// threads, e.g. by [Processor::parse_all](super::Processor::parse_all).
+#[derive(Default, Debug)]
+pub struct FileSystemStats {
+    canonicalize_calls: AtomicUsize,
+    read_calls: AtomicUsize,
+    bytes_read: AtomicUsize,
+    repeated_reads: AtomicUsize,
+    /// Time spent reading, in nanoseconds
+    read_time: AtomicU64,
+}
+
+impl FileSystemStats {
+    /// Number of calls to [FileSystem::canonicalize]
+    pub fn canonicalize_calls(&self) -> usize {
+        self.canonicalize_calls.load(Ordering::Relaxed)
+    }
+
+    /// Number of calls to [FileSystem::read]
+    pub fn read_calls(&self) -> usize {
+        self.read_calls.load(Ordering::Relaxed)
+    }
+
+    /// Total number of bytes successfully read
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Number of reads of a path that was already read before
+    pub fn repeated_reads(&self) -> usize {
+        self.repeated_reads.load(Ordering::Relaxed)
+    }
+
+    /// Total wall-clock time spent in [FileSystem::read]
+    ///
+    /// With concurrent reads, this is the sum of the time spent by each read.
+    pub fn read_time(&self) -> Duration {
+        Duration::from_nanos(self.read_time.load(Ordering::Relaxed))
+    }
+}
+
+impl Clone for FileSystemStats {
+    fn clone(&self) -> Self {
+        Self {
+            canonicalize_calls: self.canonicalize_calls().into(),
+            read_calls: self.read_calls().into(),
+            bytes_read: self.bytes_read().into(),
+            repeated_reads: self.repeated_reads().into(),
+            read_time: self.read_time.load(Ordering::Relaxed).into(),
+        }
+    }
+}
+
+impl fmt::Display for FileSystemStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} canonicalize calls, {} reads ({} repeated), {} bytes read in {:?}",
+            self.canonicalize_calls(),
+            self.read_calls(),
+            self.repeated_reads(),
+            self.bytes_read(),
+            self.read_time()
+        )
+    }
+}
+
+/// Filesystem that records IO statistics about an inner filesystem
+#[derive(Default, Debug)]
+pub struct StatisticsFileSystem<F: FileSystem> {
+    inner: F,
+    stats: FileSystemStats,
+    read_paths: Mutex<HashSet<PathBuf>>,
+}
+
+static_assertions::assert_impl_all!(StatisticsFileSystem<super::Std>: Send, Sync);
+
+impl<F: FileSystem> StatisticsFileSystem<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            stats: Default::default(),
+            read_paths: Default::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &FileSystemStats {
+        &self.stats
+    }
+
+    /// Reset all recorded statistics
+    pub fn reset(&self) {
+        self.stats.canonicalize_calls.store(0, Ordering::Relaxed);
+        self.stats.read_calls.store(0, Ordering::Relaxed);
+        self.stats.bytes_read.store(0, Ordering::Relaxed);
+        self.stats.repeated_reads.store(0, Ordering::Relaxed);
+        self.stats.read_time.store(0, Ordering::Relaxed);
+        self.read_paths.lock().unwrap().clear();
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F: FileSystem> FileSystem for StatisticsFileSystem<F> {
+    type Error = F::Error;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        self.stats
+            .canonicalize_calls
+            .fetch_add(1, Ordering::Relaxed);
+        self.inner.canonicalize(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        let stats = &self.stats;
+        stats.read_calls.fetch_add(1, Ordering::Relaxed);
+
+        if !self.read_paths.lock().unwrap().insert(path.to_owned()) {
+            stats.repeated_reads.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let start = Instant::now();
+        let result = self.inner.read(path);
+        let elapsed = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        stats.read_time.fetch_add(elapsed, Ordering::Relaxed);
+
+        if let Ok(content) = &result {
+            stats.bytes_read.fetch_add(content.len(), Ordering::Relaxed);
+        }
+
+        result
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        self.inner.is_not_found(error)
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        self.inner.error_kind(error)
+    }
+
+    fn resolve_include(&self, path: &ParsedPath) -> Option<PathBuf> {
+        self.inner.resolve_include(path)
+    }
+}
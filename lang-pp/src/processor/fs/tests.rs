@@ -0,0 +1,1170 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::exts::names::ExtNameAtom;
+
+use super::*;
+
+/// In-memory filesystem for tests
+#[derive(Default, Debug)]
+struct Memory {
+    files: HashMap<PathBuf, String>,
+    reads: AtomicUsize,
+}
+
+impl Memory {
+    fn reads(&self) -> usize {
+        self.reads.load(Ordering::Relaxed)
+    }
+
+    fn with(mut self, path: impl Into<PathBuf>, content: impl Into<String>) -> Self {
+        self.files.insert(path.into(), content.into());
+        self
+    }
+}
+
+impl FileSystem for Memory {
+    type Error = io::Error;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        if self.files.contains_key(path) {
+            Ok(path.to_owned())
+        } else {
+            Err(io::ErrorKind::NotFound.into())
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.files
+            .get(path)
+            .map(|content| Cow::Borrowed(content.as_str()))
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn is_not_found(&self, error: &Self::Error) -> bool {
+        error.kind() == io::ErrorKind::NotFound
+    }
+}
+
+#[test]
+fn overlay_fallback_only() {
+    let fs = OverlayFileSystem::new(
+        Memory::default(),
+        Memory::default().with("/src/a.glsl", "fallback"),
+    );
+
+    let path = fs.canonicalize(Path::new("/src/a.glsl")).unwrap();
+    assert_eq!(fs.read(&path).unwrap(), "fallback");
+    assert!(fs.exists(&path));
+}
+
+#[test]
+fn overlay_primary_priority() {
+    let fs = OverlayFileSystem::new(
+        Memory::default().with("/src/a.glsl", "primary"),
+        Memory::default().with("/src/a.glsl", "fallback"),
+    );
+
+    let path = fs.canonicalize(Path::new("/src/a.glsl")).unwrap();
+    assert_eq!(fs.read(&path).unwrap(), "primary");
+}
+
+#[test]
+fn overlay_not_found() {
+    let fs = OverlayFileSystem::new(Memory::default(), Memory::default());
+
+    let error = fs.canonicalize(Path::new("/src/a.glsl")).unwrap_err();
+    assert!(matches!(error, OverlayError::Fallback(_)));
+    assert!(fs.is_not_found(&error));
+}
+
+fn include(path: &str, ty: PathType) -> ParsedPath {
+    ParsedPath {
+        path: path.to_owned(),
+        ty,
+    }
+}
+
+#[test]
+fn search_path_angle_and_quote() {
+    let fs = SearchPathFileSystem::new(
+        Memory::default()
+            .with("/user/a.glsl", "user")
+            .with("/system/a.glsl", "system")
+            .with("/system/b.glsl", "system"),
+    )
+    .user_path("/user")
+    .system_path("/system");
+
+    assert_eq!(
+        fs.resolve_include(&include("a.glsl", PathType::Quote)),
+        Some(PathBuf::from("/user/a.glsl"))
+    );
+    assert_eq!(
+        fs.resolve_include(&include("a.glsl", PathType::Angle)),
+        Some(PathBuf::from("/system/a.glsl"))
+    );
+    assert_eq!(
+        fs.resolve_include(&include("b.glsl", PathType::Quote)),
+        Some(PathBuf::from("/system/b.glsl"))
+    );
+    assert_eq!(
+        fs.resolve_include(&include("c.glsl", PathType::Quote)),
+        None
+    );
+}
+
+#[test]
+fn search_path_processor() {
+    let processor = Processor::new_with_fs(
+        SearchPathFileSystem::new(
+            Memory::default()
+                .with("/src/main.glsl", "")
+                .with("/include/common.glsl", ""),
+        )
+        .system_path("/include"),
+    );
+
+    assert_eq!(
+        processor.resolve_relative_to_path("/src", &include("common.glsl", PathType::Angle)),
+        Some(PathBuf::from("/include/common.glsl"))
+    );
+    assert_eq!(
+        processor.resolve_relative_to_path("/src", &include("common.glsl", PathType::Quote)),
+        Some(PathBuf::from("/include/common.glsl"))
+    );
+    assert_eq!(
+        processor.resolve_relative_to_path("/src", &include("main.glsl", PathType::Quote)),
+        Some(PathBuf::from("/src/main.glsl"))
+    );
+}
+
+#[test]
+fn sandbox_access() {
+    let fs = SandboxFileSystem::new(
+        Memory::default()
+            .with("/shaders/a.glsl", "allowed")
+            .with("/shaders-private/b.glsl", "denied")
+            .with("/etc/passwd", "denied"),
+    )
+    .allow("/shaders");
+
+    let path = fs.canonicalize(Path::new("/shaders/a.glsl")).unwrap();
+    assert_eq!(fs.read(&path).unwrap(), "allowed");
+
+    for denied in ["/shaders-private/b.glsl", "/etc/passwd"] {
+        let error = fs.canonicalize(Path::new(denied)).unwrap_err();
+        assert!(matches!(error, FileSystemError::AccessDenied(_)));
+        assert!(!fs.is_not_found(&error));
+        assert!(fs.read(Path::new(denied)).is_err());
+        assert!(!fs.exists(Path::new(denied)));
+    }
+
+    let error = fs.canonicalize(Path::new("/shaders/c.glsl")).unwrap_err();
+    assert!(fs.is_not_found(&error));
+}
+
+#[test]
+fn error_kinds() {
+    let fs =
+        SandboxFileSystem::new(Memory::default().with("/etc/passwd", "denied")).allow("/shaders");
+
+    let error = fs.canonicalize(Path::new("/etc/passwd")).unwrap_err();
+    assert_eq!(fs.error_kind(&error), FileSystemErrorKind::PermissionDenied);
+
+    // Memory only reports missing files, through the default implementation
+    let error = fs.canonicalize(Path::new("/shaders/a.glsl")).unwrap_err();
+    assert_eq!(fs.error_kind(&error), FileSystemErrorKind::NotFound);
+
+    let error = Std.read(Path::new("/nonexistent/file.glsl")).unwrap_err();
+    assert_eq!(Std.error_kind(&error), FileSystemErrorKind::NotFound);
+}
+
+/// Filesystem which claims every file exists, like a stale directory listing would
+struct Stale<F>(F);
+
+impl<F: FileSystem> FileSystem for Stale<F> {
+    type Error = F::Error;
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, Self::Error> {
+        self.0.canonicalize(path)
+    }
+
+    fn exists(&self, _path: &Path) -> bool {
+        true
+    }
+
+    fn read(&self, path: &Path) -> Result<Cow<'_, str>, Self::Error> {
+        self.0.read(path)
+    }
+
+    fn error_kind(&self, error: &Self::Error) -> FileSystemErrorKind {
+        self.0.error_kind(error)
+    }
+
+    fn resolve_include(&self, path: &ParsedPath) -> Option<PathBuf> {
+        self.0.resolve_include(path)
+    }
+}
+
+#[test]
+fn include_not_found_retry() {
+    let mut processor = Processor::new_with_fs(Stale(
+        SearchPathFileSystem::new(
+            Memory::default()
+                .with(
+                    "/src/main.glsl",
+                    "#extension GL_GOOGLE_include_directive : require\n#include \"common.glsl\"\n",
+                )
+                .with("/include/common.glsl", ""),
+        )
+        .user_path("/include"),
+    ));
+
+    let events: Vec<_> = processor
+        .parse(Path::new("/src/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default())
+        .collect();
+
+    assert!(events
+        .iter()
+        .all(|event| !matches!(event, Err(_) | Ok(Event::Error { .. }))));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Ok(Event::EnterFile { path, .. }) if path == Path::new("/include/common.glsl")
+    )));
+}
+
+#[test]
+fn remap_first_match_wins() {
+    let fs = PathRemappingFileSystem::new(
+        Memory::default()
+            .with("/ci/shaders/a.glsl", "ci")
+            .with("/ci/common/b.glsl", "common"),
+    )
+    .remap("/home/user/shaders/common", "/ci/common")
+    .remap("/home/user/shaders", "/ci/shaders")
+    .remap("/home/user/shaders/common", "/unused");
+
+    let path = fs
+        .canonicalize(Path::new("/home/user/shaders/a.glsl"))
+        .unwrap();
+    assert_eq!(path, Path::new("/ci/shaders/a.glsl"));
+    assert_eq!(fs.read(&path).unwrap(), "ci");
+
+    assert_eq!(
+        fs.read(Path::new("/home/user/shaders/common/b.glsl"))
+            .unwrap(),
+        "common"
+    );
+    assert_eq!(
+        fs.apply(Path::new("/other/c.glsl")),
+        Path::new("/other/c.glsl")
+    );
+}
+
+struct ChangedWatcher(&'static str);
+
+impl FileWatcher for ChangedWatcher {
+    fn has_changed(&self, path: &Path) -> bool {
+        path == Path::new(self.0)
+    }
+}
+
+#[test]
+fn caching_reads_once() {
+    let fs = CachingFileSystem::new(Memory::default().with("/a.glsl", "a"));
+    let path = Path::new("/a.glsl");
+
+    assert_eq!(fs.read(path).unwrap(), "a");
+    assert_eq!(fs.read(path).unwrap(), "a");
+    assert_eq!(fs.inner().reads(), 1);
+
+    fs.invalidate(path);
+    assert!(!fs.is_cached(path));
+    assert_eq!(fs.read(path).unwrap(), "a");
+    assert_eq!(fs.inner().reads(), 2);
+
+    assert!(!fs.refresh(path).unwrap());
+    fs.clear();
+    assert!(fs.refresh(path).unwrap());
+}
+
+#[test]
+fn caching_read_shared() {
+    let fs = CachingFileSystem::new(Memory::default().with("/a.glsl", "a"));
+    let path = Path::new("/a.glsl");
+
+    let first = fs.read_shared(path).unwrap();
+    let second = fs.read_shared(path).unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(fs.inner().reads(), 1);
+}
+
+#[test]
+fn caching_max_age() {
+    let fs = CachingFileSystem::new(Memory::default().with("/a.glsl", "a"))
+        .with_max_age(std::time::Duration::ZERO);
+    let path = Path::new("/a.glsl");
+
+    let first = fs.read_shared(path).unwrap();
+    let second = fs.read_shared(path).unwrap();
+    assert_eq!(fs.inner().reads(), 2);
+    // Unchanged contents are kept when revalidating
+    assert!(Arc::ptr_eq(&first, &second));
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn caching_parse_all() {
+    let mut processor = Processor::new_with_fs(CachingFileSystem::new(
+        Memory::default()
+            .with("/a.glsl", "#define A 1\n")
+            .with("/b.glsl", "#define B 2\n"),
+    ));
+
+    let file_ids = processor
+        .parse_all(["/a.glsl", "/b.glsl"].map(Path::new))
+        .unwrap();
+    assert_eq!(file_ids.len(), 2);
+    assert!(processor.fs.is_cached(Path::new("/a.glsl")));
+    assert!(processor.fs.is_cached(Path::new("/b.glsl")));
+    assert_eq!(processor.fs.inner().reads(), 2);
+}
+
+#[test]
+fn caching_watcher() {
+    let fs = CachingFileSystem::with_watcher(
+        Memory::default().with("/a.glsl", "a").with("/b.glsl", "b"),
+        ChangedWatcher("/b.glsl"),
+    );
+
+    for _ in 0..2 {
+        fs.read(Path::new("/a.glsl")).unwrap();
+        fs.read(Path::new("/b.glsl")).unwrap();
+    }
+
+    assert_eq!(fs.inner().reads(), 3);
+}
+
+#[test]
+fn statistics() {
+    let fs = StatisticsFileSystem::new(
+        Memory::default()
+            .with("/a.glsl", "abc")
+            .with("/b.glsl", "de"),
+    );
+
+    fs.canonicalize(Path::new("/a.glsl")).unwrap();
+    fs.read(Path::new("/a.glsl")).unwrap();
+    fs.read(Path::new("/b.glsl")).unwrap();
+    fs.read(Path::new("/a.glsl")).unwrap();
+    fs.read(Path::new("/c.glsl")).unwrap_err();
+
+    let stats = fs.stats();
+    assert_eq!(stats.canonicalize_calls(), 1);
+    assert_eq!(stats.read_calls(), 4);
+    assert_eq!(stats.repeated_reads(), 1);
+    assert_eq!(stats.bytes_read(), 8);
+    assert!(stats
+        .to_string()
+        .starts_with("1 canonicalize calls, 4 reads (1 repeated), 8 bytes read in "));
+
+    fs.reset();
+    assert_eq!(fs.stats().read_calls(), 0);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn statistics_parse_all() {
+    let mut processor = Processor::new_with_fs(StatisticsFileSystem::new(
+        Memory::default()
+            .with("/a.glsl", "#define A 1\n")
+            .with("/b.glsl", "#define B 2\n"),
+    ));
+
+    processor
+        .parse_all(["/a.glsl", "/b.glsl"].map(Path::new))
+        .unwrap();
+
+    let stats = processor.fs.stats();
+    assert_eq!(stats.read_calls(), 2);
+    assert_eq!(stats.repeated_reads(), 0);
+    assert_eq!(stats.bytes_read(), 24);
+}
+
+#[test]
+fn normalize_line_endings() {
+    let fs = NormalizingFileSystem::new(
+        Memory::default()
+            .with("/crlf.glsl", "#define A \\\r\n  1\r\nA\r\n")
+            .with("/lf.glsl", "A\n"),
+    );
+
+    assert_eq!(
+        fs.read(Path::new("/crlf.glsl")).unwrap(),
+        "#define A \\\n  1\nA\n"
+    );
+    assert!(matches!(
+        fs.read(Path::new("/lf.glsl")).unwrap(),
+        Cow::Borrowed("A\n")
+    ));
+}
+
+#[test]
+fn strip_bom() {
+    let source = "#version 460\nvoid main() {}\n";
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with("/bom.glsl", format!("\u{feff}{}", source))
+            .with("/plain.glsl", source),
+    );
+
+    let bom = processor.parse(Path::new("/bom.glsl")).unwrap().ast();
+    let plain = processor.parse(Path::new("/plain.glsl")).unwrap().ast();
+
+    assert_eq!(
+        format!("{:#?}", bom.into_inner().0),
+        format!("{:#?}", plain.into_inner().0)
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parse_all() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_GOOGLE_include_directive : require\n#include \"a.glsl\"\n#include \"b.glsl\"\nA B\n",
+            )
+            .with("/a.glsl", "#define A 1\n")
+            .with("/b.glsl", "#define B 2\n"),
+    );
+
+    let file_ids = processor
+        .parse_all(["/main.glsl", "/a.glsl", "/b.glsl", "/a.glsl"].map(Path::new))
+        .unwrap();
+    assert_eq!(file_ids.len(), 4);
+    assert_eq!(file_ids[1], file_ids[3]);
+    assert_eq!(processor.fs.reads(), 3);
+
+    let tokens: String = processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::builder().finish())
+        .filter_map(|event| match event.unwrap() {
+            Event::Token {
+                token,
+                masked: false,
+            } => Some(token.text().to_owned()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(tokens.trim(), "1 2");
+    assert_eq!(processor.fs.reads(), 3);
+}
+
+#[test]
+fn shared_cache() {
+    let cache = SharedFileCache::new();
+    let fs = || {
+        Memory::default()
+            .with("/a.glsl", "#define A 1\n")
+            .with("/b.glsl", "#define A 1\n")
+            .with("/c.glsl", "#define C 1\n")
+    };
+
+    let mut first = Processor::new_with_shared_cache(fs(), cache.clone());
+    first.parse(Path::new("/a.glsl")).unwrap();
+    first.parse(Path::new("/b.glsl")).unwrap();
+    assert_eq!(cache.len(), 1);
+
+    let mut second = Processor::new_with_shared_cache(fs(), cache.clone());
+    let ast = second.parse(Path::new("/a.glsl")).unwrap().ast();
+    assert_eq!(
+        ast.green_node(),
+        first
+            .parse(Path::new("/b.glsl"))
+            .unwrap()
+            .ast()
+            .green_node()
+    );
+
+    second.parse(Path::new("/c.glsl")).unwrap();
+    assert_eq!(cache.len(), 2);
+    assert!(cache.contains("#define C 1\n"));
+}
+
+#[test]
+fn shared_cache_hash_collision() {
+    let cache = SharedFileCache::new();
+
+    // Force both sources into the same bucket: each must get its own AST
+    let a = cache.get_or_parse_hashed(0, "#define A 1\n");
+    let b = cache.get_or_parse_hashed(0, "#define B 1\n");
+    assert_ne!(a.green_node(), b.green_node());
+    assert_eq!(cache.len(), 2);
+
+    let again = cache.get_or_parse_hashed(0, "#define A 1\n");
+    assert_eq!(again.green_node(), a.green_node());
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn shared_cache_eviction() {
+    let cache = SharedFileCache::with_capacity(2);
+
+    cache.get_or_parse("#define A 1\n");
+    cache.get_or_parse("#define B 1\n");
+    // Use A again, so B is the least recently used entry
+    cache.get_or_parse("#define A 1\n");
+    cache.get_or_parse("#define C 1\n");
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.contains("#define A 1\n"));
+    assert!(!cache.contains("#define B 1\n"));
+    assert!(cache.contains("#define C 1\n"));
+}
+
+#[test]
+fn file_id_namespaces() {
+    let fs = || Memory::default().with("/a.glsl", "");
+    let mut first = Processor::new_with_fs(fs()).with_namespace(FileIdNamespace::unique());
+    let mut second = Processor::new_with_fs(fs()).with_namespace(FileIdNamespace::unique());
+
+    let first_id = first.parse(Path::new("/a.glsl")).unwrap().file_id();
+    let second_id = second.parse(Path::new("/a.glsl")).unwrap().file_id();
+
+    assert_ne!(first_id, second_id);
+    assert_eq!(first_id.number(), second_id.number());
+    assert_eq!(first.resolve(first_id), Some(Path::new("/a.glsl")));
+    assert_eq!(first.resolve(second_id), None);
+}
+
+#[test]
+fn pragma_once_content() {
+    let common = "#pragma once\nint common;\n";
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_GOOGLE_include_directive : require
+#include \"a/common.glsl\"
+#include \"b/common.glsl\"
+#include \"a/common.glsl\"
+",
+            )
+            .with("/a/common.glsl", common)
+            .with("/b/common.glsl", common),
+    );
+
+    let entered = processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default())
+        .filter(|event| matches!(event, Ok(Event::EnterFile { .. })))
+        .count();
+
+    assert_eq!(entered, 2);
+}
+
+#[test]
+fn pragma_once_distinct_content() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_GOOGLE_include_directive : require
+#include \"a.glsl\"
+#include \"b.glsl\"
+#include \"a.glsl\"
+",
+            )
+            .with("/a.glsl", "#pragma once\nint a;\n")
+            .with("/b.glsl", "#pragma once\nint b;\n"),
+    );
+
+    let entered = processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default())
+        .filter(|event| matches!(event, Ok(Event::EnterFile { .. })))
+        .count();
+
+    assert_eq!(entered, 3);
+}
+
+#[test]
+fn pragma_once_hash_collision() {
+    let parse = |source| Parser::new(source).parse();
+    let a = parse("#pragma once\nint a;\n");
+    let b = parse("#pragma once\nint b;\n");
+
+    // Force both files into the same bucket: only identical contents may be skipped
+    let mut once = PragmaOnce::default();
+    once.insert_hashed(0, &a);
+    assert!(!once.contains_hashed(0, &b));
+    once.insert_hashed(0, &b);
+    assert!(once.contains_hashed(0, &parse("#pragma once\nint a;\n")));
+}
+
+#[test]
+fn pragma_once_masked() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_GOOGLE_include_directive : require
+#include \"a.glsl\"
+#include \"a.glsl\"
+#define ONCE
+#include \"b.glsl\"
+#include \"b.glsl\"
+",
+            )
+            .with("/a.glsl", "#if 0\n#pragma once\n#endif\nint a;\n")
+            .with("/b.glsl", "#ifdef ONCE\n#pragma once\n#endif\nint b;\n"),
+    );
+
+    let entered: Vec<_> = processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default())
+        .filter_map(|event| match event.unwrap() {
+            Event::EnterFile { path, .. } => Some(path),
+            _ => None,
+        })
+        .collect();
+
+    // Only the active #pragma once directive makes b.glsl include-once
+    assert_eq!(
+        entered,
+        [
+            Path::new("/main.glsl"),
+            Path::new("/a.glsl"),
+            Path::new("/a.glsl"),
+            Path::new("/b.glsl")
+        ]
+    );
+}
+
+#[test]
+fn process_str() {
+    let mut processor =
+        Processor::new_with_fs(Memory::default().with("/shaders/common.glsl", "#define A 1\n"));
+
+    let events: Vec<_> = processor
+        .process_str(
+            "#extension GL_GOOGLE_include_directive : require\n#include \"common.glsl\"\nA\n",
+            "/shaders/main.glsl",
+            ProcessorState::default(),
+        )
+        .map(Result::unwrap)
+        .collect();
+
+    let paths: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::EnterFile { path, .. } => Some(path.as_path()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            Path::new("/shaders/main.glsl"),
+            Path::new("/shaders/common.glsl")
+        ]
+    );
+
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::Token { token, masked: false } if token.text() == "1"
+    )));
+    assert_eq!(processor.fs.reads(), 1);
+}
+
+#[test]
+fn reset() {
+    let mut processor =
+        Processor::new_with_fs(Memory::default().with("/main.glsl", "#define A 1\n"));
+    let path = Path::new("/main.glsl");
+
+    let mut expand = processor
+        .parse(path)
+        .unwrap()
+        .process(ProcessorState::default());
+    for _ in expand.by_ref() {}
+    assert!(expand.into_state().unwrap().get_definition("A").is_some());
+
+    let file_id = processor.parse(path).unwrap().file_id();
+    assert_eq!(processor.fs.reads(), 1);
+
+    // Changed contents are only picked up after a reset
+    processor
+        .fs
+        .files
+        .insert(path.to_owned(), "#define B 2\n".to_owned());
+    processor.reset();
+    assert!(processor.canonical_paths.is_empty());
+
+    // File identifiers are not reused after a reset
+    let new_file_id = processor.parse(path).unwrap().file_id();
+    assert_ne!(new_file_id, file_id);
+
+    let mut expand = processor
+        .parse(path)
+        .unwrap()
+        .process(ProcessorState::default());
+    for _ in expand.by_ref() {}
+    let state = expand.into_state().unwrap();
+    assert!(state.get_definition("A").is_none());
+    assert!(state.get_definition("B").is_some());
+    assert_eq!(processor.fs.reads(), 2);
+}
+
+#[test]
+fn include_depth() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_GOOGLE_include_directive : require\n#include \"a.glsl\"\n",
+            )
+            .with("/a.glsl", "#include \"b.glsl\"\n")
+            .with("/b.glsl", "int b;\n"),
+    );
+
+    let events: Vec<_> = processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default())
+        .filter_map(|event| match event.unwrap() {
+            Event::EnterFile { path, depth, .. } => {
+                Some(format!("enter {} {}", path.display(), depth))
+            }
+            Event::ExitFile { depth, .. } => Some(format!("exit {}", depth)),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        events,
+        [
+            "enter /main.glsl 0",
+            "enter /a.glsl 1",
+            "enter /b.glsl 2",
+            "exit 2",
+            "exit 1",
+            "exit 0"
+        ]
+    );
+}
+
+#[test]
+fn include_stack() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_GOOGLE_include_directive : require\n#include \"a.glsl\"\n",
+            )
+            .with("/a.glsl", "#include \"missing.glsl\"\n"),
+    );
+
+    let mut expand = processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default());
+
+    let mut stack_at_error = None;
+    while let Some(event) = expand.next() {
+        if matches!(event, Err(_) | Ok(Event::Error { .. })) {
+            stack_at_error = Some(
+                expand
+                    .include_stack()
+                    .iter()
+                    .map(|(_, path)| path.clone())
+                    .collect::<Vec<_>>(),
+            );
+        }
+    }
+
+    assert_eq!(
+        stack_at_error.unwrap(),
+        [PathBuf::from("/main.glsl"), PathBuf::from("/a.glsl")]
+    );
+    assert!(expand.include_stack().is_empty());
+}
+
+#[test]
+fn include_both_modes() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_ARB_shading_language_include : enable
+#extension GL_GOOGLE_include_directive : enable
+#include \"a.glsl\"
+#include <b.glsl>
+",
+            )
+            .with("/a.glsl", "int a;\n"),
+    );
+
+    let events: Vec<_> = processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default())
+        .filter_map(|event| match event.unwrap() {
+            Event::EnterFile { path, .. } => Some(format!("enter {}", path.display())),
+            Event::Directive { directive, .. } if directive.errors().is_empty() => None,
+            Event::Directive { directive, .. } => Some(format!("{:?}", directive.errors())),
+            Event::Error { error, .. } => Some(error.to_string()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(events, ["enter /main.glsl", "enter /a.glsl"]);
+}
+
+#[test]
+fn token_file_ids() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_GOOGLE_include_directive : require\n#define B b\n#include \"a.glsl\"\nint m;\n",
+            )
+            .with("/a.glsl", "int B;\n"),
+    );
+
+    let mut tokens = Vec::new();
+    for event in processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default())
+    {
+        match event.unwrap() {
+            Event::Token { token, .. } if !token.kind().is_whitespace() => {
+                tokens.push((token.text().to_owned(), token.file_id()));
+            }
+            _ => {}
+        }
+    }
+
+    let mut file_id = |path: &str| processor.parse(Path::new(path)).unwrap().file_id();
+    let (main, a) = (file_id("/main.glsl"), file_id("/a.glsl"));
+    assert_eq!(
+        tokens,
+        [
+            ("int".to_owned(), a),
+            ("b".to_owned(), a),
+            (";".to_owned(), a),
+            ("int".to_owned(), main),
+            ("m".to_owned(), main),
+            (";".to_owned(), main),
+        ]
+    );
+}
+
+#[test]
+fn arb_named_strings() {
+    let mut processor = Processor::new_with_fs(Memory::default().with(
+        "/main.glsl",
+        "#extension GL_ARB_shading_language_include : require
+#include </lib/a.glsl>
+#include </lib/unknown.glsl>
+int m;
+",
+    ));
+
+    processor.add_named_string("/lib/a.glsl", "int a;\n");
+
+    let mut events = Vec::new();
+    for event in processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default())
+    {
+        match event.unwrap() {
+            Event::Token { token, .. } if !token.kind().is_whitespace() => {
+                events.push(token.text().to_owned());
+            }
+            Event::Directive { directive, .. } if !directive.errors().is_empty() => {
+                events.push(format!("{:?}", directive.errors()));
+            }
+            Event::Error { error, .. } => events.push(error.to_string()),
+            _ => {}
+        }
+    }
+
+    // Unknown names are left to the run-time
+    assert_eq!(events, ["int", "a", ";", "int", "m", ";"]);
+}
+
+#[test]
+fn named_string_definitions() {
+    let mut processor = Processor::new_with_fs(Memory::default().with(
+        "/main.glsl",
+        "#extension GL_ARB_shading_language_include : require
+#include </shaders/common.glsl>
+float x = SCALE(2.0);
+",
+    ));
+
+    let common = processor.add_named_string(
+        "/shaders/common.glsl",
+        "#define FACTOR 0.5\n#define SCALE(x) ((x) * FACTOR)\n",
+    );
+
+    let expand = |processor: &mut Processor<Memory>| {
+        let mut texts = Vec::new();
+        let mut files = Vec::new();
+        for event in processor
+            .parse(Path::new("/main.glsl"))
+            .unwrap()
+            .process(ProcessorState::default())
+        {
+            match event.unwrap() {
+                Event::Token { token, .. } if !token.kind().is_whitespace() => {
+                    texts.push(token.text().to_owned());
+                }
+                Event::EnterFile { file_id, .. } => files.push(file_id),
+                _ => {}
+            }
+        }
+
+        (texts.concat(), files)
+    };
+
+    let (text, files) = expand(&mut processor);
+    assert_eq!(text, "floatx=((2.0)*0.5);");
+    assert!(files.contains(&common));
+
+    // Once removed, the include is left to the run-time
+    assert!(processor.remove_named_string("/shaders/common.glsl"));
+    assert!(!processor.remove_named_string("/shaders/common.glsl"));
+
+    let (text, files) = expand(&mut processor);
+    assert_eq!(text, "floatx=SCALE(2.0);");
+    assert!(!files.contains(&common));
+}
+
+#[test]
+fn parsed_files() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_GOOGLE_include_directive : require\n#include \"a.glsl\"\n",
+            )
+            .with("/a.glsl", "int a;\n"),
+    );
+
+    for event in processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default())
+    {
+        event.unwrap();
+    }
+
+    let source = processor.parse_source("int s;\n", Path::new("/")).file_id();
+
+    assert_eq!(processor.file_count(), 3);
+
+    let mut paths: Vec<_> = processor
+        .parsed_file_ids()
+        .filter_map(|file_id| processor.path_for_file(file_id))
+        .collect();
+    paths.sort();
+    assert_eq!(paths, [Path::new("/a.glsl"), Path::new("/main.glsl")]);
+
+    assert!(processor.parsed_file_ids().any(|file_id| file_id == source));
+    assert_eq!(processor.path_for_file(source), None);
+}
+
+#[test]
+fn process_all() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#version 450\n#extension GL_GOOGLE_include_directive : require\n#include \"a.glsl\"\n#include \"missing.glsl\"\n",
+            )
+            .with("/a.glsl", "#if 0\n#error masked\n#endif\n#error visible\n"),
+    );
+
+    let output = processor
+        .process_all(Path::new("/main.glsl"), ProcessorState::default())
+        .unwrap();
+
+    assert_eq!(output.final_state.current_version().0, 450);
+    assert!(output
+        .final_state
+        .is_extension_active(&ExtNameAtom::from("GL_GOOGLE_include_directive")));
+    assert_eq!(output.diagnostics.len(), 1);
+    assert_eq!(
+        output.events.iter().filter(|event| event.is_err()).count(),
+        1
+    );
+
+    let errors = output.into_result().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors.to_string().contains("visible"));
+
+    assert!(processor
+        .process_all(Path::new("/missing.glsl"), ProcessorState::default())
+        .is_err());
+}
+
+#[test]
+fn process_multiple() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with("/vert.glsl", "#define SHARED 1\nvoid main() {}\n")
+            .with("/frag.glsl", "#if SHARED\nint x;\n#endif\n"),
+    );
+
+    let mut expand = processor
+        .process_multiple(
+            &[Path::new("/vert.glsl"), Path::new("/frag.glsl")],
+            ProcessorState::default(),
+        )
+        .unwrap();
+
+    let mut files = Vec::new();
+    let mut text = String::new();
+    for event in expand.by_ref() {
+        match event.unwrap() {
+            Event::EnterFile { path, depth, .. } => files.push((path, depth)),
+            Event::Token {
+                token,
+                masked: false,
+            } => text.push_str(token.text()),
+            _ => {}
+        }
+    }
+
+    assert_eq!(
+        files,
+        [
+            (PathBuf::from("/vert.glsl"), 0),
+            (PathBuf::from("/frag.glsl"), 0)
+        ]
+    );
+    assert!(text.contains("int x;"));
+    assert!(expand
+        .into_state()
+        .unwrap()
+        .get_definition("SHARED")
+        .is_some());
+
+    assert!(processor
+        .process_multiple(&[Path::new("/missing.glsl")], ProcessorState::default())
+        .is_err());
+    assert!(processor
+        .process_multiple(&[], ProcessorState::default())
+        .unwrap()
+        .next()
+        .is_none());
+}
+
+#[test]
+fn output_limit_across_includes() {
+    let mut processor = Processor::new_with_fs(
+        Memory::default()
+            .with(
+                "/main.glsl",
+                "#extension GL_GOOGLE_include_directive : require
+#define M x x x x x x x x x x
+#include \"a.glsl\"
+#include \"b.glsl\"
+",
+            )
+            .with("/a.glsl", "M\n")
+            .with("/b.glsl", "M\n"),
+    );
+
+    // Each include expands to 20 tokens, which only exceeds the limit once both are counted
+    let mut errors = Vec::new();
+    let mut text = String::new();
+    for event in processor
+        .parse(Path::new("/main.glsl"))
+        .unwrap()
+        .process(ProcessorState::default().with_max_output_tokens(30))
+    {
+        match event.unwrap() {
+            Event::Error { error, .. } => errors.push(error.into_inner()),
+            Event::Token {
+                token,
+                masked: false,
+            } => text.push_str(token.text()),
+            _ => {}
+        }
+    }
+
+    assert_eq!(
+        errors,
+        [ProcessingErrorKind::MacroExpansionLimit { limit: 30 }.into()]
+    );
+    assert_eq!(text.trim(), "x x x x x x x x x x\nM");
+}
+
+#[test]
+fn version_in_included_file() {
+    let errors = |main: &str| {
+        let mut processor = Processor::new_with_fs(
+            Memory::default()
+                .with("/main.glsl", main)
+                .with("/a.glsl", "#version 450 core\nint a;\n"),
+        );
+
+        processor
+            .parse(Path::new("/main.glsl"))
+            .unwrap()
+            .process(ProcessorState::default().with_version_checks(true))
+            .filter_map(|event| match event.unwrap() {
+                Event::Directive { directive, .. } => Some(directive.into_errors()),
+                _ => None,
+            })
+            .flatten()
+            .map(|error| error.into_inner())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        errors(
+            "#version 450 core
+#extension GL_GOOGLE_include_directive : require
+#include \"a.glsl\"
+"
+        ),
+        [ProcessingErrorKind::DuplicateVersion.into()]
+    );
+    assert_eq!(
+        errors(
+            "#extension GL_GOOGLE_include_directive : require
+#include \"a.glsl\"
+"
+        ),
+        [ProcessingErrorKind::VersionNotFirst.into()]
+    );
+}
@@ -1,7 +1,7 @@
 use std::{borrow::Cow, cmp::Ordering, convert::TryFrom, str::FromStr};
 
 use arrayvec::ArrayVec;
-use rowan::NodeOrToken;
+use rowan::{GreenNode, NodeOrToken};
 use thiserror::Error;
 
 use lang_util::{FileId, SmolStr};
@@ -321,6 +321,17 @@ impl ExtensionBehavior {
     }
 }
 
+impl std::fmt::Display for ExtensionBehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtensionBehavior::Require => write!(f, "require"),
+            ExtensionBehavior::Enable => write!(f, "enable"),
+            ExtensionBehavior::Warn => write!(f, "warn"),
+            ExtensionBehavior::Disable => write!(f, "disable"),
+        }
+    }
+}
+
 impl FromStr for ExtensionBehavior {
     type Err = ();
 
@@ -424,6 +435,11 @@ impl Extension {
             behavior: ExtensionBehavior::Disable,
         }
     }
+
+    /// Return the `#extension` directive for this extension, including the trailing newline
+    pub fn to_directive_string(&self) -> String {
+        format!("#extension {} : {}\n", self.name, self.behavior)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
@@ -484,22 +500,81 @@ impl TryFrom<(FileId, SyntaxNode)> for Extension {
     }
 }
 
+/// Return true if `s` is a valid macro or macro argument name
+pub(crate) fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replacement list of a macro
+///
+/// The tokens are stored as a green node, which unlike [SyntaxNode] can be shared between
+/// threads. Substituted tokens take the range of the macro invocation, so the position of the
+/// body in its source file is not kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DefineBody {
+    tokens: GreenNode,
+}
+
+impl DefineBody {
+    fn node(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.tokens.clone())
+    }
+}
+
+impl From<SyntaxNode> for DefineBody {
+    fn from(node: SyntaxNode) -> Self {
+        Self {
+            tokens: node.green().into_owned(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DefineObject {
-    tokens: SyntaxNode,
+    body: DefineBody,
 }
 
 impl DefineObject {
     pub fn new(tokens: SyntaxNode) -> Self {
-        Self { tokens }
+        Self {
+            body: tokens.into(),
+        }
     }
 
     pub fn one() -> Self {
-        Self::from_str("1").unwrap()
+        Self::from_int(1)
+    }
+
+    /// Create a body made of a single integer constant, without going through the parser
+    ///
+    /// Negative values are represented as a `-` token followed by the digits.
+    pub fn from_int(value: i64) -> Self {
+        let mut builder = rowan::GreenNodeBuilder::new();
+        builder.start_node(PP_DEFINE_BODY.into());
+        if value < 0 {
+            builder.token(DASH.into(), "-");
+        }
+        builder.token(DIGITS.into(), &value.unsigned_abs().to_string());
+        builder.finish_node();
+
+        Self {
+            body: DefineBody {
+                tokens: builder.finish(),
+            },
+        }
     }
 
-    pub fn body(&self) -> &SyntaxNode {
-        &self.tokens
+    /// Return the replacement list of this macro
+    ///
+    /// The returned node is the root of a new tree: token ranges are relative to the start of the
+    /// body.
+    pub fn body(&self) -> SyntaxNode {
+        self.body.node()
     }
 }
 
@@ -507,31 +582,55 @@ impl FromStr for DefineObject {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {
-            tokens: crate::parser::Parser::new(s)
+        Ok(Self::new(
+            crate::parser::Parser::new(s)
                 .parse_define_body()
                 .ok_or(())?,
-        })
+        ))
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DefineFunction {
     args: Vec<SmolStr>,
-    tokens: SyntaxNode,
+    body: DefineBody,
 }
 
 impl DefineFunction {
     pub fn new(args: Vec<SmolStr>, tokens: SyntaxNode) -> Self {
-        Self { args, tokens }
+        Self {
+            args,
+            body: tokens.into(),
+        }
+    }
+
+    /// Parse a function-like macro from its argument names and body
+    ///
+    /// Returns `None` if an argument is not a valid identifier, is repeated, or if the body
+    /// cannot be parsed.
+    pub fn parse(args: &[&str], body: &str) -> Option<Self> {
+        for (i, arg) in args.iter().enumerate() {
+            if !is_identifier(arg) || args[..i].contains(arg) {
+                return None;
+            }
+        }
+
+        Some(Self::new(
+            args.iter().map(|arg| SmolStr::from(*arg)).collect(),
+            crate::parser::Parser::new(body).parse_define_body()?,
+        ))
     }
 
     pub fn arg_names(&self) -> &[SmolStr] {
         &self.args
     }
 
-    pub fn body(&self) -> &SyntaxNode {
-        &self.tokens
+    /// Return the replacement list of this macro
+    ///
+    /// The returned node is the root of a new tree: token ranges are relative to the start of the
+    /// body.
+    pub fn body(&self) -> SyntaxNode {
+        self.body.node()
     }
 }
 
@@ -580,6 +679,59 @@ impl Define {
     pub fn protected(&self) -> bool {
         self.protected
     }
+
+    /// Return true if `other` has the same name, parameters and replacement list as this
+    /// definition
+    ///
+    /// Whitespace in the replacement lists is not significant.
+    pub fn same_tokens(&self, other: &Define) -> bool {
+        self.name == other.name
+            && std::mem::discriminant(&self.kind) == std::mem::discriminant(&other.kind)
+            && self.arg_names() == other.arg_names()
+            && self
+                .body_tokens()
+                .map(|token| (token.kind(), token.text().to_owned()))
+                .eq(other
+                    .body_tokens()
+                    .map(|token| (token.kind(), token.text().to_owned())))
+    }
+
+    /// Return the name of this definition, followed by its parameter list for function-like
+    /// macros (`F(x, y)`)
+    pub fn signature(&self) -> SmolStr {
+        match self.kind() {
+            DefineKind::Object(_) => self.name.clone(),
+            DefineKind::Function(function) => {
+                format!("{}({})", self.name, function.arg_names().join(", ")).into()
+            }
+        }
+    }
+
+    /// Return the replacement list of this definition, with its tokens separated by single
+    /// spaces
+    pub fn replacement_text(&self) -> String {
+        self.body_tokens()
+            .map(|token| Unescaped::new(token.text()).to_string().into_owned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn arg_names(&self) -> &[SmolStr] {
+        match self.kind() {
+            DefineKind::Object(_) => &[],
+            DefineKind::Function(function) => function.arg_names(),
+        }
+    }
+
+    fn body_tokens(&self) -> impl Iterator<Item = SyntaxToken> {
+        match self.kind() {
+            DefineKind::Object(object) => object.body(),
+            DefineKind::Function(function) => function.body(),
+        }
+        .descendants_with_tokens()
+        .filter_map(NodeOrToken::into_token)
+        .filter(|token| !token.kind().is_whitespace())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
@@ -1205,6 +1357,7 @@ pub enum ParsedPragma {
     StdGl(SyntaxNode),
     Optimize(bool),
     Debug(bool),
+    Once,
     Unknown(SyntaxNode),
 }
 
@@ -1254,6 +1407,12 @@ impl TryFrom<(FileId, SyntaxNode)> for Pragma {
                             Err(Self::Error::IncorrectSyntax { name })
                         };
                     }
+                    "once" if tokens.len() == 1 => {
+                        return Ok(Self {
+                            value: ParsedPragma::Once,
+                            raw,
+                        });
+                    }
                     "debug" => {
                         return if let Some(value) = Self::parse_function_pragma(&tokens) {
                             Ok(Self {
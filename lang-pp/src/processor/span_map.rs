@@ -0,0 +1,122 @@
+//! Mapping of expanded output positions to the original source
+//!
+//! Tokens resulting from a macro expansion carry the span of the macro definition they come from.
+//! A [SpanMapper] instead maps them to the macro invocation in the source that was processed,
+//! which is where errors on the expanded output should be reported.
+
+use lang_util::{position::NodeSpan, FileId, TextRange, TextSize};
+
+use super::event::Event;
+
+/// Map from ranges of the expanded output to the source
+///
+/// The expanded output is the concatenation of the text of the unmasked [Event::Token] events.
+/// Macro invocations are tracked using the [Event::EnterMacro] and [Event::ExitMacro] events, so
+/// the processor state should be created with
+/// [with_macro_events](super::ProcessorState::with_macro_events).
+#[derive(Default, Debug, Clone)]
+pub struct SpanMapper {
+    /// Ranges of the expanded output and their original span, in output order
+    segments: Vec<(TextRange, NodeSpan)>,
+    /// Invocation spans of the macros being expanded
+    macro_stack: Vec<NodeSpan>,
+    /// Length of the expanded output so far
+    offset: TextSize,
+}
+
+impl SpanMapper {
+    /// Create a new, empty mapper
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an event of the expansion
+    pub fn push(&mut self, event: &Event) {
+        match event {
+            Event::EnterMacro {
+                invocation_span, ..
+            } => {
+                self.macro_stack.push(*invocation_span);
+            }
+            Event::ExitMacro { .. } => {
+                self.macro_stack.pop();
+            }
+            Event::Token {
+                token,
+                masked: false,
+            } => {
+                let range = TextRange::at(self.offset, TextSize::of(token.text()));
+                if range.is_empty() {
+                    return;
+                }
+
+                // Nested expansions are attributed to the outermost invocation
+                let original = self
+                    .macro_stack
+                    .first()
+                    .copied()
+                    .unwrap_or_else(|| token.text_range());
+
+                match self.segments.last_mut() {
+                    Some((last, last_original)) if *last_original == original => {
+                        *last = last.cover(range);
+                    }
+                    _ => self.segments.push((range, original)),
+                }
+
+                self.offset = range.end();
+            }
+            _ => {}
+        }
+    }
+
+    /// Return the length of the expanded output recorded so far
+    pub fn expanded_len(&self) -> TextSize {
+        self.offset
+    }
+
+    /// Map a range of the expanded output to the file and range it originates from
+    ///
+    /// If the range spans multiple tokens, the result covers their original ranges. Returns
+    /// `None` if the range is outside of the recorded output.
+    pub fn map_to_original(&self, expanded_span: TextRange) -> Option<(FileId, TextRange)> {
+        let find = |offset: TextSize| {
+            let index = self
+                .segments
+                .partition_point(|(range, _)| range.end() <= offset);
+            self.segments
+                .get(index)
+                .filter(|(range, _)| range.contains(offset))
+                .map(|(_, original)| *original)
+        };
+
+        let start = find(expanded_span.start())?;
+        let end = if expanded_span.is_empty() {
+            start
+        } else {
+            find(expanded_span.end() - TextSize::from(1)).unwrap_or(start)
+        };
+
+        if start.source_id() == end.source_id() {
+            Some((start.source_id(), start.range().cover(end.range())))
+        } else {
+            Some((start.source_id(), start.range()))
+        }
+    }
+}
+
+impl<'e> Extend<&'e Event> for SpanMapper {
+    fn extend<T: IntoIterator<Item = &'e Event>>(&mut self, iter: T) {
+        for event in iter {
+            self.push(event);
+        }
+    }
+}
+
+impl<'e> FromIterator<&'e Event> for SpanMapper {
+    fn from_iter<T: IntoIterator<Item = &'e Event>>(iter: T) -> Self {
+        let mut mapper = Self::new();
+        mapper.extend(iter);
+        mapper
+    }
+}
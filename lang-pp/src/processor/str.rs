@@ -24,7 +24,11 @@ pub enum ProcessStrError {
 }
 
 pub fn process(input: &str, state: ProcessorState) -> ExpandStr {
-    let file_id = FileId::new(0);
+    process_with_file_id(input, state, FileId::new(0))
+}
+
+/// Process the given input, using `file_id` as the source identifier of the resulting tokens
+pub fn process_with_file_id(input: &str, state: ProcessorState, file_id: FileId) -> ExpandStr {
     let ast = parser::Parser::new(input).parse();
     ExpandStr {
         inner: ExpandOne::new((file_id, ast), state),
@@ -56,17 +60,24 @@ impl Iterator for ExpandStr {
     type Item = Result<Event, Located<ProcessStrError>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let event = self.inner.next()?;
-        match event {
-            ExpandEvent::Event(event) => Some(Ok(event)),
-            ExpandEvent::EnterFile(node, path) => Some(Err(LocatedBuilder::new()
-                .pos(node.text_range())
-                .resolve_file(self.inner.location())
-                .finish(ProcessStrError::IncludeRequested(path)))),
-            ExpandEvent::Completed(state) => {
-                self.final_state = Some(state);
-                None
-            }
+        loop {
+            let event = self.inner.next()?;
+            return match event {
+                ExpandEvent::Event(event) => Some(Ok(event)),
+                ExpandEvent::EnterFile(node, path) => Some(Err(LocatedBuilder::new()
+                    .pos(node.text_range())
+                    .resolve_file(self.inner.location())
+                    .finish(ProcessStrError::IncludeRequested(path)))),
+                ExpandEvent::EnterNamedString(..) => {
+                    // No named strings without a processor, the directive is left to the
+                    // run-time
+                    continue;
+                }
+                ExpandEvent::Completed(state) => {
+                    self.final_state = Some(state);
+                    None
+                }
+            };
         }
     }
 }
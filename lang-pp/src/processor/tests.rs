@@ -0,0 +1,886 @@
+use std::sync::Arc;
+
+use crate::{
+    exts::{names::ExtNameAtom, ExtensionConflictRegistry, ExtensionDependencyRegistry},
+    last::LocatedIterator,
+};
+
+use super::{
+    event::{DirectiveKind, ErrorKind, Event, ProcessingErrorKind},
+    nodes::{ExtensionBehavior, ExtensionName},
+    Definition, GlslProfile, IncludeMode, LineDirectiveMode, MacroDefinitionError, MergeConflict,
+    ProcessorState,
+};
+
+fn process(input: &str, state: ProcessorState) -> ProcessorState {
+    let mut expand = super::str::process(input, state);
+    for _ in expand.by_ref() {}
+    expand.into_state().expect("processing did not complete")
+}
+
+fn active_extensions(state: &ProcessorState) -> Vec<String> {
+    state
+        .active_extensions()
+        .map(|extension| extension.name.to_string())
+        .collect()
+}
+
+fn directive_errors(input: &str, state: ProcessorState) -> (Vec<ErrorKind>, ProcessorState) {
+    let mut expand = super::str::process(input, state);
+
+    let errors = expand
+        .by_ref()
+        .filter_map(|event| match event.unwrap() {
+            Event::Directive { directive, .. } => Some(directive.into_errors()),
+            _ => None,
+        })
+        .flatten()
+        .map(|error| error.into_inner())
+        .collect();
+
+    (errors, expand.into_state().unwrap())
+}
+
+#[test]
+fn active_extensions_last_wins() {
+    let state = process(
+        "#extension GL_OES_texture_3D : enable
+#extension GL_ARB_gpu_shader5 : require
+#extension GL_OES_texture_3D : disable
+#extension GL_EXT_ray_query : warn
+",
+        ProcessorState::default(),
+    );
+
+    assert_eq!(
+        active_extensions(&state),
+        ["GL_ARB_gpu_shader5", "GL_EXT_ray_query"]
+    );
+    assert!(!state.is_extension_active(&ExtNameAtom::from("GL_OES_texture_3D")));
+}
+
+#[test]
+fn dependencies_enabled() {
+    let registry = ExtensionDependencyRegistry::new()
+        .depends_on("GL_ARB_gpu_shader5", ["GL_ARB_sample_shading"])
+        .depends_on(
+            "GL_ARB_sample_shading",
+            ["GL_ARB_gpu_shader5", "GL_OES_texture_3D"],
+        );
+
+    let state = process(
+        "#extension GL_ARB_gpu_shader5 : enable\n",
+        ProcessorState::default().with_dependency_registry(Arc::new(registry)),
+    );
+
+    assert_eq!(
+        active_extensions(&state),
+        [
+            "GL_ARB_gpu_shader5",
+            "GL_ARB_sample_shading",
+            "GL_OES_texture_3D"
+        ]
+    );
+    assert!(state
+        .active_extensions()
+        .all(|extension| extension.name != ExtensionName::All));
+}
+
+#[test]
+fn dependencies_not_enabled_by_warn() {
+    let registry =
+        ExtensionDependencyRegistry::new().depends_on("GL_ARB_gpu_shader5", ["GL_OES_texture_3D"]);
+
+    let state = process(
+        "#extension GL_ARB_gpu_shader5 : warn\n",
+        ProcessorState::default().with_dependency_registry(Arc::new(registry)),
+    );
+
+    assert_eq!(active_extensions(&state), ["GL_ARB_gpu_shader5"]);
+}
+
+#[test]
+fn conflicting_extensions() {
+    let registry =
+        ExtensionConflictRegistry::new().conflict("GL_EXT_ray_query", "GL_NV_ray_tracing");
+
+    let (errors, state) = directive_errors(
+        "#extension GL_EXT_ray_query : enable
+#extension GL_NV_ray_tracing : enable
+",
+        ProcessorState::default().with_conflict_registry(Arc::new(registry)),
+    );
+
+    assert_eq!(
+        errors,
+        [ErrorKind::Processing(
+            ProcessingErrorKind::ConflictingExtensions {
+                enabled: ExtNameAtom::from("GL_NV_ray_tracing"),
+                conflicting: ExtNameAtom::from("GL_EXT_ray_query"),
+            }
+        )]
+    );
+
+    assert_eq!(active_extensions(&state), ["GL_EXT_ray_query"]);
+}
+
+#[test]
+fn promoted_extensions() {
+    let input = "#version 330 core
+#extension GL_ARB_explicit_attrib_location : enable
+#extension GL_ARB_gpu_shader5 : enable
+";
+
+    let (errors, state) = directive_errors(
+        input,
+        ProcessorState::default().with_promotion_table(Arc::new(Default::default())),
+    );
+
+    assert_eq!(
+        errors,
+        [ErrorKind::ExtensionRedundant {
+            extension: ExtNameAtom::from("GL_ARB_explicit_attrib_location"),
+            core_version: 330,
+        }]
+    );
+    assert_eq!(active_extensions(&state), ["GL_ARB_gpu_shader5"]);
+
+    // Without a promotion table, extensions are always recorded
+    let (errors, state) = directive_errors(input, ProcessorState::default());
+    assert!(errors.is_empty());
+    assert_eq!(
+        active_extensions(&state),
+        ["GL_ARB_explicit_attrib_location", "GL_ARB_gpu_shader5"]
+    );
+}
+
+#[test]
+fn extension_directives() {
+    let state = process(
+        "#extension GL_OES_texture_3D : enable
+#extension GL_ARB_gpu_shader5 : require
+#extension GL_EXT_ray_query : warn
+#extension GL_OES_texture_3D : disable
+",
+        ProcessorState::default(),
+    );
+
+    assert_eq!(
+        state.extension_directives(),
+        "#extension GL_ARB_gpu_shader5 : require\n#extension GL_EXT_ray_query : warn\n"
+    );
+}
+
+#[test]
+fn required_extensions() {
+    let state = process(
+        "#extension GL_OES_texture_3D : enable
+#extension GL_ARB_gpu_shader5 : require
+#extension GL_EXT_ray_query : require
+#extension GL_EXT_ray_query : enable
+",
+        ProcessorState::default(),
+    );
+
+    assert_eq!(
+        active_extensions(&state),
+        [
+            "GL_OES_texture_3D",
+            "GL_ARB_gpu_shader5",
+            "GL_EXT_ray_query"
+        ]
+    );
+    assert_eq!(
+        state
+            .required_extensions()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>(),
+        ["GL_ARB_gpu_shader5"]
+    );
+}
+
+#[test]
+fn builtin_definitions_protected() {
+    let (errors, state) = directive_errors(
+        "#define __LINE__ 1\n#undef __FILE__\n",
+        ProcessorState::default(),
+    );
+
+    assert_eq!(
+        errors,
+        [
+            ErrorKind::Processing(ProcessingErrorKind::ProtectedDefine {
+                ident: "__LINE__".into(),
+                is_undef: false,
+            }),
+            ErrorKind::Processing(ProcessingErrorKind::ProtectedDefine {
+                ident: "__FILE__".into(),
+                is_undef: true,
+            })
+        ]
+    );
+    assert!(state.get_definition("__LINE__").is_some());
+    assert!(state.get_definition("__VERSION__").is_some());
+}
+
+fn expand_tokens(input: &str) -> String {
+    super::str::process(input, ProcessorState::default())
+        .filter_map(|event| match event.unwrap() {
+            Event::Token {
+                token,
+                masked: false,
+            } => Some(token.text().to_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn macro_rescan() {
+    assert_eq!(
+        expand_tokens("#define A B\n#define B 42\nint x = A;\n"),
+        "int x = 42;\n"
+    );
+    assert_eq!(
+        expand_tokens("#define F(x) G(x) + 1\n#define G(x) (x * 2)\nint y = F(3);\n"),
+        "int y = (3 * 2) + 1;\n"
+    );
+
+    // The originating macro is not expanded again
+    assert_eq!(
+        expand_tokens("#define A A + 1\nint z = A;\n"),
+        "int z = A + 1;\n"
+    );
+    assert_eq!(
+        expand_tokens("#define A B\n#define B A\nint w = A;\n"),
+        "int w = A;\n"
+    );
+}
+
+#[test]
+fn defines_map() {
+    let state = process(
+        "#define A 1
+#define F(x,  y)   x+y * \\
+    2
+#define EMPTY
+#undef EMPTY
+#define E
+",
+        ProcessorState::default(),
+    );
+
+    let map = state.into_defines_map();
+    assert_eq!(map.len(), 3);
+    assert_eq!(map["A"], "1");
+    assert_eq!(map["F(x, y)"], "x + y * 2");
+    assert_eq!(map["E"], "");
+}
+
+#[test]
+fn version_placement() {
+    let (errors, _) = directive_errors(
+        "// comment\n#version 450 core\nint x;\n",
+        ProcessorState::default().with_version_checks(true),
+    );
+    assert!(errors.is_empty());
+
+    let input = "int x;\n#version 330 core\n#version 450 core\n";
+    let (errors, _) = directive_errors(input, ProcessorState::default());
+    assert!(errors.is_empty());
+
+    let (errors, state) =
+        directive_errors(input, ProcessorState::default().with_version_checks(true));
+    assert_eq!(
+        errors,
+        [
+            ErrorKind::Processing(ProcessingErrorKind::VersionNotFirst),
+            ErrorKind::Processing(ProcessingErrorKind::DuplicateVersion)
+        ]
+    );
+    assert_eq!(state.version.number, 450);
+}
+
+#[test]
+fn version_after_directive() {
+    let (errors, _) = directive_errors(
+        "#define X\n#version 450 core\n",
+        ProcessorState::default().with_version_checks(true),
+    );
+    assert_eq!(
+        errors,
+        [ErrorKind::Processing(ProcessingErrorKind::VersionNotFirst)]
+    );
+}
+
+#[test]
+fn macro_expansion_limit() {
+    // Each level doubles the size of the expansion
+    let mut input = String::from("#define M0 x\n");
+    for i in 1..32 {
+        input.push_str(&format!("#define M{} M{} M{}\n", i, i - 1, i - 1));
+    }
+    input.push_str("M31 M1\n");
+
+    let events: Vec<_> = super::str::process(
+        &input,
+        ProcessorState::default().with_max_output_tokens(1000),
+    )
+    .filter_map(|event| match event.unwrap() {
+        Event::Error { error, .. } => Some(error.into_inner().to_string()),
+        Event::Token { token, .. } => Some(token.text().to_owned()),
+        _ => None,
+    })
+    .collect();
+
+    assert_eq!(
+        events.concat(),
+        format!(
+            "{}M31 M1\n",
+            ProcessingErrorKind::MacroExpansionLimit { limit: 1000 }
+        )
+    );
+}
+
+#[test]
+fn resolved_definitions() {
+    let state = process("#define F(a, b) a + b\n", ProcessorState::default());
+
+    let f = state.get_definition("F").unwrap();
+    assert_eq!(f.name(), "F");
+    assert!(!f.object_like());
+    assert_eq!(f.arg_count(), 2);
+    assert!(!f.protected());
+
+    assert_eq!(state.get_definition("__LINE__"), Some(&Definition::Line));
+    assert!(state.get_definition("G").is_none());
+}
+
+#[test]
+fn define_function() {
+    let mut state = ProcessorState::default();
+    assert_eq!(
+        state.define_function("ADD", &["a", "b"], "((a) + (b))"),
+        Ok(())
+    );
+    assert_eq!(
+        state.define_function("BAD", &["a", "a"], "a"),
+        Err(MacroDefinitionError::InvalidDefinition { name: "BAD".into() })
+    );
+    assert_eq!(
+        state.define_function("BAD", &["1a"], "a"),
+        Err(MacroDefinitionError::InvalidDefinition { name: "BAD".into() })
+    );
+    assert_eq!(
+        state.define_function("1BAD", &["a"], "a"),
+        Err(MacroDefinitionError::InvalidName {
+            name: "1BAD".into()
+        })
+    );
+    assert_eq!(
+        state.define_function("__LINE__", &["a"], "a"),
+        Err(MacroDefinitionError::ProtectedName {
+            name: "__LINE__".into()
+        })
+    );
+    assert_eq!(
+        state.define_function("GL_ADD", &["a"], "a"),
+        Err(MacroDefinitionError::ProtectedName {
+            name: "GL_ADD".into()
+        })
+    );
+
+    let output: String = super::str::process("int x = ADD(1, 2);\n", state)
+        .filter_map(|event| match event.unwrap() {
+            Event::Token { token, .. } => Some(token.text().to_owned()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(output, "int x = ((1) + (2));\n");
+}
+
+#[test]
+fn define_object_macro() {
+    let mut state = ProcessorState::default();
+    assert_eq!(state.define_object_macro("VALUE", 42), Ok(()));
+    assert_eq!(state.define_object_macro("NEGATIVE", -7), Ok(()));
+    assert_eq!(state.define_bool_macro("ENABLED"), Ok(()));
+    assert_eq!(
+        state.define_object_macro("GL_VALUE", 1),
+        Err(MacroDefinitionError::ProtectedName {
+            name: "GL_VALUE".into()
+        })
+    );
+    assert_eq!(
+        state.define_bool_macro("__VERSION__"),
+        Err(MacroDefinitionError::ProtectedName {
+            name: "__VERSION__".into()
+        })
+    );
+    assert_eq!(
+        state.define_object_macro("1abc", 1),
+        Err(MacroDefinitionError::InvalidName {
+            name: "1abc".into()
+        })
+    );
+    assert_eq!(
+        state.define_bool_macro("a b"),
+        Err(MacroDefinitionError::InvalidName { name: "a b".into() })
+    );
+    assert!(state.get_definition("1abc").is_none());
+
+    let output: String = super::str::process(
+        "#if ENABLED && VALUE == 42 && NEGATIVE < 0\nint x = VALUE - NEGATIVE;\n#endif\n",
+        state,
+    )
+    .filter_map(|event| match event.unwrap() {
+        Event::Token { token, .. } => Some(token.text().to_owned()),
+        _ => None,
+    })
+    .collect();
+    assert_eq!(output, "int x = 42 - -7;\n");
+}
+
+#[test]
+fn substituted_token_spans() {
+    let input = "#define A 1 + 2\n#define F(x) x ## 3 + x\nA F(4)\n";
+
+    let tokens: Vec<_> = super::str::process(input, ProcessorState::default())
+        .filter_map(|event| match event.unwrap() {
+            Event::Token { token, .. } if !token.text().trim().is_empty() => {
+                let range = token.text_range().range();
+                Some((
+                    token.text().to_owned(),
+                    usize::from(range.start())..usize::from(range.end()),
+                ))
+            }
+            _ => None,
+        })
+        .collect();
+
+    // Substituted tokens take the range of the macro invocation, not of the macro body
+    let a = input.rfind("A ").unwrap();
+    let f = input.rfind("F(4)").unwrap();
+    assert_eq!(
+        tokens,
+        [
+            ("1".to_owned(), a..a + 1),
+            ("+".to_owned(), a..a + 1),
+            ("2".to_owned(), a..a + 1),
+            ("43".to_owned(), f..f + 4),
+            ("+".to_owned(), f..f + 4),
+            ("4".to_owned(), f..f + 4),
+        ]
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn shared_state() {
+    use std::sync::Mutex;
+
+    let state = process(
+        "#define SCALE 2\n#define TWICE(x) ((x) * SCALE)\n",
+        ProcessorState::default(),
+    );
+
+    let outputs = Mutex::new(Vec::new());
+    rayon::scope(|scope| {
+        for (i, source) in ["SCALE\n", "TWICE(3)\n"].into_iter().enumerate() {
+            let (state, outputs) = (&state, &outputs);
+
+            scope.spawn(move |_| {
+                let output: String = super::str::process(source, state.clone())
+                    .filter_map(|event| match event.unwrap() {
+                        Event::Token { token, .. } => Some(token.text().to_owned()),
+                        _ => None,
+                    })
+                    .collect();
+
+                outputs.lock().unwrap().push((i, output));
+            });
+        }
+    });
+
+    let mut outputs = outputs.into_inner().unwrap();
+    outputs.sort();
+    assert_eq!(
+        outputs,
+        [(0, "2\n".to_owned()), (1, "((3) * 2)\n".to_owned())]
+    );
+}
+
+#[test]
+fn merge_states() {
+    let header = process(
+        "#version 330 core
+#define SCALE 2.0
+#define TWICE(x) ((x) * 2)
+#extension GL_ARB_gpu_shader5 : enable
+",
+        ProcessorState::default(),
+    );
+
+    let shader = process(
+        "#version 450 core
+#define SCALE   2.0
+#define OFFSET 1.0
+#extension GL_GOOGLE_include_directive : enable
+",
+        ProcessorState::default(),
+    );
+
+    let merged = header.clone().merge(shader).unwrap();
+    assert_eq!(merged.version.number, 450);
+    assert!(matches!(
+        merged.include_mode,
+        IncludeMode::GoogleInclude { .. }
+    ));
+    assert!(merged.get_definition("TWICE").is_some());
+    assert!(merged.get_definition("OFFSET").is_some());
+    assert_eq!(
+        active_extensions(&merged),
+        ["GL_ARB_gpu_shader5", "GL_GOOGLE_include_directive"]
+    );
+
+    let conflicting = process(
+        "#define SCALE 3.0\n#define TWICE(y) ((y) * 2)\n",
+        ProcessorState::default(),
+    );
+    assert_eq!(
+        header.merge(conflicting).unwrap_err(),
+        MergeConflict {
+            names: vec!["SCALE".into(), "TWICE".into()]
+        }
+    );
+}
+
+#[test]
+fn merge_include_modes() {
+    let arb = process(
+        "#extension GL_ARB_shading_language_include : warn\n",
+        ProcessorState::default(),
+    );
+    let google = process(
+        "#extension GL_GOOGLE_include_directive : enable\n",
+        ProcessorState::default(),
+    );
+
+    let expected = IncludeMode::Both {
+        arb_warn: true,
+        google_warn: false,
+    };
+    assert_eq!(
+        arb.clone().merge(google.clone()).unwrap().include_mode,
+        expected
+    );
+    assert_eq!(google.merge(arb).unwrap().include_mode, expected);
+}
+
+#[test]
+fn diff_states() {
+    let before = process(
+        "#define A 1
+#define B 2
+#define C 3
+#extension GL_ARB_gpu_shader5 : enable
+#extension GL_EXT_ray_query : enable
+",
+        ProcessorState::default(),
+    );
+
+    let after = process(
+        "#define A 1
+#define C 4
+#define D 5
+#extension GL_ARB_gpu_shader5 : warn
+#extension GL_OES_texture_3D : enable
+",
+        ProcessorState::default(),
+    );
+
+    let diff = ProcessorState::diff(&before, &after);
+    assert_eq!(diff.added, ["D"]);
+    assert_eq!(diff.removed, ["B"]);
+    assert_eq!(diff.changed, ["C"]);
+    assert_eq!(
+        diff.extensions_added,
+        [ExtNameAtom::from("GL_OES_texture_3D")]
+    );
+    assert_eq!(
+        diff.extensions_removed,
+        [ExtNameAtom::from("GL_EXT_ray_query")]
+    );
+    assert_eq!(
+        diff.extensions_changed,
+        [ExtNameAtom::from("GL_ARB_gpu_shader5")]
+    );
+
+    assert!(ProcessorState::diff(&before, &before).is_empty());
+}
+
+#[test]
+fn macro_events() {
+    let input = "#define A 1\nint x = A;\n";
+
+    let events: Vec<_> =
+        super::str::process(input, ProcessorState::default().with_macro_events(true))
+            .filter_map(|event| match event.unwrap() {
+                Event::EnterMacro { name, .. } => Some(format!("enter {}", name)),
+                Event::ExitMacro { name } => Some(format!("exit {}", name)),
+                Event::Token {
+                    token,
+                    masked: false,
+                } if token.text() == "1" => Some("1".to_owned()),
+                _ => None,
+            })
+            .collect();
+    assert_eq!(events, ["enter A", "1", "exit A"]);
+
+    assert!(
+        super::str::process(input, ProcessorState::default()).all(|event| !matches!(
+            event.unwrap(),
+            Event::EnterMacro { .. } | Event::ExitMacro { .. }
+        ))
+    );
+}
+
+#[test]
+fn span_mapper() {
+    use lang_util::{TextRange, TextSize};
+
+    use super::span_map::SpanMapper;
+
+    let input = "#define ONE 1\n#define TWO (ONE + ONE)\nint x = TWO;\n";
+    let events: Vec<_> =
+        super::str::process(input, ProcessorState::default().with_macro_events(true))
+            .map(|event| event.unwrap())
+            .collect();
+
+    let output: String = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Token {
+                token,
+                masked: false,
+            } => Some(token.text()),
+            _ => None,
+        })
+        .collect();
+    let mapper: SpanMapper = events.iter().collect();
+    assert_eq!(usize::from(mapper.expanded_len()), output.len());
+
+    let map = |needle: &str| {
+        let start = output.find(needle).unwrap();
+        mapper
+            .map_to_original(TextRange::at(
+                TextSize::from(start as u32),
+                TextSize::of(needle),
+            ))
+            .map(|(_, range)| &input[range])
+    };
+
+    // Expanded tokens map to the outermost invocation
+    assert_eq!(map("1 + 1"), Some("TWO"));
+    assert_eq!(map("x"), Some("x"));
+    assert_eq!(map("x = (1"), Some("x = TWO"));
+    assert_eq!(
+        mapper.map_to_original(TextRange::empty(TextSize::from(output.len() as u32))),
+        None
+    );
+}
+
+#[test]
+fn extension_warnings() {
+    let input = "#extension GL_ARB_gpu_shader5 : warn\n#extension GL_EXT_ray_query : enable\n";
+
+    let (errors, state) = directive_errors(
+        input,
+        ProcessorState::default().with_extension_warnings(true),
+    );
+    assert_eq!(
+        errors,
+        [ErrorKind::Processing(ProcessingErrorKind::ExtensionWarn {
+            name: ExtNameAtom::from("GL_ARB_gpu_shader5"),
+        })]
+    );
+    assert_eq!(
+        active_extensions(&state),
+        ["GL_ARB_gpu_shader5", "GL_EXT_ray_query"]
+    );
+
+    let (errors, _) = directive_errors(input, ProcessorState::default());
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn line_directive_mode() {
+    let input = "#line 10 \"other.glsl\"\n";
+
+    let (errors, state) = directive_errors(input, ProcessorState::default());
+    assert_eq!(
+        errors,
+        [ErrorKind::Processing(
+            ProcessingErrorKind::CppStyleLineNotSupported
+        )]
+    );
+    assert_eq!(state.line_directive_mode(), LineDirectiveMode::Integer);
+
+    let (errors, state) = directive_errors(
+        &format!(
+            "#extension GL_GOOGLE_cpp_style_line_directive : enable\n{}",
+            input
+        ),
+        ProcessorState::default(),
+    );
+    assert!(errors.is_empty());
+    assert_eq!(state.line_directive_mode(), LineDirectiveMode::String);
+
+    let state = process(
+        "#extension GL_GOOGLE_cpp_style_line_directive : enable
+#extension GL_GOOGLE_cpp_style_line_directive : disable
+",
+        ProcessorState::default(),
+    );
+    assert_eq!(state.line_directive_mode(), LineDirectiveMode::Integer);
+}
+
+#[test]
+fn stray_conditionals_emit_directives() {
+    let mut expand = super::str::process(
+        "#else\n#endif\n#if 1\n#else\n#else\n#endif\n",
+        ProcessorState::default(),
+    );
+
+    // Directives with if stack errors are still forwarded, carrying the error, so the full
+    // directive stream can be reconstructed
+    let directives: Vec<_> = expand
+        .by_ref()
+        .filter_map(|event| match event.unwrap() {
+            Event::Directive { directive, .. } => {
+                let kind = match directive.kind() {
+                    DirectiveKind::If(_) => "if",
+                    DirectiveKind::Else(_) => "else",
+                    DirectiveKind::EndIf(_) => "endif",
+                    _ => "other",
+                };
+
+                let errors: Vec<_> = directive
+                    .into_errors()
+                    .into_iter()
+                    .map(|error| error.into_inner())
+                    .collect();
+
+                Some((kind, errors))
+            }
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        directives,
+        [
+            ("else", vec![ProcessingErrorKind::ExtraElse.into()]),
+            ("endif", vec![ProcessingErrorKind::ExtraEndIf.into()]),
+            ("if", vec![]),
+            ("else", vec![]),
+            ("else", vec![ProcessingErrorKind::ExtraElse.into()]),
+            ("endif", vec![]),
+        ]
+    );
+}
+
+#[test]
+fn current_version() {
+    assert_eq!(
+        ProcessorState::default().current_version(),
+        (110, GlslProfile::Core)
+    );
+
+    for (input, expected) in [
+        ("#version 450\n", (450, GlslProfile::Core)),
+        (
+            "#version 330 compatibility\n",
+            (330, GlslProfile::Compatibility),
+        ),
+        ("#version 300 es\n", (300, GlslProfile::Es)),
+        ("#version 100\n", (100, GlslProfile::Es)),
+    ] {
+        let state = process(input, ProcessorState::default());
+        assert_eq!(state.current_version(), expected, "{}", input);
+    }
+}
+
+#[test]
+fn predefined_and_user_macros() {
+    let mut state = process(
+        "#define FOO 1\n#define BAR(x) x\n",
+        ProcessorState::default(),
+    );
+
+    let predefined = state.predefined_macros_map();
+    assert!(predefined.contains_key("GL_core_profile"));
+    assert!(predefined.contains_key("__LINE__"));
+    assert!(!predefined.contains_key("FOO"));
+
+    let mut user: Vec<_> = state.user_macros_map().into_keys().collect();
+    user.sort();
+    assert_eq!(user, ["BAR", "FOO"]);
+
+    state.clear_user_macros();
+    assert!(state.user_macros_map().is_empty());
+    assert!(state.get_definition("FOO").is_none());
+    assert!(state.get_definition("GL_core_profile").is_some());
+}
+
+#[test]
+fn programmatic_extensions() {
+    let mut state = ProcessorState::default();
+
+    assert!(state.enable_extension(
+        ExtNameAtom::from("GL_GOOGLE_include_directive"),
+        ExtensionBehavior::Enable
+    ));
+    assert!(state.is_extension_active(&ExtNameAtom::from("GL_GOOGLE_include_directive")));
+    assert_eq!(
+        state.include_mode,
+        IncludeMode::GoogleInclude { warn: false }
+    );
+    assert_eq!(state.line_directive_mode(), LineDirectiveMode::String);
+
+    assert!(!state.enable_extension(
+        ExtNameAtom::from("GL_EXT_not_an_extension"),
+        ExtensionBehavior::Enable
+    ));
+    assert_eq!(active_extensions(&state), ["GL_GOOGLE_include_directive"]);
+
+    state.disable_all_extensions();
+    assert!(active_extensions(&state).is_empty());
+}
+
+#[test]
+fn current_file_name() {
+    let mut expand = super::str::process(
+        "#extension GL_GOOGLE_cpp_style_line_directive : enable
+#line 10 \"other.glsl\"
+#line 20
+",
+        ProcessorState::default(),
+    );
+
+    assert_eq!(expand.location().current_file_name(), None);
+
+    let mut names = Vec::new();
+    while let Some(event) = expand.next() {
+        if let Event::Directive { directive, .. } = event.unwrap() {
+            if matches!(directive.kind(), DirectiveKind::Line(_)) {
+                names.push(expand.location().current_file_name().map(str::to_owned));
+            }
+        }
+    }
+
+    assert_eq!(
+        names,
+        [Some("other.glsl".to_owned()), Some("other.glsl".to_owned())]
+    );
+}
@@ -30,6 +30,37 @@ impl LineMap {
         let (prev_start_offset, line) = self.map.range(..=offset).next_back().unwrap();
         (*line, offset - prev_start_offset)
     }
+
+    /// Convert an offset into 1-based line and column numbers, as used in GLSL error messages
+    ///
+    /// # Parameters
+    ///
+    /// * `byte_offset`: offset to convert
+    pub fn to_line_col(&self, byte_offset: u32) -> (u32, u32) {
+        let (line, col) = self.get_line_and_col(byte_offset);
+        (line + 1, col + 1)
+    }
+
+    /// Convert 1-based line and column numbers into an offset
+    ///
+    /// This is the inverse of [LineMap::to_line_col]. Lines past the end of the input are
+    /// clamped to the last line, and columns are not checked against the line length.
+    ///
+    /// # Parameters
+    ///
+    /// * `line`: 1-based line number
+    /// * `col`: 1-based column number
+    pub fn from_line_col(&self, line: u32, col: u32) -> u32 {
+        let line_start = self
+            .map
+            .keys()
+            .nth(line.saturating_sub(1) as usize)
+            .or_else(|| self.map.keys().next_back())
+            .copied()
+            .unwrap_or(0);
+
+        line_start + col.saturating_sub(1)
+    }
 }
 
 impl Default for LineMap {
@@ -49,3 +80,29 @@ impl lang_util::located::Resolver for LineMap {
         self.get_line_and_col(offset.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::LineMap;
+
+    #[test]
+    fn line_col_roundtrip() {
+        // "ab\ncde\n\nf"
+        let mut map = LineMap::new();
+        map.add_line(3);
+        map.add_line(7);
+        map.add_line(8);
+
+        assert_eq!(map.to_line_col(0), (1, 1));
+        assert_eq!(map.to_line_col(4), (2, 2));
+        assert_eq!(map.to_line_col(7), (3, 1));
+        assert_eq!(map.to_line_col(9), (4, 2));
+
+        for offset in 0..10 {
+            let (line, col) = map.to_line_col(offset);
+            assert_eq!(map.from_line_col(line, col), offset);
+        }
+
+        assert_eq!(map.from_line_col(10, 1), 8);
+    }
+}
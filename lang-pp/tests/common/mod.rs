@@ -148,7 +148,9 @@ pub fn test_file(path: impl AsRef<Path>) {
                     }
                 }
 
-                Event::EnterFile { .. } => {}
+                Event::EnterFile { .. }
+                | Event::ExitFile { .. }
+                | Event::EnterMacro { .. } | Event::ExitMacro { .. } => {}
 
                 Event::Token {
                     source_token,
@@ -1888,12 +1888,27 @@ fn tokenize_text_range(text_range: glsl_lang::ast::TextRange) -> TokenStream {
     quote! { glsl_lang::ast::TextRange::new(#start, #end) }
 }
 
+fn tokenize_file_id(file_id: glsl_lang::ast::FileId) -> TokenStream {
+    let raw = file_id.raw();
+
+    if let Some(namespace) = file_id.namespace() {
+        let namespace = namespace.get().get();
+        quote! {
+            glsl_lang::ast::FileId::from(#raw).with_namespace(
+                glsl_lang::ast::FileIdNamespace::new(::std::num::NonZeroU32::new(#namespace).unwrap())
+            )
+        }
+    } else {
+        quote! { glsl_lang::ast::FileId::from(#raw) }
+    }
+}
+
 fn tokenize_span(s: &Option<ast::NodeSpan>) -> TokenStream {
     if let Some(s) = s {
-        let source_id = u32::from(s.source_id());
+        let source_id = tokenize_file_id(s.source_id());
         let range = tokenize_text_range(s.range());
 
-        quote! { Some(glsl_lang::ast::NodeSpan::new(#source_id.into(), #range)) }
+        quote! { Some(glsl_lang::ast::NodeSpan::new(#source_id, #range)) }
     } else {
         quote! { None }
     }
@@ -26,7 +26,7 @@ use rserde::{Deserialize, Serialize};
 pub use lang_util::{
     node::{Node, NodeDisplay},
     position::NodeSpan,
-    FileId, NodeContent, NodeContentDisplay, SmolStr, TextRange, TextSize,
+    FileId, FileIdNamespace, NodeContent, NodeContentDisplay, SmolStr, TextRange, TextSize,
 };
 
 macro_rules! impl_node_content {
@@ -617,6 +617,167 @@ impl_node_content! {
     pub type TypeSpecifierNonArray = Node<TypeSpecifierNonArrayData>;
 }
 
+impl TypeSpecifierNonArrayData {
+    /// Return the name of this type, e.g. `vec4` or the name of a user-defined type
+    ///
+    /// Returns `None` for anonymous struct types.
+    pub fn base_type_name(&self) -> Option<&str> {
+        match self {
+            Self::Void => Some("void"),
+            Self::Bool => Some("bool"),
+            Self::Int => Some("int"),
+            Self::UInt => Some("uint"),
+            Self::Float => Some("float"),
+            Self::Double => Some("double"),
+            Self::Vec2 => Some("vec2"),
+            Self::Vec3 => Some("vec3"),
+            Self::Vec4 => Some("vec4"),
+            Self::DVec2 => Some("dvec2"),
+            Self::DVec3 => Some("dvec3"),
+            Self::DVec4 => Some("dvec4"),
+            Self::BVec2 => Some("bvec2"),
+            Self::BVec3 => Some("bvec3"),
+            Self::BVec4 => Some("bvec4"),
+            Self::IVec2 => Some("ivec2"),
+            Self::IVec3 => Some("ivec3"),
+            Self::IVec4 => Some("ivec4"),
+            Self::UVec2 => Some("uvec2"),
+            Self::UVec3 => Some("uvec3"),
+            Self::UVec4 => Some("uvec4"),
+            Self::Mat2 => Some("mat2"),
+            Self::Mat3 => Some("mat3"),
+            Self::Mat4 => Some("mat4"),
+            Self::Mat22 => Some("mat2x2"),
+            Self::Mat23 => Some("mat2x3"),
+            Self::Mat24 => Some("mat2x4"),
+            Self::Mat32 => Some("mat3x2"),
+            Self::Mat33 => Some("mat3x3"),
+            Self::Mat34 => Some("mat3x4"),
+            Self::Mat42 => Some("mat4x2"),
+            Self::Mat43 => Some("mat4x3"),
+            Self::Mat44 => Some("mat4x4"),
+            Self::DMat2 => Some("dmat2"),
+            Self::DMat3 => Some("dmat3"),
+            Self::DMat4 => Some("dmat4"),
+            Self::DMat22 => Some("dmat2x2"),
+            Self::DMat23 => Some("dmat2x3"),
+            Self::DMat24 => Some("dmat2x4"),
+            Self::DMat32 => Some("dmat3x2"),
+            Self::DMat33 => Some("dmat3x3"),
+            Self::DMat34 => Some("dmat3x4"),
+            Self::DMat42 => Some("dmat4x2"),
+            Self::DMat43 => Some("dmat4x3"),
+            Self::DMat44 => Some("dmat4x4"),
+            Self::Sampler1D => Some("sampler1D"),
+            Self::Image1D => Some("image1D"),
+            Self::Sampler2D => Some("sampler2D"),
+            Self::Image2D => Some("image2D"),
+            Self::Sampler3D => Some("sampler3D"),
+            Self::Image3D => Some("image3D"),
+            Self::SamplerCube => Some("samplerCube"),
+            Self::ImageCube => Some("imageCube"),
+            Self::Sampler2DRect => Some("sampler2DRect"),
+            Self::Image2DRect => Some("image2DRect"),
+            Self::Sampler1DArray => Some("sampler1DArray"),
+            Self::Image1DArray => Some("image1DArray"),
+            Self::Sampler2DArray => Some("sampler2DArray"),
+            Self::Image2DArray => Some("image2DArray"),
+            Self::SamplerBuffer => Some("samplerBuffer"),
+            Self::ImageBuffer => Some("imageBuffer"),
+            Self::Sampler2DMs => Some("sampler2DMS"),
+            Self::Image2DMs => Some("image2DMS"),
+            Self::Sampler2DMsArray => Some("sampler2DMSArray"),
+            Self::Image2DMsArray => Some("image2DMSArray"),
+            Self::SamplerCubeArray => Some("samplerCubeArray"),
+            Self::ImageCubeArray => Some("imageCubeArray"),
+            Self::Sampler1DShadow => Some("sampler1DShadow"),
+            Self::Sampler2DShadow => Some("sampler2DShadow"),
+            Self::Sampler2DRectShadow => Some("sampler2DRectShadow"),
+            Self::Sampler1DArrayShadow => Some("sampler1DArrayShadow"),
+            Self::Sampler2DArrayShadow => Some("sampler2DArrayShadow"),
+            Self::SamplerCubeShadow => Some("samplerCubeShadow"),
+            Self::SamplerCubeArrayShadow => Some("samplerCubeArrayShadow"),
+            Self::ISampler1D => Some("isampler1D"),
+            Self::IImage1D => Some("iimage1D"),
+            Self::ISampler2D => Some("isampler2D"),
+            Self::IImage2D => Some("iimage2D"),
+            Self::ISampler3D => Some("isampler3D"),
+            Self::IImage3D => Some("iimage3D"),
+            Self::ISamplerCube => Some("isamplerCube"),
+            Self::IImageCube => Some("iimageCube"),
+            Self::ISampler2DRect => Some("isampler2DRect"),
+            Self::IImage2DRect => Some("iimage2DRect"),
+            Self::ISampler1DArray => Some("isampler1DArray"),
+            Self::IImage1DArray => Some("iimage1DArray"),
+            Self::ISampler2DArray => Some("isampler2DArray"),
+            Self::IImage2DArray => Some("iimage2DArray"),
+            Self::ISamplerBuffer => Some("isamplerBuffer"),
+            Self::IImageBuffer => Some("iimageBuffer"),
+            Self::ISampler2DMs => Some("isampler2DMS"),
+            Self::IImage2DMs => Some("iimage2DMS"),
+            Self::ISampler2DMsArray => Some("isampler2DMSArray"),
+            Self::IImage2DMsArray => Some("iimage2DMSArray"),
+            Self::ISamplerCubeArray => Some("isamplerCubeArray"),
+            Self::IImageCubeArray => Some("iimageCubeArray"),
+            Self::AtomicUInt => Some("atomic_uint"),
+            Self::USampler1D => Some("usampler1D"),
+            Self::UImage1D => Some("uimage1D"),
+            Self::USampler2D => Some("usampler2D"),
+            Self::UImage2D => Some("uimage2D"),
+            Self::USampler3D => Some("usampler3D"),
+            Self::UImage3D => Some("uimage3D"),
+            Self::USamplerCube => Some("usamplerCube"),
+            Self::UImageCube => Some("uimageCube"),
+            Self::USampler2DRect => Some("usampler2DRect"),
+            Self::UImage2DRect => Some("uimage2DRect"),
+            Self::USampler1DArray => Some("usampler1DArray"),
+            Self::UImage1DArray => Some("uimage1DArray"),
+            Self::USampler2DArray => Some("usampler2DArray"),
+            Self::UImage2DArray => Some("uimage2DArray"),
+            Self::USamplerBuffer => Some("usamplerBuffer"),
+            Self::UImageBuffer => Some("uimageBuffer"),
+            Self::USampler2DMs => Some("usampler2DMS"),
+            Self::UImage2DMs => Some("uimage2DMS"),
+            Self::USampler2DMsArray => Some("usampler2DMSArray"),
+            Self::UImage2DMsArray => Some("uimage2DMSArray"),
+            Self::USamplerCubeArray => Some("usamplerCubeArray"),
+            Self::UImageCubeArray => Some("uimageCubeArray"),
+            Self::Texture1D => Some("texture1D"),
+            Self::Texture2D => Some("texture2D"),
+            Self::Texture3D => Some("texture3D"),
+            Self::TextureCube => Some("textureCube"),
+            Self::Texture2DRect => Some("texture2DRect"),
+            Self::Texture1DArray => Some("texture1DArray"),
+            Self::Texture2DArray => Some("texture2DArray"),
+            Self::TextureBuffer => Some("textureBuffer"),
+            Self::Texture2DMs => Some("texture2DMS"),
+            Self::Texture2DMsArray => Some("texture2DMSArray"),
+            Self::TextureCubeArray => Some("textureCubeArray"),
+            Self::ITexture1D => Some("itexture1D"),
+            Self::ITexture2D => Some("itexture2D"),
+            Self::ITexture3D => Some("itexture3D"),
+            Self::ITextureCube => Some("itextureCube"),
+            Self::ITexture2DRect => Some("itexture2DRect"),
+            Self::ITexture1DArray => Some("itexture1DArray"),
+            Self::ITexture2DArray => Some("itexture2DArray"),
+            Self::ITextureBuffer => Some("itextureBuffer"),
+            Self::ITexture2DMs => Some("itexture2DMS"),
+            Self::ITexture2DMsArray => Some("itexture2DMSArray"),
+            Self::ITextureCubeArray => Some("itextureCubeArray"),
+            Self::Sampler => Some("sampler"),
+            Self::SamplerShadow => Some("samplerShadow"),
+            Self::SubpassInput => Some("subpassInput"),
+            Self::ISubpassInput => Some("isubpassInput"),
+            Self::USubpassInput => Some("usubpassInput"),
+            Self::SubpassInputMs => Some("subpassInputMS"),
+            Self::ISubpassInputMs => Some("isubpassInputMS"),
+            Self::USubpassInputMs => Some("usubpassInputMS"),
+            Self::Struct(st) => st.name.as_ref().map(|name| name.as_str()),
+            Self::TypeName(name) => Some(name.as_str()),
+        }
+    }
+}
+
 impl From<TypeName> for TypeSpecifierNonArrayData {
     fn from(tn: TypeName) -> Self {
         Self::TypeName(tn)
@@ -639,6 +800,15 @@ impl_node_content! {
     pub type TypeSpecifier = Node<TypeSpecifierData>;
 }
 
+impl TypeSpecifierData {
+    /// Return the name of this type without its array specifier
+    ///
+    /// See [TypeSpecifierNonArrayData::base_type_name].
+    pub fn base_type_name(&self) -> Option<&str> {
+        self.ty.base_type_name()
+    }
+}
+
 impl From<TypeSpecifierNonArray> for TypeSpecifierData {
     fn from(ty: TypeSpecifierNonArray) -> Self {
         Self {
@@ -938,6 +1108,14 @@ impl FullySpecifiedTypeData {
             .into(),
         }
     }
+
+    /// Return the name of this type without its qualifiers and array specifier, e.g. `vec4` for
+    /// `uniform highp vec4[2]`
+    ///
+    /// See [TypeSpecifierNonArrayData::base_type_name].
+    pub fn base_type_name(&self) -> Option<&str> {
+        self.ty.base_type_name()
+    }
 }
 
 impl From<TypeSpecifierNonArrayData> for FullySpecifiedTypeData {
@@ -1267,8 +1445,70 @@ impl ExprData {
             _ => None,
         }
     }
+
+    /// Return the value of this expression if it is a literal constant
+    ///
+    /// Only constant expressions are considered, so `-1` (a unary minus applied to `1`) is not a
+    /// literal.
+    pub fn literal_value(&self) -> Option<LiteralValue> {
+        match *self {
+            Self::IntConst(x) => Some(LiteralValue::Int(x)),
+            Self::UIntConst(x) => Some(LiteralValue::UInt(x)),
+            Self::BoolConst(x) => Some(LiteralValue::Bool(x)),
+            Self::FloatConst(x) => Some(LiteralValue::Float(x)),
+            Self::DoubleConst(x) => Some(LiteralValue::Double(x)),
+            _ => None,
+        }
+    }
 }
 
+/// Value of a literal constant expression, as returned by [ExprData::literal_value]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LiteralValue {
+    /// Integral constant
+    Int(i32),
+    /// Unsigned integral constant
+    UInt(u32),
+    /// Boolean constant
+    Bool(bool),
+    /// Single precision floating constant
+    Float(f32),
+    /// Double precision floating constant
+    Double(f64),
+}
+
+/// Error returned when converting a [LiteralValue] of a different type
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+#[error("expected a {expected} literal, got {value:?}")]
+pub struct LiteralTypeError {
+    /// Value that could not be converted
+    pub value: LiteralValue,
+    /// Name of the requested type
+    pub expected: &'static str,
+}
+
+macro_rules! impl_literal_try_from {
+    ($($variant:ident => $ty:ty),*) => {
+        $(
+            impl TryFrom<LiteralValue> for $ty {
+                type Error = LiteralTypeError;
+
+                fn try_from(value: LiteralValue) -> Result<Self, Self::Error> {
+                    match value {
+                        LiteralValue::$variant(x) => Ok(x),
+                        value => Err(LiteralTypeError {
+                            value,
+                            expected: stringify!($ty),
+                        }),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_literal_try_from!(Int => i32, UInt => u32, Bool => bool, Float => f32, Double => f64);
+
 impl From<i32> for ExprData {
     fn from(x: i32) -> ExprData {
         Self::IntConst(x)
@@ -1299,6 +1539,18 @@ impl From<f64> for ExprData {
     }
 }
 
+impl From<LiteralValue> for ExprData {
+    fn from(value: LiteralValue) -> ExprData {
+        match value {
+            LiteralValue::Int(x) => Self::IntConst(x),
+            LiteralValue::UInt(x) => Self::UIntConst(x),
+            LiteralValue::Bool(x) => Self::BoolConst(x),
+            LiteralValue::Float(x) => Self::FloatConst(x),
+            LiteralValue::Double(x) => Self::DoubleConst(x),
+        }
+    }
+}
+
 /// All unary operators that exist in GLSL.
 #[derive(Clone, Debug, PartialEq, Eq, NodeContentDisplay)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -1451,6 +1703,27 @@ pub struct TranslationUnit(pub Vec<ExternalDeclaration>);
 
 impl NodeContent for TranslationUnit {}
 
+impl TranslationUnit {
+    /// Return the function definitions with the given name, in declaration order
+    pub fn functions_named<'s>(
+        &'s self,
+        name: &'s str,
+    ) -> impl Iterator<Item = &'s FunctionDefinition> + 's {
+        self.0
+            .iter()
+            .filter_map(|decl| decl.as_function_definition())
+            .filter(move |def| def.prototype.name.as_str() == name)
+    }
+
+    /// Return the `void main()` function definitions
+    pub fn entry_points(&self) -> impl Iterator<Item = &FunctionDefinition> {
+        self.functions_named("main").filter(|def| {
+            def.prototype.ty.ty.ty.content == TypeSpecifierNonArrayData::Void
+                && def.prototype.ty.ty.array_specifier.is_none()
+        })
+    }
+}
+
 /// External declaration.
 #[derive(Clone, Debug, PartialEq, NodeContentDisplay)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -1469,6 +1742,34 @@ impl_node_content! {
     pub type ExternalDeclaration = Node<ExternalDeclarationData>;
 }
 
+impl ExternalDeclarationData {
+    /// true if this is a function definition
+    pub fn is_function_definition(&self) -> bool {
+        matches!(self, Self::FunctionDefinition(_))
+    }
+
+    /// Return the function definition, if this is one
+    pub fn as_function_definition(&self) -> Option<&FunctionDefinition> {
+        match self {
+            Self::FunctionDefinition(def) => Some(def),
+            _ => None,
+        }
+    }
+
+    /// true if this is a declaration
+    pub fn is_declaration(&self) -> bool {
+        matches!(self, Self::Declaration(_))
+    }
+
+    /// Return the declaration, if this is one
+    pub fn as_declaration(&self) -> Option<&Declaration> {
+        match self {
+            Self::Declaration(decl) => Some(decl),
+            _ => None,
+        }
+    }
+}
+
 /// Function definition.
 #[derive(Clone, Debug, PartialEq, NodeContentDisplay)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -1499,6 +1800,56 @@ impl_node_content! {
     pub type CompoundStatement = Node<CompoundStatementData>;
 }
 
+impl CompoundStatementData {
+    /// Find the statement declaring the variable `name` in this block
+    ///
+    /// Only the statements directly in this block are searched, see
+    /// [CompoundStatementData::find_variable_recursive] to also search nested statements.
+    pub fn find_variable(&self, name: &str) -> Option<&Statement> {
+        self.statement_list
+            .iter()
+            .find(|statement| statement.declares_variable(name))
+    }
+
+    /// Find the first statement declaring the variable `name` in this block or in its nested
+    /// statements, in source order
+    ///
+    /// Nested compound statements and the bodies of selection, switch and iteration statements
+    /// are searched. Variables declared in `for` loop headers or loop conditions are not
+    /// declaration statements, so they are not found.
+    pub fn find_variable_recursive(&self, name: &str) -> Option<&Statement> {
+        self.statement_list
+            .iter()
+            .find_map(|statement| find_variable_in(statement, name))
+    }
+}
+
+fn find_variable_in<'s>(statement: &'s Statement, name: &str) -> Option<&'s Statement> {
+    if statement.declares_variable(name) {
+        return Some(statement);
+    }
+
+    match &**statement {
+        StatementData::Compound(compound) => compound.find_variable_recursive(name),
+        StatementData::Selection(selection) => match &*selection.rest {
+            SelectionRestStatementData::Statement(body) => find_variable_in(body, name),
+            SelectionRestStatementData::Else(body, rest) => {
+                find_variable_in(body, name).or_else(|| find_variable_in(rest, name))
+            }
+        },
+        StatementData::Switch(switch) => switch
+            .body
+            .iter()
+            .find_map(|statement| find_variable_in(statement, name)),
+        StatementData::Iteration(iteration) => match &**iteration {
+            IterationStatementData::While(_, body)
+            | IterationStatementData::DoWhile(body, _)
+            | IterationStatementData::For(_, _, body) => find_variable_in(body, name),
+        },
+        _ => None,
+    }
+}
+
 impl FromIterator<Statement> for CompoundStatementData {
     fn from_iter<T>(iter: T) -> Self
     where
@@ -1539,6 +1890,26 @@ impl_node_content! {
 }
 
 impl StatementData {
+    /// Return true if this statement is a declaration of the variable `name`
+    pub fn declares_variable(&self, name: &str) -> bool {
+        match self {
+            Self::Declaration(declaration) => match &**declaration {
+                DeclarationData::InitDeclaratorList(list) => {
+                    list.head
+                        .name
+                        .as_ref()
+                        .is_some_and(|ident| ident.as_str() == name)
+                        || list
+                            .tail
+                            .iter()
+                            .any(|decl| decl.ident.ident.as_str() == name)
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
     /// Declare a new variable.
     ///
     /// `ty` is the type of the variable, `name` the name of the binding to create,
@@ -2086,7 +2457,7 @@ impl CommentData {
 
     /// true if this comment is a single-line comment
     pub fn is_single(&self) -> bool {
-        matches!(self, Self::Multi(_))
+        matches!(self, Self::Single(_))
     }
 
     /// true if this comment is a multi-line comment
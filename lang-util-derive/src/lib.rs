@@ -19,6 +19,12 @@ pub fn node_display(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 mod token;
 
 /// Derive `lang_util::error::Token` for an enum usable with Logos
+///
+/// Adding `#[lang_util(generate_lalrpop)]` on the enum also generates a `LALRPOP_TOKENS` associated
+/// constant, which holds the `enum` token declarations to use in a LALRPOP grammar.
+///
+/// Adding `#[lang_util(generate_keywords_fn)]` on the enum also generates a `keywords()` associated
+/// function, which returns the sorted token strings of all the unit variants.
 #[proc_macro_derive(Token, attributes(lang_util))]
 pub fn token(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     token::token(input)
@@ -95,6 +95,8 @@ struct TokenVariant {
     fallback_token: Option<String>,
     #[darling(multiple, rename = "kind")]
     kinds: Vec<String>,
+    #[darling(default)]
+    deprecated: Option<String>,
 }
 
 struct Token<'s> {
@@ -105,6 +107,14 @@ struct Token<'s> {
 }
 
 impl<'s> Token<'s> {
+    fn allow_deprecated(&self) -> TokenStream {
+        if self.variant.deprecated.is_some() {
+            quote_spanned! { self.variant.ident.span() => #[allow(deprecated)] }
+        } else {
+            quote! {}
+        }
+    }
+
     fn empty_variant_header(&self) -> TokenStream {
         let variant_name = &self.variant.ident;
         let variant_header = match &self.variant.fields.style {
@@ -136,9 +146,10 @@ impl<'s> Token<'s> {
             quote_spanned! { self.variant.ident.span() => #value }
         };
 
+        let allow_deprecated = self.allow_deprecated();
         quote_spanned! {
             self.variant.ident.span() =>
-                #variant_header => #body
+                #allow_deprecated #variant_header => #body
         }
     }
 
@@ -158,10 +169,11 @@ impl<'s> Token<'s> {
     fn parser_token_arm(&self) -> TokenStream {
         let variant_header = self.empty_variant_header();
         let body = self.parser_token_body();
+        let allow_deprecated = self.allow_deprecated();
 
         quote_spanned! {
             self.variant.ident.span() =>
-                #variant_header => #body
+                #allow_deprecated #variant_header => #body
         }
     }
 
@@ -202,10 +214,11 @@ impl<'s> Token<'s> {
     fn kinds_arm(&self) -> TokenStream {
         let variant_header = self.empty_variant_header();
         let body = self.kinds_body();
+        let allow_deprecated = self.allow_deprecated();
 
         quote_spanned! {
             self.variant.ident.span() =>
-                #variant_header => #body
+                #allow_deprecated #variant_header => #body
         }
     }
 
@@ -223,34 +236,18 @@ impl<'s> Token<'s> {
                 if let Some(display) = &self.variant.display {
                     let fmt = &display.format;
 
-                    // TODO: Better than this ugly format string parsing?
-                    let args = if display.args.is_empty() {
-                        if declared_fields.len() == 1 && display.format.contains("{}") {
-                            let f = &declared_fields[0];
-                            vec![quote_spanned! { self.variant.ident.span() => #f }]
-                        } else {
-                            declared_fields
-                                .iter()
-                                .enumerate()
-                                .filter_map(|(i, df)| {
-                                    let ph = format!("_{}", i);
-
-                                    if fmt.contains(&ph) {
-                                        let ph = format_ident!("{}", ph);
-                                        Some(quote_spanned! { self.variant.ident.span() => #ph = #df })
-                                    } else {
-                                        None
-                                    }
-                                })
-                                .collect()
-                        }
+                    let (fmt, args) = if display.args.is_empty() {
+                        // Placeholders refer to the fields, capture them directly in the format
+                        // string
+                        (capture_fields(fmt, declared_fields), vec![])
                     } else {
+                        // Replace in reverse order so _1 doesn't match the prefix of _10
                         let mut repl = Vec::new();
-                        for (i, df) in declared_fields.iter().enumerate() {
+                        for (i, df) in declared_fields.iter().enumerate().rev() {
                             repl.push((format!("_{}", i), df.to_string()));
                         }
 
-                        display
+                        let args: Vec<TokenStream> = display
                             .args
                             .iter()
                             .map(|arg| {
@@ -261,10 +258,12 @@ impl<'s> Token<'s> {
 
                                 syn::parse_str(&arg).expect("parsing error")
                             })
-                            .collect()
+                            .collect();
+
+                        (fmt.clone(), args)
                     };
 
-                    let fmt = self.get_prefixed_fmt(fmt);
+                    let fmt = self.get_prefixed_fmt(&fmt);
                     return quote_spanned! {
                         self.variant.ident.span() =>
                             write!(f, #fmt, #(#args),*)
@@ -358,20 +357,44 @@ impl<'s> Token<'s> {
         let body = self.display_arm_body(&declared_fields);
 
         let base_ident = self.base_ident;
+        let allow_deprecated = self.allow_deprecated();
         quote_spanned! {
             self.variant.ident.span() =>
-                #base_ident :: #variant_header => { #body }
+                #allow_deprecated #base_ident :: #variant_header => { #body }
         }
     }
 
+    fn lalrpop_declaration(&self) -> Option<String> {
+        let parser_token = self.as_parser.as_ref().ok()?;
+        let base_ident = self.base_ident;
+        let variant_name = &self.variant.ident;
+
+        let pattern = match self.variant.fields.style {
+            darling::ast::Style::Tuple => {
+                let fields = vec!["_"; self.variant.fields.fields.len()];
+                format!("{}::{}({})", base_ident, variant_name, fields.join(", "))
+            }
+            darling::ast::Style::Struct => {
+                return None;
+            }
+            darling::ast::Style::Unit => format!("{}::{}", base_ident, variant_name),
+        };
+
+        Some(format!("{} => {},", parser_token, pattern))
+    }
+
     fn all_tokens_arm(&self) -> TokenStream {
         let variant_name = self.variant.ident.to_string();
         let parser_token = self.parser_token_body();
         let kinds = self.kinds_body();
+        let deprecated = self.variant.deprecated.as_ref().map(|note| {
+            quote_spanned! { self.variant.ident.span() => .with_deprecated(#note) }
+        });
 
         quote_spanned! {
             self.variant.ident.span() =>
                 ::lang_util::token::TokenDescriptor::new(#variant_name, #parser_token, #kinds)
+                    #deprecated
         }
     }
 }
@@ -413,6 +436,7 @@ fn parse_token_attr(variant: &TokenVariant) -> TokenAttrTy {
 #[derive(Debug)]
 enum AsParserError {
     MissingDisplayImpl,
+    DisplayHasPlaceholders,
     InvalidAs,
     InvalidTokenAttribute(String),
     NoTokenOrAs,
@@ -422,6 +446,7 @@ impl std::fmt::Display for AsParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::MissingDisplayImpl => write!(f, "in lang_util attribute, `parser = display` specified but not display implementation provided"),
+            Self::DisplayHasPlaceholders => write!(f, "in lang_util attribute, `parser = display` specified on a unit variant but the display format contains placeholders"),
             Self::InvalidAs => write!(f, "invalid `parser` value, expected display or a literal string"),
             Self::InvalidTokenAttribute(error) => write!(f, "invalid token attribute: {}", error),
             Self::NoTokenOrAs => write!(f, "missing token or lang_util(parser = \"...\") attributes"),
@@ -429,12 +454,84 @@ impl std::fmt::Display for AsParserError {
     }
 }
 
+/// Rewrite the placeholders of a format string to capture the given fields by name.
+///
+/// `{}` placeholders refer to the next field in order, while `{0}` and `{_0}` refer to the first
+/// field. Format specs (e.g. `{0:?}`) and escaped braces are preserved, and other placeholders are
+/// left untouched.
+fn capture_fields(format: &str, fields: &[syn::Ident]) -> String {
+    let mut result = String::with_capacity(format.len());
+    let mut chars = format.chars().peekable();
+    let mut next_field = 0;
+
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            result.push(c);
+            continue;
+        }
+
+        if chars.peek() == Some(&'{') {
+            chars.next();
+            result.push_str("{{");
+            continue;
+        }
+
+        let mut placeholder = String::new();
+        for c in chars.by_ref() {
+            if c == '}' {
+                break;
+            }
+
+            placeholder.push(c);
+        }
+
+        let (name, spec) = match placeholder.find(':') {
+            Some(idx) => placeholder.split_at(idx),
+            None => (placeholder.as_str(), ""),
+        };
+
+        let index = if name.is_empty() {
+            next_field += 1;
+            Some(next_field - 1)
+        } else {
+            name.strip_prefix('_').unwrap_or(name).parse::<usize>().ok()
+        };
+
+        match index.and_then(|index| fields.get(index)) {
+            Some(field) => {
+                result.push('{');
+                result.push_str(&field.to_string());
+                result.push_str(spec);
+                result.push('}');
+            }
+            None => {
+                result.push('{');
+                result.push_str(&placeholder);
+                result.push('}');
+            }
+        }
+    }
+
+    result
+}
+
+/// Returns true if the given format string contains `{...}` placeholders, ignoring escaped braces
+fn has_placeholders(format: &str) -> bool {
+    format.replace("{{", "").replace("}}", "").contains('{')
+}
+
 fn parse_as_parser(variant: &TokenVariant, token: &TokenAttrTy) -> Result<String, AsParserError> {
     if let Some(as_parser) = &variant.as_parser {
         match as_parser {
             AsParser::Path(path) => {
                 if path.get_ident().map(|id| id == "display").unwrap_or(false) {
                     if let Some(display) = &variant.display {
+                        if variant.fields.style == darling::ast::Style::Unit
+                            && has_placeholders(&display.format)
+                        {
+                            return Err(AsParserError::DisplayHasPlaceholders);
+                        }
+
                         Ok(format!("\"{}\"", &display.format))
                     } else {
                         Err(AsParserError::MissingDisplayImpl)
@@ -478,6 +575,10 @@ struct TokenOpts {
     ident: syn::Ident,
     generics: syn::Generics,
     data: darling::ast::Data<TokenVariant, ()>,
+    #[darling(default)]
+    generate_lalrpop: bool,
+    #[darling(default)]
+    generate_keywords_fn: bool,
 }
 
 fn display_impl(
@@ -540,6 +641,60 @@ fn token_impl(base_ident: &syn::Ident, enum_name: &TokenStream, variants: &[Toke
     }
 }
 
+fn lalrpop_impl(
+    base_ident: &syn::Ident,
+    generics: &syn::Generics,
+    variants: &[Token],
+) -> TokenStream {
+    let mut block = format!("enum {} {{\n", base_ident);
+    for declaration in variants.iter().filter_map(Token::lalrpop_declaration) {
+        block.push_str("    ");
+        block.push_str(&declaration);
+        block.push('\n');
+    }
+    block.push('}');
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote_spanned! {
+        base_ident.span() =>
+            impl #impl_generics #base_ident #ty_generics #where_clause {
+                /// LALRPOP token declarations for this enum, to be used in the `extern` block of
+                /// a grammar file
+                pub const LALRPOP_TOKENS: &'static str = #block;
+            }
+    }
+}
+
+fn keywords_impl(
+    base_ident: &syn::Ident,
+    generics: &syn::Generics,
+    variants: &[Token],
+) -> TokenStream {
+    let mut keywords: Vec<_> = variants
+        .iter()
+        .filter(|variant| variant.variant.fields.style == darling::ast::Style::Unit)
+        .filter_map(|variant| match &variant.token {
+            Some((Ok(token), _)) => Some(token.token.as_str()),
+            _ => None,
+        })
+        .collect();
+    keywords.sort_unstable();
+    keywords.dedup();
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote_spanned! {
+        base_ident.span() =>
+            impl #impl_generics #base_ident #ty_generics #where_clause {
+                /// Token strings of the unit variants of this enum, sorted and deduplicated
+                pub fn keywords() -> &'static [&'static str] {
+                    &[#(#keywords),*]
+                }
+            }
+    }
+}
+
 pub(crate) fn token(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let opts = {
         // Parse the input tokens into a syntax tree
@@ -604,8 +759,73 @@ pub(crate) fn token(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     decls.push(display_impl(base_ident, &enum_name, &fields));
     decls.push(token_impl(base_ident, &enum_name, &fields));
 
+    if opts.generate_lalrpop {
+        decls.push(lalrpop_impl(base_ident, &opts.generics, &fields));
+    }
+
+    if opts.generate_keywords_fn {
+        decls.push(keywords_impl(base_ident, &opts.generics, &fields));
+    }
+
     proc_macro::TokenStream::from(quote_spanned! {
         opts.ident.span() =>
             #(#decls)*
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn has_placeholders_detects_placeholders() {
+        assert!(has_placeholders("{}"));
+        assert!(has_placeholders("{0}"));
+        assert!(has_placeholders("{x:?}"));
+        assert!(has_placeholders("a {{ {} }}"));
+    }
+
+    #[test]
+    fn has_placeholders_ignores_escaped_braces() {
+        assert!(!has_placeholders("{{}}"));
+        assert!(!has_placeholders("{{0}}"));
+        assert!(!has_placeholders("plain"));
+    }
+
+    fn fields(names: &[&str]) -> Vec<syn::Ident> {
+        names
+            .iter()
+            .map(|name| syn::Ident::new(name, proc_macro2::Span::call_site()))
+            .collect()
+    }
+
+    #[test]
+    fn capture_fields_positional() {
+        let fields = fields(&["_0", "_1"]);
+        assert_eq!(capture_fields("{} and {}", &fields), "{_0} and {_1}");
+        assert_eq!(capture_fields("{1} then {0}", &fields), "{_1} then {_0}");
+        assert_eq!(capture_fields("{_1} then {_0}", &fields), "{_1} then {_0}");
+    }
+
+    #[test]
+    fn capture_fields_format_spec() {
+        let fields = fields(&["_0"]);
+        assert_eq!(capture_fields("{0:?}", &fields), "{_0:?}");
+        assert_eq!(capture_fields("{:>4}", &fields), "{_0:>4}");
+    }
+
+    #[test]
+    fn capture_fields_escaped_braces() {
+        let fields = fields(&["_0"]);
+        assert_eq!(capture_fields("{{{}}}", &fields), "{{{_0}}}");
+        assert_eq!(capture_fields("{{0}}", &fields), "{{0}}");
+    }
+
+    #[test]
+    fn capture_fields_out_of_range() {
+        let fields = fields(&["_0"]);
+        assert_eq!(capture_fields("{0} {1:?}", &fields), "{_0} {1:?}");
+        assert_eq!(capture_fields("{} {}", &fields), "{_0} {}");
+        assert_eq!(capture_fields("{name}", &fields), "{name}");
+    }
+}
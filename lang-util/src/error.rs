@@ -1,13 +1,17 @@
 //! Error type definitions
 
+#[cfg(feature = "lalrpop")]
 use std::cmp::Ordering;
+#[cfg(feature = "lalrpop")]
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
 
 use text_size::{TextRange, TextSize};
 
-use crate::{located::Located, position::LexerPosition, token::Token, FileId};
+#[cfg(feature = "lalrpop")]
+use crate::FileId;
+use crate::{located::Located, position::LexerPosition, token::Token};
 
 /// Information about a lexed token
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
@@ -65,12 +69,71 @@ pub trait LexicalError: Error {
     /// [LexerPosition] structure that indicates at which offset in the input the error occurred,
     /// and length of the range.
     fn location(&self) -> (LexerPosition, TextSize);
+
+    /// Format this error along with the source lines surrounding its location
+    ///
+    /// The lines before and after the error are shown with their line numbers, and the range of
+    /// the error is underlined, similarly to the diagnostics of GCC.
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: source string the error was raised on
+    fn display_with_source(&self, source: &str) -> String {
+        let (position, length) = self.location();
+        format_with_source(
+            &self.to_string(),
+            TextRange::at(position.offset, length),
+            source,
+        )
+    }
+}
+
+/// Number of lines to show around an error in [LexicalError::display_with_source]
+const CONTEXT_LINES: usize = 2;
+
+fn format_with_source(message: &str, range: TextRange, source: &str) -> String {
+    use crate::located::Resolver;
+    use std::fmt::Write;
+
+    let (line, column) = source.resolve(range.start());
+    let (line, column) = (line as usize, column as usize);
+
+    let lines: Vec<_> = source.lines().collect();
+    let first = line.saturating_sub(CONTEXT_LINES);
+    let last = (line + CONTEXT_LINES).min(lines.len().saturating_sub(1));
+    let width = (last + 1).to_string().len();
+
+    let mut result = String::new();
+    let _ = writeln!(result, "{}:{}: error: {}", line + 1, column + 1, message);
+
+    for (index, text) in lines.iter().enumerate().take(last + 1).skip(first) {
+        let _ = writeln!(result, "{:>width$} | {}", index + 1, text);
+
+        if index == line {
+            // Align the underline on characters, and keep it within the error line
+            let column = text.get(..column).unwrap_or(text);
+            let underlined = text
+                .get(column.len()..(column.len() + usize::from(range.len())).min(text.len()))
+                .unwrap_or_default();
+
+            let _ = writeln!(
+                result,
+                "{:width$} | {}{}",
+                "",
+                " ".repeat(column.chars().count()),
+                "^".repeat(underlined.chars().count().max(1))
+            );
+        }
+    }
+
+    result
 }
 
 /// A parsing error wrapped from lalrpop_util's error type
 pub type ParseError<E> = Located<ParseErrorKind<E>>;
 
 /// Return the LexerLocation of a lalrpop_util::ParseError
+#[cfg(feature = "lalrpop")]
 pub fn error_location<T, E: LexicalError>(
     error: &lalrpop_util::ParseError<LexerPosition, T, E>,
 ) -> (FileId, TextRange) {
@@ -127,6 +190,7 @@ pub enum ParseErrorKind<E: LexicalError> {
 
 impl<E: std::error::Error + LexicalError + 'static> std::error::Error for ParseErrorKind<E> {}
 
+#[cfg(feature = "lalrpop")]
 impl<T: Token, E: LexicalError> From<lalrpop_util::ParseError<LexerPosition, T, E>>
     for ParseErrorKind<E>
 {
@@ -309,3 +373,93 @@ impl<E: LexicalError> fmt::Display for ParseErrorKind<E> {
         }
     }
 }
+
+/// A collection of errors, e.g. raised across multiple files
+///
+/// The errors are displayed one per line, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiError<E> {
+    errors: Vec<E>,
+}
+
+impl<E> MultiError<E> {
+    /// Create a new error collection
+    pub fn new(errors: Vec<E>) -> Self {
+        Self { errors }
+    }
+
+    /// Return the collected errors
+    pub fn errors(&self) -> &[E] {
+        &self.errors
+    }
+
+    /// Return the number of collected errors
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Return `true` if no errors were collected
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Return the collected errors
+    pub fn into_inner(self) -> Vec<E> {
+        self.errors
+    }
+
+    /// Return `Ok(value)` if no errors were collected, or this collection otherwise
+    pub fn into_result<T>(self, value: T) -> Result<T, Self> {
+        if self.is_empty() {
+            Ok(value)
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl<E> From<Vec<E>> for MultiError<E> {
+    fn from(errors: Vec<E>) -> Self {
+        Self::new(errors)
+    }
+}
+
+impl<E> FromIterator<E> for MultiError<E> {
+    fn from_iter<I: IntoIterator<Item = E>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl<E> IntoIterator for MultiError<E> {
+    type Item = E;
+    type IntoIter = std::vec::IntoIter<E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl<'e, E> IntoIterator for &'e MultiError<E> {
+    type Item = &'e E;
+    type IntoIter = std::slice::Iter<'e, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.iter()
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for MultiError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<E: Error> Error for MultiError<E> {}
@@ -1,10 +1,59 @@
 //! File identifier definition
 
-/// Unique file identifier
+use std::{
+    num::NonZeroU32,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// Namespace for file identifiers
+///
+/// File identifiers allocated in different namespaces never compare equal, which allows merging
+/// results from multiple sources (e.g. preprocessors running in parallel) without collisions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(rserde::Serialize, rserde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(crate = "rserde"))]
-pub struct FileId(u32);
+pub struct FileIdNamespace(NonZeroU32);
+
+impl FileIdNamespace {
+    /// Create a namespace from its raw value
+    pub fn new(raw: NonZeroU32) -> Self {
+        Self(raw)
+    }
+
+    /// Allocate a namespace which is unique for the current process
+    ///
+    /// # Panics
+    ///
+    /// panics if all namespaces have been allocated
+    pub fn unique() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(1);
+
+        Self(
+            NonZeroU32::new(NEXT.fetch_add(1, Ordering::Relaxed))
+                .expect("file identifier namespaces exhausted"),
+        )
+    }
+
+    /// Get the raw value of this namespace
+    pub fn get(&self) -> NonZeroU32 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for FileIdNamespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Unique file identifier
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(rserde::Serialize, rserde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "rserde"))]
+pub struct FileId {
+    namespace: Option<FileIdNamespace>,
+    raw: u32,
+}
 
 const MAX_VALUE: u32 = 0x7FFFFFFF;
 const BUILTIN_BIT: u32 = 0x80000000;
@@ -20,7 +69,7 @@ impl FileId {
             panic!("file identifier is too large");
         }
 
-        Self(raw)
+        Self::from(raw)
     }
 
     /// Create a new file identifier for a built-in string
@@ -33,34 +82,69 @@ impl FileId {
             panic!("file identifier is too large");
         }
 
-        Self(BUILTIN_BIT | (raw + 1))
+        Self::from(BUILTIN_BIT | (raw + 1))
+    }
+
+    /// Return this file identifier in the given namespace
+    pub fn with_namespace(self, namespace: FileIdNamespace) -> Self {
+        Self {
+            namespace: Some(namespace),
+            ..self
+        }
+    }
+
+    /// Get the namespace of this file identifier, if any
+    pub fn namespace(&self) -> Option<FileIdNamespace> {
+        self.namespace
+    }
+
+    /// Get the raw value of this file identifier, without its namespace
+    ///
+    /// File identifiers in different namespaces may have the same raw value, so callers which
+    /// need to tell them apart must also check [FileId::namespace].
+    pub fn raw(&self) -> u32 {
+        self.raw
     }
 
     /// Get the number behind this id, regardless of its type
     pub fn number(&self) -> u32 {
-        if (self.0 & BUILTIN_BIT) == BUILTIN_BIT {
-            let raw = self.0 & !BUILTIN_BIT;
+        if (self.raw & BUILTIN_BIT) == BUILTIN_BIT {
+            let raw = self.raw & !BUILTIN_BIT;
             if raw == 0 {
                 raw
             } else {
                 raw - 1
             }
         } else {
-            self.0
+            self.raw
         }
     }
 }
 
 impl Default for FileId {
     fn default() -> Self {
-        Self(BUILTIN_BIT)
+        Self::from(BUILTIN_BIT)
+    }
+}
+
+impl std::fmt::Debug for FileId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(namespace) = self.namespace {
+            write!(f, "FileId({}:{})", namespace, self.raw)
+        } else {
+            write!(f, "FileId({})", self.raw)
+        }
     }
 }
 
 impl std::fmt::Display for FileId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if (self.0 & BUILTIN_BIT) == BUILTIN_BIT {
-            let raw = self.0 & !BUILTIN_BIT;
+        if let Some(namespace) = self.namespace {
+            write!(f, "{}:", namespace)?;
+        }
+
+        if (self.raw & BUILTIN_BIT) == BUILTIN_BIT {
+            let raw = self.raw & !BUILTIN_BIT;
 
             if raw == 0 {
                 write!(f, "internal")
@@ -68,19 +152,16 @@ impl std::fmt::Display for FileId {
                 write!(f, "builtin-{}", raw - 1)
             }
         } else {
-            write!(f, "{}", self.0)
+            write!(f, "{}", self.raw)
         }
     }
 }
 
 impl From<u32> for FileId {
     fn from(value: u32) -> Self {
-        Self(value)
-    }
-}
-
-impl From<FileId> for u32 {
-    fn from(value: FileId) -> Self {
-        value.0
+        Self {
+            namespace: None,
+            raw: value,
+        }
     }
 }
@@ -4,20 +4,20 @@
 //!
 //! This crate is tailored for use in the [`glsl-lang`](https://crates.io/crates/glsl-lang) crate,
 //! but you may use its utilities for implementing your own language parsers:
-//! - [error]: parsing error reporting module, with user-readable location information. Only
-//!   available with the `lalrpop` feature enabled.
-//! - [node]: AST node structure and display
+//! - [error]: parsing error reporting module, with user-readable location information. The
+//!   conversion from `lalrpop` errors is only available with the `lalrpop` feature enabled.
+//! - [node]: AST node structure, display and metadata
 //! - [position]: utilities for working with positions in strings
+//! - [source_map]: source map generation for mapping generated output back to input files
 
 #![deny(missing_docs)]
 
 pub use lang_util_derive::{NodeContentDisplay, Token};
 
-#[cfg(feature = "lalrpop")]
 pub mod error;
 
 mod file_id;
-pub use file_id::FileId;
+pub use file_id::{FileId, FileIdNamespace};
 
 pub mod located;
 
@@ -26,6 +26,8 @@ pub use node::NodeContent;
 
 pub mod position;
 
+pub mod source_map;
+
 pub mod token;
 pub use token::Token;
 
@@ -2,6 +2,8 @@
 
 mod content;
 mod display;
+mod metadata;
 
 pub use content::*;
 pub use display::*;
+pub use metadata::*;
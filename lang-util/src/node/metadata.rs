@@ -0,0 +1,192 @@
+use std::{any::TypeId, collections::HashMap};
+
+use crate::position::NodeSpan;
+
+use super::{Node, NodeContent};
+
+/// Stable identifier for a syntax node
+///
+/// Identifiers are derived from the node's span and its content type, so two different node kinds
+/// covering the same input range (e.g. an expression statement and its expression) get different
+/// identifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    kind: TypeId,
+    span: NodeSpan,
+}
+
+impl NodeId {
+    /// Create a new node identifier for a node of type `T` at the given span
+    ///
+    /// # Parameters
+    ///
+    /// * `span`: span of the node
+    pub fn of<T: 'static>(span: NodeSpan) -> Self {
+        Self {
+            kind: TypeId::of::<T>(),
+            span,
+        }
+    }
+
+    /// Return the span this identifier was derived from
+    pub fn span(&self) -> NodeSpan {
+        self.span
+    }
+}
+
+/// Trait for syntax nodes that can be identified by a [NodeId]
+pub trait HasNodeId {
+    /// Return the identifier for this node
+    ///
+    /// # Returns
+    ///
+    /// `None` if this node has no span information, otherwise the node identifier.
+    fn node_id(&self) -> Option<NodeId>;
+}
+
+impl<T: NodeContent + 'static> HasNodeId for Node<T> {
+    fn node_id(&self) -> Option<NodeId> {
+        self.span.map(NodeId::of::<T>)
+    }
+}
+
+/// User data attached to syntax nodes
+///
+/// This allows downstream tools to annotate syntax trees (e.g. with resolved types) without
+/// modifying the node types. Only nodes with span information can be annotated.
+#[derive(Debug, Clone)]
+pub struct NodeMetadata<T> {
+    values: HashMap<NodeId, T>,
+}
+
+impl<T> NodeMetadata<T> {
+    /// Create a new empty metadata map
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+
+    /// Attach a value to a node
+    ///
+    /// # Parameters
+    ///
+    /// * `node`: node to attach the value to
+    /// * `value`: value to attach
+    ///
+    /// # Returns
+    ///
+    /// `false` if the node has no span information and the value could not be attached.
+    pub fn insert(&mut self, node: &impl HasNodeId, value: T) -> bool {
+        if let Some(id) = node.node_id() {
+            self.values.insert(id, value);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Attach a value to a node identifier
+    ///
+    /// # Returns
+    ///
+    /// The value previously attached to this identifier, if any.
+    pub fn insert_id(&mut self, id: NodeId, value: T) -> Option<T> {
+        self.values.insert(id, value)
+    }
+
+    /// Get the value attached to a node
+    pub fn get(&self, node: &impl HasNodeId) -> Option<&T> {
+        node.node_id().and_then(|id| self.values.get(&id))
+    }
+
+    /// Get a mutable reference to the value attached to a node
+    pub fn get_mut(&mut self, node: &impl HasNodeId) -> Option<&mut T> {
+        node.node_id().and_then(|id| self.values.get_mut(&id))
+    }
+
+    /// Get the value attached to a node identifier
+    pub fn get_id(&self, id: &NodeId) -> Option<&T> {
+        self.values.get(id)
+    }
+
+    /// Remove the value attached to a node
+    pub fn remove(&mut self, node: &impl HasNodeId) -> Option<T> {
+        node.node_id().and_then(|id| self.values.remove(&id))
+    }
+
+    /// Return true if a value is attached to the given node
+    pub fn contains(&self, node: &impl HasNodeId) -> bool {
+        self.get(node).is_some()
+    }
+
+    /// Return the number of annotated nodes
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Return true if no node is annotated
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterate over the node identifiers and their attached values
+    pub fn iter(&self) -> impl Iterator<Item = (&NodeId, &T)> {
+        self.values.iter()
+    }
+}
+
+impl<T> Default for NodeMetadata<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use text_size::TextRange;
+
+    use super::*;
+    use crate::FileId;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct A;
+    impl NodeContent for A {}
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct B;
+    impl NodeContent for B {}
+
+    fn span(start: u32, end: u32) -> Option<NodeSpan> {
+        Some(NodeSpan::new(
+            FileId::new(0),
+            TextRange::new(start.into(), end.into()),
+        ))
+    }
+
+    #[test]
+    fn attach_by_span_and_kind() {
+        let a = Node::new(A, span(0, 4));
+        let b = Node::new(B, span(0, 4));
+        let other_a = Node::new(A, span(4, 8));
+
+        let mut metadata = NodeMetadata::new();
+        assert!(metadata.insert(&a, "a"));
+        assert!(metadata.insert(&b, "b"));
+
+        assert_eq!(metadata.get(&a), Some(&"a"));
+        assert_eq!(metadata.get(&b), Some(&"b"));
+        assert_eq!(metadata.get(&other_a), None);
+        assert_eq!(metadata.get(&a.clone()), Some(&"a"));
+    }
+
+    #[test]
+    fn unspanned_node() {
+        let a = Node::new(A, None);
+
+        let mut metadata = NodeMetadata::new();
+        assert!(!metadata.insert(&a, ()));
+        assert!(metadata.is_empty());
+        assert_eq!(metadata.get(&a), None);
+    }
+}
@@ -176,3 +176,55 @@ impl From<NodeSpan> for TextRange {
         value.range
     }
 }
+
+/// Source string of a parsed input, used to recover the text of node spans
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceText<'a>(&'a str);
+
+impl<'a> SourceText<'a> {
+    /// Wrap the given source string
+    pub fn new(text: &'a str) -> Self {
+        Self(text)
+    }
+
+    /// Return the whole source string
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+
+    /// Return the text in the given range
+    ///
+    /// An empty string is returned if the range is out of bounds or does not fall on character
+    /// boundaries, which happens if the range belongs to another source.
+    pub fn slice(&self, range: impl Into<TextRange>) -> &'a str {
+        self.0
+            .get(std::ops::Range::<usize>::from(range.into()))
+            .unwrap_or_default()
+    }
+}
+
+impl<'a> From<&'a str> for SourceText<'a> {
+    fn from(text: &'a str) -> Self {
+        Self(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_text_slice() {
+        let source = SourceText::new("void main() {}");
+
+        assert_eq!(source.slice(TextRange::new(5.into(), 9.into())), "main");
+        assert_eq!(
+            source.slice(NodeSpan::new(
+                FileId::new(0),
+                TextRange::new(0.into(), 4.into())
+            )),
+            "void"
+        );
+        assert_eq!(source.slice(TextRange::new(10.into(), 20.into())), "");
+    }
+}
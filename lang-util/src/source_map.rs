@@ -0,0 +1,235 @@
+//! Source map generation
+//!
+//! This module provides a [SourceMap] type which records how ranges of generated output map back
+//! to positions in the original input files, and can be serialized to the
+//! [source map v3](https://sourcemaps.info/spec.html) JSON format.
+
+use std::{collections::HashMap, fmt::Write};
+
+use text_size::TextSize;
+
+use crate::{located::Resolver, FileId};
+
+/// A single mapping from an output offset to an input position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourceMapping {
+    /// Byte offset in the generated output
+    pub output_offset: TextSize,
+    /// Input file this output was generated from
+    pub source_id: FileId,
+    /// 0-based line number in the input file
+    pub line: u32,
+    /// 0-based column number in the input file
+    pub col: u32,
+}
+
+/// Source map from generated output to input files
+#[derive(Default, Debug, Clone)]
+pub struct SourceMap {
+    mappings: Vec<SourceMapping>,
+    source_names: HashMap<FileId, String>,
+    file: Option<String>,
+}
+
+impl SourceMap {
+    /// Create a new empty source map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the name of the generated file, as reported in the `file` field of the source map
+    pub fn set_file(&mut self, file: impl Into<String>) {
+        self.file = Some(file.into());
+    }
+
+    /// Set the name of an input file, as reported in the `sources` field of the source map
+    ///
+    /// Input files without a name are reported using the display form of their [FileId].
+    ///
+    /// # Parameters
+    ///
+    /// * `source_id`: identifier of the input file
+    /// * `name`: name (usually the path) of the input file
+    pub fn set_source_name(&mut self, source_id: FileId, name: impl Into<String>) {
+        self.source_names.insert(source_id, name.into());
+    }
+
+    /// Record a new mapping
+    ///
+    /// # Parameters
+    ///
+    /// * `output_offset`: byte offset in the generated output
+    /// * `source_id`: input file the output at this offset was generated from
+    /// * `line`: 0-based line number in the input file
+    /// * `col`: 0-based column number in the input file
+    pub fn add_mapping(&mut self, output_offset: TextSize, source_id: FileId, line: u32, col: u32) {
+        self.mappings.push(SourceMapping {
+            output_offset,
+            source_id,
+            line,
+            col,
+        });
+    }
+
+    /// Return the recorded mappings, in insertion order
+    pub fn mappings(&self) -> &[SourceMapping] {
+        &self.mappings
+    }
+
+    /// Serialize this source map to the source map v3 JSON format
+    ///
+    /// # Parameters
+    ///
+    /// * `output`: generated output the mappings refer to, used to convert output offsets into
+    ///   line and column numbers
+    pub fn to_json(&self, output: &str) -> String {
+        let mut mappings = self.mappings.clone();
+        mappings.sort_by_key(|mapping| mapping.output_offset);
+
+        // Assign source indices in order of first use
+        let mut sources: Vec<FileId> = Vec::new();
+        let mut source_indices = HashMap::new();
+        for mapping in &mappings {
+            source_indices.entry(mapping.source_id).or_insert_with(|| {
+                sources.push(mapping.source_id);
+                sources.len() - 1
+            });
+        }
+
+        let mut encoded = String::new();
+        let mut current_line = 0;
+        let mut prev_output_col = 0i64;
+        let mut prev_source = 0i64;
+        let mut prev_line = 0i64;
+        let mut prev_col = 0i64;
+
+        for mapping in &mappings {
+            let (output_line, output_col) = output.resolve(mapping.output_offset);
+
+            if output_line > current_line {
+                for _ in current_line..output_line {
+                    encoded.push(';');
+                }
+
+                current_line = output_line;
+                prev_output_col = 0;
+            } else if !encoded.is_empty() && !encoded.ends_with(';') {
+                encoded.push(',');
+            }
+
+            let source = source_indices[&mapping.source_id] as i64;
+            encode_vlq(&mut encoded, output_col as i64 - prev_output_col);
+            encode_vlq(&mut encoded, source - prev_source);
+            encode_vlq(&mut encoded, mapping.line as i64 - prev_line);
+            encode_vlq(&mut encoded, mapping.col as i64 - prev_col);
+
+            prev_output_col = output_col as _;
+            prev_source = source;
+            prev_line = mapping.line as _;
+            prev_col = mapping.col as _;
+        }
+
+        let mut json = String::from("{\"version\":3,");
+
+        if let Some(file) = &self.file {
+            json.push_str("\"file\":");
+            write_json_string(&mut json, file);
+            json.push(',');
+        }
+
+        json.push_str("\"sources\":[");
+        for (i, source_id) in sources.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+
+            if let Some(name) = self.source_names.get(source_id) {
+                write_json_string(&mut json, name);
+            } else {
+                write_json_string(&mut json, &source_id.to_string());
+            }
+        }
+
+        json.push_str("],\"names\":[],\"mappings\":");
+        write_json_string(&mut json, &encoded);
+        json.push('}');
+
+        json
+    }
+}
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_vlq(out: &mut String, value: i64) {
+    let mut value = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+
+    loop {
+        let mut digit = value & 0b11111;
+        value >>= 5;
+
+        if value > 0 {
+            digit |= 0b100000;
+        }
+
+        out.push(BASE64_CHARS[digit as usize] as char);
+
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(out, "\\u{:04x}", c as u32).unwrap();
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vlq() {
+        let mut s = String::new();
+        for value in [0, 1, -1, 15, 16, 123] {
+            encode_vlq(&mut s, value);
+            s.push(' ');
+        }
+
+        assert_eq!(s, "A C D e gB 2H ");
+    }
+
+    #[test]
+    fn to_json() {
+        let mut map = SourceMap::new();
+        map.set_file("out.glsl");
+        map.set_source_name(FileId::new(0), "main.glsl");
+        map.add_mapping(2.into(), FileId::new(0), 1, 2);
+        map.add_mapping(0.into(), FileId::new(0), 0, 0);
+        map.add_mapping(3.into(), FileId::new(1), 4, 0);
+
+        assert_eq!(
+            map.to_json("a\nbc"),
+            r#"{"version":3,"file":"out.glsl","sources":["main.glsl","1"],"names":[],"mappings":"AAAA;AACE,CCGF"}"#
+        );
+    }
+}
@@ -1,6 +1,9 @@
 //! Token derive support definitions
 
 /// Information about a known token
+///
+/// New fields may be added in future releases, use [TokenDescriptor::new] to build one.
+#[non_exhaustive]
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
 #[cfg_attr(feature = "serde", derive(rserde::Serialize))]
 #[cfg_attr(feature = "serde", serde(crate = "rserde"))]
@@ -13,6 +16,9 @@ pub struct TokenDescriptor {
 
     /// List of kinds this token belongs to
     pub kinds: &'static [&'static str],
+
+    /// Deprecation note, if this token has been marked with `#[lang_util(deprecated = "...")]`
+    pub deprecated: Option<&'static str>,
 }
 
 impl TokenDescriptor {
@@ -26,6 +32,15 @@ impl TokenDescriptor {
             variant_name,
             parser_token,
             kinds,
+            deprecated: None,
+        }
+    }
+
+    /// Mark this token as deprecated, with the given note
+    pub const fn with_deprecated(self, note: &'static str) -> Self {
+        Self {
+            deprecated: Some(note),
+            ..self
         }
     }
 }
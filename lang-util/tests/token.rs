@@ -0,0 +1,31 @@
+//! Tests for `derive(Token)` on deprecated variants, which must build without warnings
+#![deny(warnings)]
+
+use lang_util::Token as _;
+
+#[derive(Debug, Clone, PartialEq, lang_util::Token)]
+enum Token {
+    #[lang_util(token = "current", kind = "keyword")]
+    Current,
+    #[deprecated(note = "use Current instead")]
+    #[lang_util(token = "legacy", kind = "keyword", deprecated = "use current instead")]
+    Legacy,
+}
+
+#[test]
+fn deprecated_variant() {
+    let deprecated: Vec<_> = Token::all_tokens()
+        .iter()
+        .map(|token| (token.variant_name, token.deprecated))
+        .collect();
+    assert_eq!(
+        deprecated,
+        [("Current", None), ("Legacy", Some("use current instead"))]
+    );
+
+    assert_eq!(Token::Current.variant_name(), "Current");
+
+    #[allow(deprecated)]
+    let legacy = Token::Legacy;
+    assert_eq!(legacy.variant_name(), "Legacy");
+}
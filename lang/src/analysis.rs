@@ -0,0 +1,8 @@
+//! Analysis passes on parsed syntax trees
+
+mod const_fold;
+pub(crate) use const_fold::is_const;
+pub use const_fold::{ConstValue, ConstantFold, FoldError};
+
+pub mod scope;
+pub mod stage;
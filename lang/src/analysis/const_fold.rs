@@ -0,0 +1,835 @@
+//! Compile-time evaluation of constant expressions
+//!
+//! [ConstantFold] evaluates constant expressions, as used in array sizes or layout qualifiers, to
+//! a [ConstValue]. References to `const` variables are resolved using a [ScopeTree], and their
+//! initializers are folded in source order when building the pass.
+
+use std::collections::HashMap;
+
+use lang_util::position::NodeSpan;
+use thiserror::Error;
+
+use crate::{
+    analysis::scope::{ScopeEntryKind, ScopeTree},
+    ast::{self, LiteralValue},
+    visitor::{Host, Visit, Visitor},
+};
+
+/// Value of a constant expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    /// Scalar constant
+    Scalar(LiteralValue),
+    /// Vector constant. All components have the same type.
+    Vector(Vec<LiteralValue>),
+    /// Matrix constant, as a list of columns
+    Matrix(Vec<Vec<LiteralValue>>),
+}
+
+impl ConstValue {
+    /// Return the value of this constant if it is a scalar
+    pub fn as_scalar(&self) -> Option<LiteralValue> {
+        match self {
+            Self::Scalar(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Return the components of this constant, in column-major order for matrices
+    pub fn components(&self) -> Vec<LiteralValue> {
+        match self {
+            Self::Scalar(value) => vec![*value],
+            Self::Vector(values) => values.clone(),
+            Self::Matrix(columns) => columns.iter().flatten().copied().collect(),
+        }
+    }
+
+    /// Build an expression evaluating to this constant
+    ///
+    /// Vectors and matrices are built using constructor calls. Returns `None` if this constant
+    /// does not have a valid GLSL type, e.g. a vector of 5 components, or if it contains an
+    /// infinite or NaN component, which has no literal representation.
+    pub fn to_expr(&self) -> Option<ast::Expr> {
+        if !self.components().into_iter().all(is_finite) {
+            return None;
+        }
+
+        let (ty, components) = match self {
+            Self::Scalar(value) => return Some(ast::ExprData::from(*value).into()),
+            Self::Vector(values) => (
+                vector_type(kind_of(*values.first()?), values.len())?,
+                values.clone(),
+            ),
+            Self::Matrix(columns) => (
+                matrix_type(
+                    kind_of(*columns.first()?.first()?),
+                    columns.len(),
+                    columns[0].len(),
+                )?,
+                self.components(),
+            ),
+        };
+
+        Some(
+            ast::ExprData::FunCall(
+                ast::FunIdentifierData::TypeSpecifier(Box::new(
+                    ast::TypeSpecifierData::from(ty).into(),
+                ))
+                .into(),
+                components
+                    .into_iter()
+                    .map(|x| ast::ExprData::from(x).into())
+                    .collect(),
+            )
+            .into(),
+        )
+    }
+}
+
+impl From<LiteralValue> for ConstValue {
+    fn from(value: LiteralValue) -> Self {
+        Self::Scalar(value)
+    }
+}
+
+/// Error raised when folding an expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum FoldError {
+    /// The expression is not a constant expression
+    #[error("expression is not constant")]
+    NotConstant,
+    /// The operands do not have compatible types
+    #[error("type mismatch in constant expression")]
+    TypeMismatch,
+    /// Integer division or modulo by zero
+    #[error("division by zero in constant expression")]
+    DivisionByZero,
+    /// Index out of the bounds of a vector or matrix
+    #[error("index out of range in constant expression")]
+    IndexOutOfRange,
+    /// The expression is constant, but cannot be evaluated by this pass
+    #[error("unsupported constant expression")]
+    Unsupported,
+}
+
+/// Type of a scalar value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ScalarKind {
+    Bool,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+/// Return true if `value` is not an infinite or NaN floating-point value
+fn is_finite(value: LiteralValue) -> bool {
+    match value {
+        LiteralValue::Float(x) => x.is_finite(),
+        LiteralValue::Double(x) => x.is_finite(),
+        _ => true,
+    }
+}
+
+fn kind_of(value: LiteralValue) -> ScalarKind {
+    match value {
+        LiteralValue::Bool(_) => ScalarKind::Bool,
+        LiteralValue::Int(_) => ScalarKind::Int,
+        LiteralValue::UInt(_) => ScalarKind::UInt,
+        LiteralValue::Float(_) => ScalarKind::Float,
+        LiteralValue::Double(_) => ScalarKind::Double,
+    }
+}
+
+/// Convert `value` to `kind`, as done by constructors
+fn cast(value: LiteralValue, kind: ScalarKind) -> LiteralValue {
+    use LiteralValue::*;
+
+    match (value, kind) {
+        (Bool(x), ScalarKind::Bool) => Bool(x),
+        (Bool(x), ScalarKind::Int) => Int(x as i32),
+        (Bool(x), ScalarKind::UInt) => UInt(x as u32),
+        (Bool(x), ScalarKind::Float) => Float(x as u8 as f32),
+        (Bool(x), ScalarKind::Double) => Double(x as u8 as f64),
+        (Int(x), ScalarKind::Bool) => Bool(x != 0),
+        (Int(x), ScalarKind::Int) => Int(x),
+        (Int(x), ScalarKind::UInt) => UInt(x as u32),
+        (Int(x), ScalarKind::Float) => Float(x as f32),
+        (Int(x), ScalarKind::Double) => Double(x as f64),
+        (UInt(x), ScalarKind::Bool) => Bool(x != 0),
+        (UInt(x), ScalarKind::Int) => Int(x as i32),
+        (UInt(x), ScalarKind::UInt) => UInt(x),
+        (UInt(x), ScalarKind::Float) => Float(x as f32),
+        (UInt(x), ScalarKind::Double) => Double(x as f64),
+        (Float(x), ScalarKind::Bool) => Bool(x != 0.),
+        (Float(x), ScalarKind::Int) => Int(x as i32),
+        (Float(x), ScalarKind::UInt) => UInt(x as u32),
+        (Float(x), ScalarKind::Float) => Float(x),
+        (Float(x), ScalarKind::Double) => Double(x as f64),
+        (Double(x), ScalarKind::Bool) => Bool(x != 0.),
+        (Double(x), ScalarKind::Int) => Int(x as i32),
+        (Double(x), ScalarKind::UInt) => UInt(x as u32),
+        (Double(x), ScalarKind::Float) => Float(x as f32),
+        (Double(x), ScalarKind::Double) => Double(x),
+    }
+}
+
+/// Apply the implicit conversions to bring both operands to the same type
+fn promote(
+    lhs: LiteralValue,
+    rhs: LiteralValue,
+) -> Result<(LiteralValue, LiteralValue), FoldError> {
+    let (lkind, rkind) = (kind_of(lhs), kind_of(rhs));
+
+    if lkind == rkind {
+        Ok((lhs, rhs))
+    } else if lkind == ScalarKind::Bool || rkind == ScalarKind::Bool {
+        Err(FoldError::TypeMismatch)
+    } else {
+        let kind = lkind.max(rkind);
+        Ok((cast(lhs, kind), cast(rhs, kind)))
+    }
+}
+
+/// Evaluate an arithmetic or bitwise operator on scalars
+fn scalar_arith(
+    op: &ast::BinaryOpData,
+    lhs: LiteralValue,
+    rhs: LiteralValue,
+) -> Result<LiteralValue, FoldError> {
+    use ast::BinaryOpData as Op;
+    use LiteralValue::*;
+
+    // The result of a shift has the type of its left operand
+    if let Op::LShift | Op::RShift = op {
+        let amount = match rhs {
+            Int(x) => x as u32,
+            UInt(x) => x,
+            _ => return Err(FoldError::TypeMismatch),
+        };
+
+        return match (op, lhs) {
+            (Op::LShift, Int(x)) => Ok(Int(x.wrapping_shl(amount))),
+            (Op::LShift, UInt(x)) => Ok(UInt(x.wrapping_shl(amount))),
+            (Op::RShift, Int(x)) => Ok(Int(x.wrapping_shr(amount))),
+            (Op::RShift, UInt(x)) => Ok(UInt(x.wrapping_shr(amount))),
+            _ => Err(FoldError::TypeMismatch),
+        };
+    }
+
+    macro_rules! integer_op {
+        ($x:ident, $y:ident, $variant:ident) => {
+            match op {
+                Op::Add => $variant($x.wrapping_add($y)),
+                Op::Sub => $variant($x.wrapping_sub($y)),
+                Op::Mult => $variant($x.wrapping_mul($y)),
+                Op::Div | Op::Mod if $y == 0 => return Err(FoldError::DivisionByZero),
+                Op::Div => $variant($x.wrapping_div($y)),
+                Op::Mod => $variant($x.wrapping_rem($y)),
+                Op::BitOr => $variant($x | $y),
+                Op::BitXor => $variant($x ^ $y),
+                Op::BitAnd => $variant($x & $y),
+                _ => return Err(FoldError::TypeMismatch),
+            }
+        };
+    }
+
+    macro_rules! float_op {
+        ($x:ident, $y:ident, $variant:ident) => {
+            match op {
+                Op::Add => $variant($x + $y),
+                Op::Sub => $variant($x - $y),
+                Op::Mult => $variant($x * $y),
+                Op::Div => $variant($x / $y),
+                _ => return Err(FoldError::TypeMismatch),
+            }
+        };
+    }
+
+    Ok(match promote(lhs, rhs)? {
+        (Int(x), Int(y)) => integer_op!(x, y, Int),
+        (UInt(x), UInt(y)) => integer_op!(x, y, UInt),
+        (Float(x), Float(y)) => float_op!(x, y, Float),
+        (Double(x), Double(y)) => float_op!(x, y, Double),
+        _ => return Err(FoldError::TypeMismatch),
+    })
+}
+
+/// Compare two numeric scalars, after implicit conversions
+fn scalar_cmp(lhs: LiteralValue, rhs: LiteralValue) -> Result<std::cmp::Ordering, FoldError> {
+    use LiteralValue::*;
+
+    match promote(lhs, rhs)? {
+        (Int(x), Int(y)) => Ok(x.cmp(&y)),
+        (UInt(x), UInt(y)) => Ok(x.cmp(&y)),
+        (Float(x), Float(y)) => x.partial_cmp(&y).ok_or(FoldError::Unsupported),
+        (Double(x), Double(y)) => x.partial_cmp(&y).ok_or(FoldError::Unsupported),
+        _ => Err(FoldError::TypeMismatch),
+    }
+}
+
+fn scalar_min(lhs: LiteralValue, rhs: LiteralValue) -> Result<LiteralValue, FoldError> {
+    let (lhs, rhs) = promote(lhs, rhs)?;
+    Ok(if scalar_cmp(rhs, lhs)?.is_lt() {
+        rhs
+    } else {
+        lhs
+    })
+}
+
+fn scalar_max(lhs: LiteralValue, rhs: LiteralValue) -> Result<LiteralValue, FoldError> {
+    let (lhs, rhs) = promote(lhs, rhs)?;
+    Ok(if scalar_cmp(rhs, lhs)?.is_gt() {
+        rhs
+    } else {
+        lhs
+    })
+}
+
+/// Apply `f` to every component of `value`
+fn map(
+    value: ConstValue,
+    f: impl Fn(LiteralValue) -> Result<LiteralValue, FoldError>,
+) -> Result<ConstValue, FoldError> {
+    Ok(match value {
+        ConstValue::Scalar(x) => ConstValue::Scalar(f(x)?),
+        ConstValue::Vector(xs) => {
+            ConstValue::Vector(xs.into_iter().map(f).collect::<Result<_, _>>()?)
+        }
+        ConstValue::Matrix(columns) => ConstValue::Matrix(
+            columns
+                .into_iter()
+                .map(|column| column.into_iter().map(&f).collect())
+                .collect::<Result<_, _>>()?,
+        ),
+    })
+}
+
+/// Apply `f` component-wise on `lhs` and `rhs`, broadcasting scalar operands
+fn zip(
+    lhs: ConstValue,
+    rhs: ConstValue,
+    f: impl Fn(LiteralValue, LiteralValue) -> Result<LiteralValue, FoldError>,
+) -> Result<ConstValue, FoldError> {
+    match (lhs, rhs) {
+        (lhs, ConstValue::Scalar(y)) => map(lhs, |x| f(x, y)),
+        (ConstValue::Scalar(x), rhs) => map(rhs, |y| f(x, y)),
+        (ConstValue::Vector(xs), ConstValue::Vector(ys)) if xs.len() == ys.len() => {
+            Ok(ConstValue::Vector(
+                xs.into_iter()
+                    .zip(ys)
+                    .map(|(x, y)| f(x, y))
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+        (ConstValue::Matrix(xs), ConstValue::Matrix(ys))
+            if xs.len() == ys.len() && xs.iter().zip(&ys).all(|(x, y)| x.len() == y.len()) =>
+        {
+            Ok(ConstValue::Matrix(
+                xs.into_iter()
+                    .zip(ys)
+                    .map(|(x, y)| x.into_iter().zip(y).map(|(x, y)| f(x, y)).collect())
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+        _ => Err(FoldError::TypeMismatch),
+    }
+}
+
+/// Type constructed by a constructor call
+enum ConstructedType {
+    Scalar(ScalarKind),
+    Vector(ScalarKind, usize),
+    Matrix(ScalarKind, usize, usize),
+}
+
+fn constructed_type(ty: &ast::TypeSpecifierNonArrayData) -> Option<ConstructedType> {
+    use ast::TypeSpecifierNonArrayData::*;
+    use ConstructedType::{Matrix as M, Scalar as S, Vector as V};
+    use ScalarKind::{Bool as B, Double as D, Float as F, Int as I, UInt as U};
+
+    Some(match ty {
+        Bool => S(B),
+        Int => S(I),
+        UInt => S(U),
+        Float => S(F),
+        Double => S(D),
+        Vec2 => V(F, 2),
+        Vec3 => V(F, 3),
+        Vec4 => V(F, 4),
+        DVec2 => V(D, 2),
+        DVec3 => V(D, 3),
+        DVec4 => V(D, 4),
+        BVec2 => V(B, 2),
+        BVec3 => V(B, 3),
+        BVec4 => V(B, 4),
+        IVec2 => V(I, 2),
+        IVec3 => V(I, 3),
+        IVec4 => V(I, 4),
+        UVec2 => V(U, 2),
+        UVec3 => V(U, 3),
+        UVec4 => V(U, 4),
+        Mat2 | Mat22 => M(F, 2, 2),
+        Mat3 | Mat33 => M(F, 3, 3),
+        Mat4 | Mat44 => M(F, 4, 4),
+        Mat23 => M(F, 2, 3),
+        Mat24 => M(F, 2, 4),
+        Mat32 => M(F, 3, 2),
+        Mat34 => M(F, 3, 4),
+        Mat42 => M(F, 4, 2),
+        Mat43 => M(F, 4, 3),
+        DMat2 | DMat22 => M(D, 2, 2),
+        DMat3 | DMat33 => M(D, 3, 3),
+        DMat4 | DMat44 => M(D, 4, 4),
+        DMat23 => M(D, 2, 3),
+        DMat24 => M(D, 2, 4),
+        DMat32 => M(D, 3, 2),
+        DMat34 => M(D, 3, 4),
+        DMat42 => M(D, 4, 2),
+        DMat43 => M(D, 4, 3),
+        _ => return None,
+    })
+}
+
+fn vector_type(kind: ScalarKind, size: usize) -> Option<ast::TypeSpecifierNonArrayData> {
+    use ast::TypeSpecifierNonArrayData::*;
+
+    Some(match (kind, size) {
+        (ScalarKind::Bool, 2) => BVec2,
+        (ScalarKind::Bool, 3) => BVec3,
+        (ScalarKind::Bool, 4) => BVec4,
+        (ScalarKind::Int, 2) => IVec2,
+        (ScalarKind::Int, 3) => IVec3,
+        (ScalarKind::Int, 4) => IVec4,
+        (ScalarKind::UInt, 2) => UVec2,
+        (ScalarKind::UInt, 3) => UVec3,
+        (ScalarKind::UInt, 4) => UVec4,
+        (ScalarKind::Float, 2) => Vec2,
+        (ScalarKind::Float, 3) => Vec3,
+        (ScalarKind::Float, 4) => Vec4,
+        (ScalarKind::Double, 2) => DVec2,
+        (ScalarKind::Double, 3) => DVec3,
+        (ScalarKind::Double, 4) => DVec4,
+        _ => return None,
+    })
+}
+
+fn matrix_type(
+    kind: ScalarKind,
+    columns: usize,
+    rows: usize,
+) -> Option<ast::TypeSpecifierNonArrayData> {
+    use ast::TypeSpecifierNonArrayData::*;
+
+    Some(match (kind, columns, rows) {
+        (ScalarKind::Float, 2, 2) => Mat2,
+        (ScalarKind::Float, 2, 3) => Mat23,
+        (ScalarKind::Float, 2, 4) => Mat24,
+        (ScalarKind::Float, 3, 2) => Mat32,
+        (ScalarKind::Float, 3, 3) => Mat3,
+        (ScalarKind::Float, 3, 4) => Mat34,
+        (ScalarKind::Float, 4, 2) => Mat42,
+        (ScalarKind::Float, 4, 3) => Mat43,
+        (ScalarKind::Float, 4, 4) => Mat4,
+        (ScalarKind::Double, 2, 2) => DMat2,
+        (ScalarKind::Double, 2, 3) => DMat23,
+        (ScalarKind::Double, 2, 4) => DMat24,
+        (ScalarKind::Double, 3, 2) => DMat32,
+        (ScalarKind::Double, 3, 3) => DMat3,
+        (ScalarKind::Double, 3, 4) => DMat34,
+        (ScalarKind::Double, 4, 2) => DMat42,
+        (ScalarKind::Double, 4, 3) => DMat43,
+        (ScalarKind::Double, 4, 4) => DMat4,
+        _ => return None,
+    })
+}
+
+fn construct(ty: ConstructedType, args: Vec<ConstValue>) -> Result<ConstValue, FoldError> {
+    let components: Vec<_> = args.iter().flat_map(ConstValue::components).collect();
+
+    match ty {
+        ConstructedType::Scalar(kind) => components
+            .first()
+            .map(|&x| ConstValue::Scalar(cast(x, kind)))
+            .ok_or(FoldError::TypeMismatch),
+
+        ConstructedType::Vector(kind, size) => {
+            if let [ConstValue::Scalar(x)] = args[..] {
+                Ok(ConstValue::Vector(vec![cast(x, kind); size]))
+            } else if components.len() >= size {
+                Ok(ConstValue::Vector(
+                    components[..size].iter().map(|&x| cast(x, kind)).collect(),
+                ))
+            } else {
+                Err(FoldError::TypeMismatch)
+            }
+        }
+
+        ConstructedType::Matrix(kind, columns, rows) => {
+            let zero = cast(LiteralValue::Int(0), kind);
+            let one = cast(LiteralValue::Int(1), kind);
+
+            match &args[..] {
+                // Diagonal matrix
+                [ConstValue::Scalar(x)] => Ok(ConstValue::Matrix(
+                    (0..columns)
+                        .map(|c| {
+                            (0..rows)
+                                .map(|r| if c == r { cast(*x, kind) } else { zero })
+                                .collect()
+                        })
+                        .collect(),
+                )),
+                // Resized matrix, filled from the identity
+                [ConstValue::Matrix(m)] => Ok(ConstValue::Matrix(
+                    (0..columns)
+                        .map(|c| {
+                            (0..rows)
+                                .map(|r| match m.get(c).and_then(|column| column.get(r)) {
+                                    Some(&x) => cast(x, kind),
+                                    None if c == r => one,
+                                    None => zero,
+                                })
+                                .collect()
+                        })
+                        .collect(),
+                )),
+                _ if components.len() == columns * rows => Ok(ConstValue::Matrix(
+                    components
+                        .chunks(rows)
+                        .map(|column| column.iter().map(|&x| cast(x, kind)).collect())
+                        .collect(),
+                )),
+                _ => Err(FoldError::TypeMismatch),
+            }
+        }
+    }
+}
+
+/// Evaluate a constant built-in function
+fn builtin(name: &str, args: Vec<ConstValue>) -> Result<ConstValue, FoldError> {
+    use LiteralValue::*;
+
+    let mut args = args.into_iter();
+    let mut next = || args.next().ok_or(FoldError::TypeMismatch);
+
+    match name {
+        "abs" => map(next()?, |x| match x {
+            Int(x) => Ok(Int(x.wrapping_abs())),
+            Float(x) => Ok(Float(x.abs())),
+            Double(x) => Ok(Double(x.abs())),
+            _ => Err(FoldError::TypeMismatch),
+        }),
+        "sign" => map(next()?, |x| match x {
+            Int(x) => Ok(Int(x.signum())),
+            Float(x) => Ok(Float(if x == 0. { 0. } else { x.signum() })),
+            Double(x) => Ok(Double(if x == 0. { 0. } else { x.signum() })),
+            _ => Err(FoldError::TypeMismatch),
+        }),
+        "floor" => map(next()?, |x| match x {
+            Float(x) => Ok(Float(x.floor())),
+            Double(x) => Ok(Double(x.floor())),
+            _ => Err(FoldError::TypeMismatch),
+        }),
+        "ceil" => map(next()?, |x| match x {
+            Float(x) => Ok(Float(x.ceil())),
+            Double(x) => Ok(Double(x.ceil())),
+            _ => Err(FoldError::TypeMismatch),
+        }),
+        "min" => zip(next()?, next()?, scalar_min),
+        "max" => zip(next()?, next()?, scalar_max),
+        "clamp" => {
+            let (x, lo, hi) = (next()?, next()?, next()?);
+            zip(zip(x, lo, scalar_max)?, hi, scalar_min)
+        }
+        _ => Err(FoldError::NotConstant),
+    }
+}
+
+/// Swizzle a vector using the components named by `field`
+fn swizzle(components: Vec<LiteralValue>, field: &str) -> Result<ConstValue, FoldError> {
+    const SETS: [&str; 3] = ["xyzw", "rgba", "stpq"];
+
+    let set = field
+        .chars()
+        .next()
+        .and_then(|first| SETS.iter().find(|set| set.contains(first)))
+        .ok_or(FoldError::Unsupported)?;
+
+    let values = field
+        .chars()
+        .map(|c| {
+            let index = set.find(c).ok_or(FoldError::TypeMismatch)?;
+            components
+                .get(index)
+                .copied()
+                .ok_or(FoldError::IndexOutOfRange)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match values[..] {
+        [value] => Ok(ConstValue::Scalar(value)),
+        _ if values.len() <= 4 => Ok(ConstValue::Vector(values)),
+        _ => Err(FoldError::TypeMismatch),
+    }
+}
+
+/// Constant folding pass
+///
+/// Folding is only supported on expressions from the translation unit the pass was built from,
+/// since variable references are resolved from their location in the source.
+#[derive(Debug)]
+pub struct ConstantFold {
+    scopes: ScopeTree,
+    /// Values of the folded `const` variables, by span of their declared identifier
+    values: HashMap<NodeSpan, ConstValue>,
+}
+
+impl ConstantFold {
+    /// Build the constant folding pass for a translation unit, folding the initializers of its
+    /// `const` variables
+    pub fn new(tu: &ast::TranslationUnit) -> Self {
+        let mut this = Self {
+            scopes: ScopeTree::new(tu),
+            values: HashMap::new(),
+        };
+
+        tu.visit(&mut this);
+        this
+    }
+
+    /// Evaluate a constant expression
+    pub fn fold(&self, expr: &ast::Expr) -> Result<ConstValue, FoldError> {
+        use ast::{BinaryOpData as Op, ExprData, UnaryOpData};
+
+        match &**expr {
+            ExprData::IntConst(x) => Ok(LiteralValue::Int(*x).into()),
+            ExprData::UIntConst(x) => Ok(LiteralValue::UInt(*x).into()),
+            ExprData::BoolConst(x) => Ok(LiteralValue::Bool(*x).into()),
+            ExprData::FloatConst(x) => Ok(LiteralValue::Float(*x).into()),
+            ExprData::DoubleConst(x) => Ok(LiteralValue::Double(*x).into()),
+
+            ExprData::Variable(ident) => {
+                let span = ident.span.ok_or(FoldError::NotConstant)?;
+                let entry = self
+                    .scopes
+                    .resolve(ident.as_str(), span)
+                    .ok_or(FoldError::NotConstant)?;
+
+                self.values
+                    .get(&entry.span)
+                    .cloned()
+                    .ok_or(FoldError::NotConstant)
+            }
+
+            ExprData::Unary(op, operand) => {
+                let operand = self.fold(operand)?;
+
+                match &**op {
+                    UnaryOpData::Inc | UnaryOpData::Dec => Err(FoldError::NotConstant),
+                    UnaryOpData::Add => map(operand, |x| match x {
+                        LiteralValue::Bool(_) => Err(FoldError::TypeMismatch),
+                        x => Ok(x),
+                    }),
+                    UnaryOpData::Minus => map(operand, |x| match x {
+                        LiteralValue::Int(x) => Ok(LiteralValue::Int(x.wrapping_neg())),
+                        LiteralValue::UInt(x) => Ok(LiteralValue::UInt(x.wrapping_neg())),
+                        LiteralValue::Float(x) => Ok(LiteralValue::Float(-x)),
+                        LiteralValue::Double(x) => Ok(LiteralValue::Double(-x)),
+                        LiteralValue::Bool(_) => Err(FoldError::TypeMismatch),
+                    }),
+                    UnaryOpData::Not => match operand {
+                        ConstValue::Scalar(LiteralValue::Bool(x)) => {
+                            Ok(LiteralValue::Bool(!x).into())
+                        }
+                        _ => Err(FoldError::TypeMismatch),
+                    },
+                    UnaryOpData::Complement => map(operand, |x| match x {
+                        LiteralValue::Int(x) => Ok(LiteralValue::Int(!x)),
+                        LiteralValue::UInt(x) => Ok(LiteralValue::UInt(!x)),
+                        _ => Err(FoldError::TypeMismatch),
+                    }),
+                }
+            }
+
+            ExprData::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (self.fold(lhs)?, self.fold(rhs)?);
+
+                match &**op {
+                    Op::Or | Op::Xor | Op::And => match (lhs, rhs) {
+                        (
+                            ConstValue::Scalar(LiteralValue::Bool(x)),
+                            ConstValue::Scalar(LiteralValue::Bool(y)),
+                        ) => Ok(LiteralValue::Bool(match &**op {
+                            Op::Or => x || y,
+                            Op::Xor => x != y,
+                            _ => x && y,
+                        })
+                        .into()),
+                        _ => Err(FoldError::TypeMismatch),
+                    },
+
+                    Op::Equal | Op::NonEqual => {
+                        // Compare component-wise, after implicit conversions
+                        let equal = zip(lhs, rhs, |x, y| {
+                            let (x, y) = promote(x, y)?;
+                            Ok(LiteralValue::Bool(x == y))
+                        })?
+                        .components()
+                        .into_iter()
+                        .all(|x| x == LiteralValue::Bool(true));
+
+                        Ok(LiteralValue::Bool(equal == matches!(&**op, Op::Equal)).into())
+                    }
+
+                    Op::Lt | Op::Gt | Op::Lte | Op::Gte => match (lhs, rhs) {
+                        (ConstValue::Scalar(x), ConstValue::Scalar(y)) => {
+                            let ordering = scalar_cmp(x, y)?;
+
+                            Ok(LiteralValue::Bool(match &**op {
+                                Op::Lt => ordering.is_lt(),
+                                Op::Gt => ordering.is_gt(),
+                                Op::Lte => ordering.is_le(),
+                                _ => ordering.is_ge(),
+                            })
+                            .into())
+                        }
+                        _ => Err(FoldError::TypeMismatch),
+                    },
+
+                    // Linear algebra products are not supported
+                    Op::Mult
+                        if matches!(
+                            (&lhs, &rhs),
+                            (
+                                ConstValue::Matrix(_),
+                                ConstValue::Vector(_) | ConstValue::Matrix(_)
+                            ) | (ConstValue::Vector(_), ConstValue::Matrix(_))
+                        ) =>
+                    {
+                        Err(FoldError::Unsupported)
+                    }
+
+                    op => zip(lhs, rhs, |x, y| scalar_arith(op, x, y)),
+                }
+            }
+
+            ExprData::Ternary(condition, then, otherwise) => match self.fold(condition)? {
+                ConstValue::Scalar(LiteralValue::Bool(true)) => self.fold(then),
+                ConstValue::Scalar(LiteralValue::Bool(false)) => self.fold(otherwise),
+                _ => Err(FoldError::TypeMismatch),
+            },
+
+            ExprData::Bracket(base, index) => {
+                let base = self.fold(base)?;
+                let index = match self.fold(index)? {
+                    ConstValue::Scalar(LiteralValue::Int(x)) => {
+                        usize::try_from(x).map_err(|_| FoldError::IndexOutOfRange)?
+                    }
+                    ConstValue::Scalar(LiteralValue::UInt(x)) => x as usize,
+                    _ => return Err(FoldError::TypeMismatch),
+                };
+
+                match base {
+                    ConstValue::Vector(xs) => xs
+                        .get(index)
+                        .map(|&x| x.into())
+                        .ok_or(FoldError::IndexOutOfRange),
+                    ConstValue::Matrix(mut columns) if index < columns.len() => {
+                        Ok(ConstValue::Vector(columns.swap_remove(index)))
+                    }
+                    ConstValue::Matrix(_) => Err(FoldError::IndexOutOfRange),
+                    ConstValue::Scalar(_) => Err(FoldError::TypeMismatch),
+                }
+            }
+
+            ExprData::Dot(base, field) => match self.fold(base)? {
+                ConstValue::Matrix(_) => Err(FoldError::TypeMismatch),
+                base => swizzle(base.components(), field.as_str()),
+            },
+
+            ExprData::FunCall(fun, args) => {
+                let name = match &**fun {
+                    ast::FunIdentifierData::TypeSpecifier(ty) => {
+                        if ty.array_specifier.is_some() {
+                            return Err(FoldError::Unsupported);
+                        }
+
+                        let ty = constructed_type(&ty.ty).ok_or(FoldError::Unsupported)?;
+                        let args = args
+                            .iter()
+                            .map(|arg| self.fold(arg))
+                            .collect::<Result<_, _>>()?;
+                        return construct(ty, args);
+                    }
+                    ast::FunIdentifierData::Expr(_) => {
+                        fun.as_ident().ok_or(FoldError::NotConstant)?
+                    }
+                };
+
+                // User-defined functions shadow the built-ins
+                if let Some(span) = name.span {
+                    if self
+                        .scopes
+                        .resolve(name.as_str(), span)
+                        .is_some_and(|entry| entry.kind == ScopeEntryKind::Function)
+                    {
+                        return Err(FoldError::NotConstant);
+                    }
+                }
+
+                let args = args
+                    .iter()
+                    .map(|arg| self.fold(arg))
+                    .collect::<Result<_, _>>()?;
+                builtin(name.as_str(), args)
+            }
+
+            ExprData::Assignment(_, _, _)
+            | ExprData::PostInc(_)
+            | ExprData::PostDec(_)
+            | ExprData::Comma(_, _) => Err(FoldError::NotConstant),
+        }
+    }
+}
+
+/// Return `true` if `ty` has a `const` storage qualifier
+pub(crate) fn is_const(ty: &ast::FullySpecifiedType) -> bool {
+    ty.qualifier.as_ref().is_some_and(|qualifier| {
+        qualifier.qualifiers.iter().any(|qualifier| {
+            matches!(
+                &**qualifier,
+                ast::TypeQualifierSpecData::Storage(storage)
+                    if **storage == ast::StorageQualifierData::Const
+            )
+        })
+    })
+}
+
+impl Visitor for ConstantFold {
+    fn visit_init_declarator_list(&mut self, list: &ast::InitDeclaratorList) -> Visit {
+        if is_const(&list.head.ty) {
+            let declarators =
+                list.head
+                    .name
+                    .as_ref()
+                    .map(|name| (name, list.head.initializer.as_ref()))
+                    .into_iter()
+                    .chain(list.tail.iter().map(|declarator| {
+                        (&declarator.ident.ident, declarator.initializer.as_ref())
+                    }));
+
+            for (name, initializer) in declarators {
+                if let (Some(span), Some(initializer)) = (name.span, initializer) {
+                    if let ast::InitializerData::Simple(expr) = &**initializer {
+                        if let Ok(value) = self.fold(expr) {
+                            self.values.insert(span, value);
+                        }
+                    }
+                }
+            }
+        }
+
+        Visit::Parent
+    }
+}
@@ -0,0 +1,370 @@
+//! Resolution of identifiers to their declarations
+//!
+//! A [ScopeTree] records the variables, parameters and functions declared in a translation unit,
+//! along with the scopes they are declared in. Identifiers can then be resolved from their
+//! location in the source, following the GLSL scoping rules.
+//!
+//! The tree is built from node spans, so the translation unit should come from the parser rather
+//! than being built programmatically. Spans carry their source file, so translation units
+//! assembled from several files (e.g. through `#include`) are supported: a declaration from
+//! another file than the use is considered visible if it is in an enclosing scope.
+
+use std::collections::HashMap;
+
+use lang_util::{
+    position::{LexerPosition, NodeSpan},
+    SmolStr,
+};
+
+use crate::ast;
+
+/// Kind of declaration recorded in a [ScopeEntry]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeEntryKind {
+    /// Variable, including block instances and loop condition variables
+    Variable,
+    /// Function parameter
+    Parameter,
+    /// Function prototype or definition
+    Function,
+}
+
+/// Declaration of a name in a [ScopeTree]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeEntry {
+    /// Declared name
+    pub name: SmolStr,
+    /// Kind of declaration
+    pub kind: ScopeEntryKind,
+    /// Span of the declared identifier
+    pub span: NodeSpan,
+    /// Declared type. For functions, this is the return type.
+    pub ty: ast::TypeSpecifier,
+    /// Storage qualifier of the declaration, if any
+    pub storage: Option<ast::StorageQualifier>,
+    /// Position from which the declaration is visible
+    visible_from: LexerPosition,
+}
+
+#[derive(Debug)]
+struct Scope {
+    span: Option<NodeSpan>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    entries: HashMap<SmolStr, Vec<ScopeEntry>>,
+}
+
+/// Tree of the scopes of a translation unit, and the names they declare
+#[derive(Debug)]
+pub struct ScopeTree {
+    /// Scopes in traversal order, the first one is the global scope
+    scopes: Vec<Scope>,
+}
+
+fn storage_of(qualifier: Option<&ast::TypeQualifier>) -> Option<ast::StorageQualifier> {
+    qualifier?
+        .qualifiers
+        .iter()
+        .find_map(|qualifier| match &**qualifier {
+            ast::TypeQualifierSpecData::Storage(storage) => Some(storage.clone()),
+            _ => None,
+        })
+}
+
+struct Builder {
+    scopes: Vec<Scope>,
+    stack: Vec<usize>,
+}
+
+impl Builder {
+    fn current(&mut self) -> &mut Scope {
+        &mut self.scopes[*self.stack.last().unwrap()]
+    }
+
+    fn push_scope(&mut self, span: Option<NodeSpan>) {
+        let index = self.scopes.len();
+        let parent = self.stack.last().copied();
+
+        self.scopes.push(Scope {
+            span,
+            parent,
+            children: Vec::new(),
+            entries: HashMap::new(),
+        });
+
+        if let Some(parent) = parent {
+            self.scopes[parent].children.push(index);
+        }
+
+        self.stack.push(index);
+    }
+
+    fn pop_scope(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Declare `ident` in the current scope, visible after `visible_after` (or the identifier
+    /// itself if not specified)
+    ///
+    /// Identifiers without a span cannot be resolved, so they are not recorded.
+    fn declare(
+        &mut self,
+        ident: &ast::Identifier,
+        kind: ScopeEntryKind,
+        ty: &ast::TypeSpecifier,
+        storage: Option<ast::StorageQualifier>,
+        visible_after: Option<NodeSpan>,
+    ) {
+        let Some(span) = ident.span else {
+            return;
+        };
+        let visible_from = visible_after.unwrap_or(span).end();
+
+        self.current()
+            .entries
+            .entry(ident.0.clone())
+            .or_default()
+            .push(ScopeEntry {
+                name: ident.0.clone(),
+                kind,
+                span,
+                ty: ty.clone(),
+                storage,
+                visible_from,
+            });
+    }
+
+    fn external_declaration(&mut self, declaration: &ast::ExternalDeclaration) {
+        match &**declaration {
+            ast::ExternalDeclarationData::Preprocessor(_) => {}
+            ast::ExternalDeclarationData::FunctionDefinition(definition) => {
+                self.function_prototype(&definition.prototype);
+
+                // Parameters and the function body share the same scope
+                self.push_scope(definition.span);
+                self.parameters(&definition.prototype);
+                for statement in &definition.statement.statement_list {
+                    self.statement(statement);
+                }
+                self.pop_scope();
+            }
+            ast::ExternalDeclarationData::Declaration(declaration) => {
+                self.declaration(declaration);
+            }
+        }
+    }
+
+    fn function_prototype(&mut self, prototype: &ast::FunctionPrototype) {
+        self.declare(
+            &prototype.name,
+            ScopeEntryKind::Function,
+            &prototype.ty.ty,
+            None,
+            None,
+        );
+    }
+
+    fn parameters(&mut self, prototype: &ast::FunctionPrototype) {
+        for parameter in &prototype.parameters {
+            if let ast::FunctionParameterDeclarationData::Named(qualifier, declarator) =
+                &**parameter
+            {
+                self.declare(
+                    &declarator.ident.ident,
+                    ScopeEntryKind::Parameter,
+                    &declarator.ty,
+                    storage_of(qualifier.as_ref()),
+                    None,
+                );
+            }
+        }
+    }
+
+    fn declaration(&mut self, declaration: &ast::Declaration) {
+        match &**declaration {
+            ast::DeclarationData::FunctionPrototype(prototype) => {
+                self.function_prototype(prototype);
+            }
+            ast::DeclarationData::InitDeclaratorList(list) => {
+                let ty = &list.head.ty;
+                let storage = storage_of(ty.qualifier.as_ref());
+
+                if let Some(name) = &list.head.name {
+                    // A variable is visible right after its initializer, if any
+                    self.declare(
+                        name,
+                        ScopeEntryKind::Variable,
+                        &ty.ty,
+                        storage.clone(),
+                        list.head.initializer.as_ref().and_then(|init| init.span),
+                    );
+                }
+
+                for declarator in &list.tail {
+                    self.declare(
+                        &declarator.ident.ident,
+                        ScopeEntryKind::Variable,
+                        &ty.ty,
+                        storage.clone(),
+                        declarator.initializer.as_ref().and_then(|init| init.span),
+                    );
+                }
+            }
+            ast::DeclarationData::Block(block) => {
+                if let Some(identifier) = &block.identifier {
+                    let ty: ast::TypeSpecifier =
+                        ast::TypeSpecifierData::from(ast::TypeSpecifierNonArrayData::TypeName(
+                            ast::TypeNameData(block.name.0.clone()).into(),
+                        ))
+                        .into();
+
+                    self.declare(
+                        &identifier.ident,
+                        ScopeEntryKind::Variable,
+                        &ty,
+                        storage_of(Some(&block.qualifier)),
+                        None,
+                    );
+                }
+            }
+            ast::DeclarationData::Precision(_, _) | ast::DeclarationData::Invariant(_) => {}
+        }
+    }
+
+    fn condition(&mut self, condition: &ast::Condition) {
+        if let ast::ConditionData::Assignment(ty, ident, initializer) = &**condition {
+            self.declare(
+                ident,
+                ScopeEntryKind::Variable,
+                &ty.ty,
+                storage_of(ty.qualifier.as_ref()),
+                initializer.span,
+            );
+        }
+    }
+
+    fn statement(&mut self, statement: &ast::Statement) {
+        match &**statement {
+            ast::StatementData::Declaration(declaration) => self.declaration(declaration),
+            ast::StatementData::Compound(compound) => {
+                self.push_scope(compound.span);
+                for statement in &compound.statement_list {
+                    self.statement(statement);
+                }
+                self.pop_scope();
+            }
+            ast::StatementData::Selection(selection) => match &*selection.rest {
+                ast::SelectionRestStatementData::Statement(body) => self.statement(body),
+                ast::SelectionRestStatementData::Else(body, rest) => {
+                    self.statement(body);
+                    self.statement(rest);
+                }
+            },
+            ast::StatementData::Switch(switch) => {
+                self.push_scope(switch.span);
+                for statement in &switch.body {
+                    self.statement(statement);
+                }
+                self.pop_scope();
+            }
+            ast::StatementData::Iteration(iteration) => {
+                // Loop headers introduce a scope which also contains the loop body
+                self.push_scope(iteration.span);
+                match &**iteration {
+                    ast::IterationStatementData::While(condition, body) => {
+                        self.condition(condition);
+                        self.statement(body);
+                    }
+                    ast::IterationStatementData::DoWhile(body, _) => {
+                        self.statement(body);
+                    }
+                    ast::IterationStatementData::For(init, rest, body) => {
+                        if let ast::ForInitStatementData::Declaration(declaration) = &**init {
+                            self.declaration(declaration);
+                        }
+
+                        if let Some(condition) = &rest.condition {
+                            self.condition(condition);
+                        }
+
+                        self.statement(body);
+                    }
+                }
+                self.pop_scope();
+            }
+            ast::StatementData::Expression(_)
+            | ast::StatementData::CaseLabel(_)
+            | ast::StatementData::Jump(_) => {}
+        }
+    }
+}
+
+impl ScopeTree {
+    /// Build the scope tree of a translation unit
+    pub fn new(tu: &ast::TranslationUnit) -> Self {
+        let mut builder = Builder {
+            scopes: Vec::new(),
+            stack: Vec::new(),
+        };
+
+        // The global scope covers the whole source
+        builder.push_scope(None);
+        for declaration in &tu.0 {
+            builder.external_declaration(declaration);
+        }
+
+        Self {
+            scopes: builder.scopes,
+        }
+    }
+
+    /// Find the innermost scope containing `position`
+    fn innermost_scope(&self, position: LexerPosition) -> usize {
+        let mut current = 0;
+
+        while let Some(&child) = self.scopes[current].children.iter().find(|&&child| {
+            self.scopes[child].span.is_some_and(|span| {
+                span.source_id() == position.source_id && span.range().contains(position.offset)
+            })
+        }) {
+            current = child;
+        }
+
+        current
+    }
+
+    /// Resolve the identifier `name` used at `at_span` to its declaration
+    ///
+    /// Only declarations which are visible at `at_span` are considered: declarations in enclosing
+    /// scopes, which appear before `at_span` in the source. Declarations from other files are
+    /// assumed to appear before `at_span`. When multiple declarations match, e.g. a function
+    /// prototype and its definition, the last one is returned.
+    pub fn resolve(&self, name: &str, at_span: NodeSpan) -> Option<&ScopeEntry> {
+        let position = at_span.start();
+        let mut scope = Some(self.innermost_scope(position));
+
+        while let Some(index) = scope {
+            let current = &self.scopes[index];
+
+            if let Some(entry) = current.entries.get(name).and_then(|entries| {
+                entries.iter().rev().find(|entry| {
+                    entry.visible_from.source_id != position.source_id
+                        || entry.visible_from.offset <= position.offset
+                })
+            }) {
+                return Some(entry);
+            }
+
+            scope = current.parent;
+        }
+
+        None
+    }
+
+    /// Return all the declarations recorded in this tree, in no particular order
+    pub fn entries(&self) -> impl Iterator<Item = &ScopeEntry> {
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.entries.values().flatten())
+    }
+}
@@ -0,0 +1,217 @@
+//! Validation of built-in usages against the shader stage
+//!
+//! Some built-in variables and functions are only available in specific stages, e.g.
+//! `gl_FragDepth` in fragment shaders or `EmitVertex` in geometry shaders. [validate_stage]
+//! reports the usages of these built-ins which are not valid for a given [ShaderStage].
+
+use std::collections::HashSet;
+
+use lang_util::{position::NodeSpan, SmolStr};
+use thiserror::Error;
+
+use crate::{
+    ast,
+    parse::ShaderStage,
+    visitor::{Host, Visit, Visitor},
+};
+
+use ShaderStage::*;
+
+const RAY_TRACING: &[ShaderStage] = &[RayGen, Intersection, AnyHit, ClosestHit, Miss, Callable];
+const RAY_HIT: &[ShaderStage] = &[Intersection, AnyHit, ClosestHit, Miss];
+const WORKGROUP: &[ShaderStage] = &[Compute, Mesh, Task];
+const PRE_RASTER: &[ShaderStage] = &[Vertex, TessControl, TessEval, Geometry, Mesh];
+
+/// Stages in which stage-specific built-in variables are available
+fn builtin_variable_stages(name: &str) -> Option<&'static [ShaderStage]> {
+    Some(match name {
+        "gl_VertexID" | "gl_InstanceID" | "gl_VertexIndex" | "gl_InstanceIndex"
+        | "gl_BaseVertex" | "gl_BaseInstance" => &[Vertex],
+        "gl_Position" | "gl_PointSize" => PRE_RASTER,
+        // Also fragment shader inputs, since GLSL 4.30 and 4.50 respectively
+        "gl_ClipDistance" | "gl_CullDistance" => {
+            &[Vertex, TessControl, TessEval, Geometry, Mesh, Fragment]
+        }
+        "gl_PatchVerticesIn" | "gl_TessLevelOuter" | "gl_TessLevelInner" => {
+            &[TessControl, TessEval]
+        }
+        "gl_TessCoord" => &[TessEval],
+        "gl_InvocationID" => &[TessControl, Geometry],
+        "gl_PrimitiveIDIn" => &[Geometry],
+        "gl_FragCoord"
+        | "gl_FrontFacing"
+        | "gl_PointCoord"
+        | "gl_FragDepth"
+        | "gl_SampleID"
+        | "gl_SamplePosition"
+        | "gl_SampleMaskIn"
+        | "gl_SampleMask"
+        | "gl_HelperInvocation"
+        | "gl_FragColor"
+        | "gl_FragData" => &[Fragment],
+        "gl_NumWorkGroups"
+        | "gl_WorkGroupID"
+        | "gl_WorkGroupSize"
+        | "gl_LocalInvocationID"
+        | "gl_GlobalInvocationID"
+        | "gl_LocalInvocationIndex" => WORKGROUP,
+        "gl_MeshVerticesEXT"
+        | "gl_MeshPrimitivesEXT"
+        | "gl_PrimitivePointIndicesEXT"
+        | "gl_PrimitiveLineIndicesEXT"
+        | "gl_PrimitiveTriangleIndicesEXT" => &[Mesh],
+        "gl_LaunchIDEXT" | "gl_LaunchSizeEXT" => RAY_TRACING,
+        "gl_WorldRayOriginEXT"
+        | "gl_WorldRayDirectionEXT"
+        | "gl_RayTminEXT"
+        | "gl_RayTmaxEXT"
+        | "gl_IncomingRayFlagsEXT" => RAY_HIT,
+        "gl_HitTEXT"
+        | "gl_HitKindEXT"
+        | "gl_ObjectToWorldEXT"
+        | "gl_WorldToObjectEXT"
+        | "gl_ObjectRayOriginEXT"
+        | "gl_ObjectRayDirectionEXT" => &[Intersection, AnyHit, ClosestHit],
+        _ => return None,
+    })
+}
+
+/// Stages in which stage-specific built-in functions are available
+fn builtin_function_stages(name: &str) -> Option<&'static [ShaderStage]> {
+    Some(match name {
+        "EmitVertex" | "EndPrimitive" | "EmitStreamVertex" | "EndStreamPrimitive" => &[Geometry],
+        "barrier" => &[TessControl, Compute, Mesh, Task],
+        "dFdx"
+        | "dFdy"
+        | "fwidth"
+        | "dFdxFine"
+        | "dFdyFine"
+        | "fwidthFine"
+        | "dFdxCoarse"
+        | "dFdyCoarse"
+        | "fwidthCoarse"
+        | "interpolateAtCentroid"
+        | "interpolateAtSample"
+        | "interpolateAtOffset" => &[Fragment],
+        "SetMeshOutputsEXT" => &[Mesh],
+        "EmitMeshTasksEXT" => &[Task],
+        "traceRayEXT" => &[RayGen, ClosestHit, Miss],
+        "reportIntersectionEXT" => &[Intersection],
+        "executeCallableEXT" => &[RayGen, ClosestHit, Miss, Callable],
+        _ => return None,
+    })
+}
+
+/// Kind of construct reported by a [StageValidationError]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageConstruct {
+    /// Built-in variable
+    Variable,
+    /// Built-in function
+    Function,
+    /// Statement, e.g. `discard`
+    Statement,
+}
+
+/// Usage of a construct which is not available in the validated shader stage
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{name} is not available in {stage} shaders")]
+pub struct StageValidationError {
+    /// Name of the construct
+    pub name: SmolStr,
+    /// Kind of the construct
+    pub construct: StageConstruct,
+    /// Stage the translation unit was validated against
+    pub stage: ShaderStage,
+    /// Location of the usage, if known
+    pub span: Option<NodeSpan>,
+}
+
+struct StageValidator {
+    stage: ShaderStage,
+    /// Functions declared in the translation unit, which may shadow built-in functions
+    user_functions: HashSet<SmolStr>,
+    errors: Vec<StageValidationError>,
+}
+
+impl StageValidator {
+    fn check(
+        &mut self,
+        name: &str,
+        construct: StageConstruct,
+        stages: Option<&[ShaderStage]>,
+        span: Option<NodeSpan>,
+    ) {
+        if stages.is_some_and(|stages| !stages.contains(&self.stage)) {
+            self.errors.push(StageValidationError {
+                name: name.into(),
+                construct,
+                stage: self.stage,
+                span,
+            });
+        }
+    }
+}
+
+impl Visitor for StageValidator {
+    fn visit_function_prototype(&mut self, prototype: &ast::FunctionPrototype) -> Visit {
+        self.user_functions.insert(prototype.name.0.clone());
+        Visit::Children
+    }
+
+    fn visit_expr(&mut self, expr: &ast::Expr) -> Visit {
+        match &**expr {
+            ast::ExprData::Variable(ident) => {
+                self.check(
+                    ident.as_str(),
+                    StageConstruct::Variable,
+                    builtin_variable_stages(ident.as_str()),
+                    ident.span,
+                );
+            }
+            ast::ExprData::FunCall(fun, _) => {
+                if let Some(ident) = fun.as_ident() {
+                    if !self.user_functions.contains(ident.as_str()) {
+                        self.check(
+                            ident.as_str(),
+                            StageConstruct::Function,
+                            builtin_function_stages(ident.as_str()),
+                            ident.span,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Visit::Children
+    }
+
+    fn visit_jump_statement(&mut self, jump: &ast::JumpStatement) -> Visit {
+        if let ast::JumpStatementData::Discard = **jump {
+            self.check(
+                "discard",
+                StageConstruct::Statement,
+                Some(&[Fragment]),
+                jump.span,
+            );
+        }
+
+        Visit::Children
+    }
+}
+
+/// Check the translation unit for usages of built-ins which are not available in `stage`
+///
+/// Only built-ins whose availability depends on the stage are checked. Built-in functions are
+/// not reported if the translation unit declares a function with the same name.
+pub fn validate_stage(tu: &ast::TranslationUnit, stage: ShaderStage) -> Vec<StageValidationError> {
+    let mut validator = StageValidator {
+        stage,
+        user_functions: HashSet::new(),
+        errors: Vec::new(),
+    };
+
+    tu.visit(&mut validator);
+    validator.errors
+}
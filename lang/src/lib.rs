@@ -63,7 +63,10 @@ lalrpop_mod!(
     #[allow(clippy::all)]
     parser
 );
+pub mod analysis;
+pub mod lint;
 pub mod parse;
+pub mod rewrite;
 pub mod transpiler;
 pub mod visitor;
 
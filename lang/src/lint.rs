@@ -0,0 +1,149 @@
+//! Lint passes on parsed syntax trees
+//!
+//! Lints report code which is valid GLSL, but likely to be a mistake. They are built on the
+//! [analysis](crate::analysis) passes, so the translation unit should come from the parser.
+
+use std::collections::HashSet;
+
+use lang_util::{position::NodeSpan, SmolStr};
+
+use crate::{
+    analysis::scope::{ScopeEntryKind, ScopeTree},
+    ast,
+    visitor::{Host, Visit, Visitor},
+};
+
+/// Suggested fix for a [LintWarning]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintFix {
+    /// Remove the declaration
+    Remove,
+    /// Rename the declared identifier
+    Rename(SmolStr),
+}
+
+/// Kind of a [LintWarning]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    /// Local variable which is declared but never read
+    UnusedVariable,
+}
+
+impl std::fmt::Display for LintKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnusedVariable => write!(f, "unused variable"),
+        }
+    }
+}
+
+/// Warning reported by a lint pass
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// Kind of the warning
+    pub kind: LintKind,
+    /// Span of the declared identifier
+    pub span: NodeSpan,
+    /// Name of the declared identifier
+    pub name: SmolStr,
+    /// Suggested fixes, in order of preference
+    pub fixes: Vec<LintFix>,
+}
+
+impl std::fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} `{}`", self.kind, self.name)
+    }
+}
+
+/// Lint for local variables which are declared but never read
+///
+/// Plain assignments to a variable do not count as reads, but compound assignments and
+/// increments do. Variables whose name starts with `_` are exempt.
+pub struct UnusedVariableLint;
+
+struct ReadCollector<'t> {
+    tree: &'t ScopeTree,
+    /// Spans of the declarations which are read
+    read: HashSet<NodeSpan>,
+    /// Spans of the identifiers which are only written to
+    written: HashSet<NodeSpan>,
+    /// Spans of the function definitions
+    functions: Vec<NodeSpan>,
+}
+
+impl Visitor for ReadCollector<'_> {
+    fn visit_function_definition(&mut self, definition: &ast::FunctionDefinition) -> Visit {
+        if let Some(span) = definition.span {
+            self.functions.push(span);
+        }
+
+        Visit::Children
+    }
+
+    fn visit_expr(&mut self, expr: &ast::Expr) -> Visit {
+        match &**expr {
+            ast::ExprData::Assignment(lhs, op, _) if **op == ast::AssignmentOpData::Equal => {
+                if let ast::ExprData::Variable(ident) = &***lhs {
+                    if let Some(span) = ident.span {
+                        self.written.insert(span);
+                    }
+                }
+            }
+            ast::ExprData::Variable(ident) => {
+                if let Some(span) = ident.span {
+                    if !self.written.contains(&span) {
+                        if let Some(entry) = self.tree.resolve(ident.as_str(), span) {
+                            self.read.insert(entry.span);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Visit::Children
+    }
+}
+
+impl UnusedVariableLint {
+    /// Report the unused local variables of a translation unit
+    ///
+    /// Warnings are returned in source order, grouped by source file.
+    pub fn check(tu: &ast::TranslationUnit) -> Vec<LintWarning> {
+        let tree = ScopeTree::new(tu);
+        let mut collector = ReadCollector {
+            tree: &tree,
+            read: HashSet::new(),
+            written: HashSet::new(),
+            functions: Vec::new(),
+        };
+
+        tu.visit(&mut collector);
+
+        let mut warnings: Vec<_> = tree
+            .entries()
+            .filter(|entry| {
+                entry.kind == ScopeEntryKind::Variable
+                    && !entry.name.starts_with('_')
+                    && !collector.read.contains(&entry.span)
+                    && collector.functions.iter().any(|function| {
+                        function.source_id() == entry.span.source_id()
+                            && function.range().contains_range(entry.span.range())
+                    })
+            })
+            .map(|entry| LintWarning {
+                kind: LintKind::UnusedVariable,
+                span: entry.span,
+                name: entry.name.clone(),
+                fixes: vec![
+                    LintFix::Remove,
+                    LintFix::Rename(format!("_{}", entry.name).into()),
+                ],
+            })
+            .collect();
+
+        warnings.sort_by_key(|warning| warning.span.start());
+        warnings
+    }
+}
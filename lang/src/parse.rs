@@ -6,7 +6,7 @@ use crate::{ast, parser};
 
 use glsl_lang_lexer::{HasLexerError, LangLexer, Token};
 
-pub use glsl_lang_lexer::{ParseContext, ParseContextData, ParseOptions};
+pub use glsl_lang_lexer::{ParseContext, ParseContextData, ParseOptions, ShaderStage};
 
 mod builder;
 pub use builder::*;
@@ -45,6 +45,9 @@ pub trait HasParser: Sized {
 }
 
 /// GLSL language parsing functions
+///
+/// The grammar does not perform error recovery: parsing stops at the first lexical or syntax
+/// error, which is the one reported in the returned [ParseError].
 #[allow(clippy::result_large_err)]
 pub trait Parse: HasParser {
     /// Parse the input source
@@ -45,6 +45,341 @@ fn parse_comments() {
     );
 }
 
+#[test]
+fn comments_around_span() {
+    let ctx = ParseContext::new_with_comments();
+
+    let (tu, ctx, _): (ast::TranslationUnit, _, _) =
+        "// first\n/* doc */\nvoid main() {} // trailing\n/* last */"
+            .builder()
+            .context(&ctx)
+            .parse()
+            .unwrap();
+
+    let span = tu.0[0].span.unwrap();
+    let data = ctx.data();
+
+    let before: Vec<_> = data
+        .comments_before(span)
+        .map(|comment| comment.text())
+        .collect();
+    assert_eq!(before, [" first", " doc "]);
+
+    let after: Vec<_> = data
+        .comments_after(span)
+        .map(|comment| comment.text())
+        .collect();
+    assert_eq!(after, [" trailing", " last "]);
+    assert!(data.comments_after(span).next().unwrap().is_single());
+    assert!(data.comments_after(span).last().unwrap().is_multi());
+}
+
+#[test]
+fn source_id_spans() {
+    for source_id in [1, 2] {
+        let opts = ParseOptions::new().with_source_id(lang_util::FileId::new(source_id));
+        let (tu, _, _): (ast::TranslationUnit, _, _) = "void main() { float x = 1.0; }"
+            .builder()
+            .opts(&opts)
+            .parse()
+            .unwrap();
+
+        let ast::ExternalDeclarationData::FunctionDefinition(def) = &tu.0[0].content else {
+            panic!("expected a function definition");
+        };
+
+        assert_eq!(
+            tu.0[0].span.unwrap().source_id(),
+            lang_util::FileId::new(source_id)
+        );
+        assert_eq!(
+            def.statement.span.unwrap().source_id(),
+            lang_util::FileId::new(source_id)
+        );
+    }
+}
+
+#[test]
+fn registered_type_names() {
+    let ctx = ParseContext::new();
+    ctx.add_type_name(ast::IdentifierData::from("Light").into());
+    assert!(ctx.is_type_name("Light"));
+
+    let (tu, _, _): (ast::TranslationUnit, _, _) =
+        "Light l;".builder().context(&ctx).parse().unwrap();
+    assert!(matches!(
+        tu.0[0].content,
+        ast::ExternalDeclarationData::Declaration(_)
+    ));
+
+    assert!(ctx.remove_type_name("Light"));
+    assert!(!ctx.is_type_name("Light"));
+    assert!(!ctx.remove_type_name("Light"));
+}
+
+#[test]
+fn type_table_policy_type_names() {
+    #[derive(Debug)]
+    struct SuffixPolicy;
+
+    impl glsl_lang_lexer::TypeTablePolicy for SuffixPolicy {
+        fn promote_to_type_name(
+            &self,
+            _: &ast::Identifier,
+            _: glsl_lang_lexer::IdentifierContext,
+        ) -> bool {
+            false
+        }
+
+        fn is_type_name(&self, name: &str) -> bool {
+            name.ends_with("_t")
+        }
+    }
+
+    let ctx = ParseContext::new_with_policy(SuffixPolicy);
+    assert!(ctx.is_type_name("light_t"));
+    assert!(!ctx.is_type_name("light"));
+
+    let (tu, _, _): (ast::TranslationUnit, _, _) =
+        "light_t l;".builder().context(&ctx).parse().unwrap();
+    assert!(matches!(
+        tu.0[0].content,
+        ast::ExternalDeclarationData::Declaration(_)
+    ));
+}
+
+#[test]
+fn external_declaration_accessors() {
+    let tu = ast::TranslationUnit::parse("float x;\nvoid main() {}").unwrap();
+
+    assert!(tu.0[0].is_declaration());
+    assert!(tu.0[0].as_function_definition().is_none());
+    assert!(tu.0[1].is_function_definition());
+    assert_eq!(
+        tu.0[1]
+            .as_function_definition()
+            .map(|def| def.prototype.name.as_str()),
+        Some("main")
+    );
+    assert!(tu.0[1].as_declaration().is_none());
+}
+
+#[test]
+fn translation_unit_entry_points() {
+    let tu = ast::TranslationUnit::parse(
+        "float helper() { return 1.0; }
+void main() {}
+float helper(float x) { return x; }
+",
+    )
+    .unwrap();
+
+    assert_eq!(tu.entry_points().count(), 1);
+    assert_eq!(tu.functions_named("helper").count(), 2);
+    assert_eq!(tu.functions_named("missing").count(), 0);
+
+    let tu = ast::TranslationUnit::parse("int main() { return 0; }").unwrap();
+    assert_eq!(tu.entry_points().count(), 0);
+}
+
+#[test]
+fn type_base_type_name() {
+    let tu = ast::TranslationUnit::parse(
+        "uniform highp vec4 color[2];
+struct Light { vec3 pos; };
+Light light;
+struct { float x; } anonymous;
+uniform isampler2DMS samples;
+",
+    )
+    .unwrap();
+
+    let names: Vec<_> = tu
+        .0
+        .iter()
+        .filter_map(|decl| match &decl.as_declaration()?.content {
+            ast::DeclarationData::InitDeclaratorList(list) => Some(list.head.ty.base_type_name()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        names,
+        [
+            Some("vec4"),
+            Some("Light"),
+            Some("Light"),
+            None,
+            Some("isampler2DMS")
+        ]
+    );
+}
+
+#[test]
+fn compound_statement_find_variable() {
+    let tu = ast::TranslationUnit::parse(
+        "void main() {
+    float a = 1.0, b;
+    if (a > 0.0) {
+        vec2 c = vec2(a);
+    } else
+        for (int i = 0; i < 2; ++i) { int d = i; }
+}
+",
+    )
+    .unwrap();
+
+    let body = &tu.functions_named("main").next().unwrap().statement;
+    let first = &body.statement_list[0];
+
+    assert!(std::ptr::eq(body.find_variable("a").unwrap(), first));
+    assert!(std::ptr::eq(body.find_variable("b").unwrap(), first));
+    assert!(body.find_variable("c").is_none());
+
+    assert!(body.find_variable_recursive("c").is_some());
+    assert!(body.find_variable_recursive("d").is_some());
+    assert!(body.find_variable_recursive("i").is_none());
+}
+
+#[test]
+fn expr_literal_value() {
+    use ast::LiteralValue;
+
+    let literal = |src| ast::Expr::parse(src).unwrap().literal_value();
+
+    assert_eq!(literal("42"), Some(LiteralValue::Int(42)));
+    assert_eq!(literal("42u"), Some(LiteralValue::UInt(42)));
+    assert_eq!(literal("true"), Some(LiteralValue::Bool(true)));
+    assert_eq!(literal("0.5"), Some(LiteralValue::Float(0.5)));
+    assert_eq!(literal("0.5lf"), Some(LiteralValue::Double(0.5)));
+    assert_eq!(literal("-1"), None);
+    assert_eq!(literal("x"), None);
+
+    assert_eq!(i32::try_from(LiteralValue::Int(3)), Ok(3));
+    assert!(f32::try_from(LiteralValue::Int(3)).is_err());
+}
+
+#[test]
+fn scope_tree_resolve() {
+    use crate::analysis::scope::{ScopeEntryKind, ScopeTree};
+    use lang_util::{position::NodeSpan, FileId, TextRange, TextSize};
+
+    let src = "uniform float u;
+float f(in float a) { return a; }
+void main() {
+    float x = u;
+    { int x = x + 1; x; }
+    for (int i = 0; i < 2; i++) { x += float(i); }
+    x;
+}
+";
+    let tu = ast::TranslationUnit::parse(src).unwrap();
+    let tree = ScopeTree::new(&tu);
+
+    let at = |pattern: &str, offset: usize| {
+        NodeSpan::new(
+            FileId::new(0),
+            TextRange::at(
+                TextSize::from((src.find(pattern).unwrap() + offset) as u32),
+                TextSize::from(1),
+            ),
+        )
+    };
+    let resolve = |name, pattern, offset| {
+        tree.resolve(name, at(pattern, offset))
+            .map(|entry| (entry.kind, entry.ty.base_type_name(), entry.span))
+    };
+
+    // The initializer refers to the outer variable, the next use to the inner one
+    assert_eq!(
+        resolve("x", "x + 1", 0),
+        Some((ScopeEntryKind::Variable, Some("float"), at("x = u", 0)))
+    );
+    assert_eq!(
+        resolve("x", "x; }", 0),
+        Some((ScopeEntryKind::Variable, Some("int"), at("x = x", 0)))
+    );
+    assert_eq!(
+        resolve("x", "x;\n}", 0),
+        Some((ScopeEntryKind::Variable, Some("float"), at("x = u", 0)))
+    );
+
+    assert_eq!(
+        resolve("i", "float(i)", 6),
+        Some((ScopeEntryKind::Variable, Some("int"), at("i = 0", 0)))
+    );
+    assert_eq!(resolve("i", "x;\n}", 0), None);
+
+    let a = tree.resolve("a", at("return a", 7)).unwrap();
+    assert_eq!(a.kind, ScopeEntryKind::Parameter);
+    assert_eq!(a.storage.as_deref(), Some(&ast::StorageQualifierData::In));
+    assert_eq!(resolve("a", "x = u", 0), None);
+
+    let u = tree.resolve("u", at("x = u", 4)).unwrap();
+    assert_eq!(
+        u.storage.as_deref(),
+        Some(&ast::StorageQualifierData::Uniform)
+    );
+    assert_eq!(
+        resolve("f", "x = u", 0).map(|(kind, ..)| kind),
+        Some(ScopeEntryKind::Function)
+    );
+}
+
+#[test]
+fn validate_stage() {
+    use crate::analysis::stage::{validate_stage, StageConstruct};
+    use parse::ShaderStage;
+
+    let tu = ast::TranslationUnit::parse(
+        "void main() {
+    gl_FragDepth = 0.5;
+    if (gl_FragCoord.x < 0.) discard;
+    EmitVertex();
+    gl_Position = vec4(0.);
+}
+",
+    )
+    .unwrap();
+
+    let errors = |stage| {
+        validate_stage(&tu, stage)
+            .into_iter()
+            .map(|error| (error.name.to_string(), error.construct))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        errors(ShaderStage::Fragment),
+        [
+            ("EmitVertex".to_owned(), StageConstruct::Function),
+            ("gl_Position".to_owned(), StageConstruct::Variable),
+        ]
+    );
+    assert_eq!(
+        errors(ShaderStage::Geometry),
+        [
+            ("gl_FragDepth".to_owned(), StageConstruct::Variable),
+            ("gl_FragCoord".to_owned(), StageConstruct::Variable),
+            ("discard".to_owned(), StageConstruct::Statement),
+        ]
+    );
+
+    // Clip and cull distances are inputs of fragment shaders
+    let tu = ast::TranslationUnit::parse(
+        "void main() { float d = gl_ClipDistance[0] + gl_CullDistance[0]; }",
+    )
+    .unwrap();
+    assert!(validate_stage(&tu, ShaderStage::Fragment).is_empty());
+    assert!(validate_stage(&tu, ShaderStage::Vertex).is_empty());
+    assert_eq!(validate_stage(&tu, ShaderStage::Compute).len(), 2);
+
+    // User functions shadow built-in functions
+    let tu =
+        ast::TranslationUnit::parse("void EmitVertex() {}\nvoid main() { EmitVertex(); }").unwrap();
+    assert!(validate_stage(&tu, ShaderStage::Vertex).is_empty());
+}
+
 #[test]
 fn parse_unary_op() {
     assert_eq!(ast::UnaryOp::parse("+"), Ok(ast::UnaryOpData::Add.into()));
@@ -3075,6 +3410,30 @@ fn parse_pp_extension() {
     );
 }
 
+#[test]
+#[cfg(feature = "lexer-v2-full")]
+fn parse_pp_control_flow_attributes() {
+    let body = "void main() {
+  int a[2], b[2];
+  [[unroll]] for (int i = 0; i < 2; i++) { a[b[0]] = i; }
+  [[dont_unroll, loop_count(4)]] while (true) { break; }
+}
+";
+
+    let external_declarations = parse_pp(&format!(
+        "#extension GL_EXT_control_flow_attributes : enable\n{}",
+        body
+    ))
+    .unwrap()
+    .collect::<Vec<_>>();
+
+    assert_eq!(external_declarations.len(), 2);
+    assert!(external_declarations[1].as_function_definition().is_some());
+
+    // Without the extension, attributes are not valid syntax
+    assert!(parse_pp(body).is_err());
+}
+
 #[test]
 fn parse_pp_several() {
     let external_declarations =
@@ -3287,3 +3646,308 @@ fn parse_dangling_else() {
         .into())
     );
 }
+
+#[test]
+fn constant_fold() {
+    use crate::analysis::{ConstValue, ConstantFold, FoldError};
+    use ast::LiteralValue::*;
+
+    let src = "const int N = 4;
+const float S = float(N) * 0.5;
+const vec3 V = vec3(1.0, 2.0, S);
+const mat2 M = mat2(3.0);
+uniform float u;
+float f(float x) { return x; }
+int a = N * 2 + 1 << 1;
+float b = -abs(-S) + max(1.0, 3.0);
+bool c = N > 3 && !(N == 4u);
+vec2 d = V.zx + 1.0;
+int e = clamp(N % 3 - 5, -2, 2);
+float g = M[1][1];
+float h = u * 2.0;
+float i = f(1.0);
+int j = N / (N - 4);
+float k = min(S, N);
+float l = 1.0 / 0.0;
+";
+    let tu = ast::TranslationUnit::parse(src).unwrap();
+    let fold = ConstantFold::new(&tu);
+
+    let initializer = |name: &str| {
+        tu.0.iter()
+            .find_map(|declaration| match &**declaration {
+                ast::ExternalDeclarationData::Declaration(declaration) => match &**declaration {
+                    ast::DeclarationData::InitDeclaratorList(list)
+                        if list.head.name.as_ref().map(|name| name.as_str()) == Some(name) =>
+                    {
+                        match &**list.head.initializer.as_ref()? {
+                            ast::InitializerData::Simple(expr) => Some(fold.fold(expr)),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    assert_eq!(initializer("S"), Ok(Float(2.).into()));
+    assert_eq!(
+        initializer("V"),
+        Ok(ConstValue::Vector(vec![Float(1.), Float(2.), Float(2.)]))
+    );
+    assert_eq!(
+        initializer("M"),
+        Ok(ConstValue::Matrix(vec![
+            vec![Float(3.), Float(0.)],
+            vec![Float(0.), Float(3.)]
+        ]))
+    );
+    assert_eq!(initializer("a"), Ok(Int(18).into()));
+    assert_eq!(initializer("b"), Ok(Float(1.).into()));
+    assert_eq!(initializer("c"), Ok(Bool(false).into()));
+    assert_eq!(
+        initializer("d"),
+        Ok(ConstValue::Vector(vec![Float(3.), Float(2.)]))
+    );
+    assert_eq!(initializer("e"), Ok(Int(-2).into()));
+    assert_eq!(initializer("g"), Ok(Float(3.).into()));
+    assert_eq!(initializer("h"), Err(FoldError::NotConstant));
+    assert_eq!(initializer("i"), Err(FoldError::NotConstant));
+    assert_eq!(initializer("j"), Err(FoldError::DivisionByZero));
+    assert_eq!(initializer("k"), Ok(Float(2.).into()));
+
+    // Float division by zero folds, but has no literal representation
+    assert_eq!(initializer("l"), Ok(Float(f32::INFINITY).into()));
+    assert!(initializer("l").unwrap().to_expr().is_none());
+    assert!(ConstValue::Vector(vec![Float(1.), Float(f32::NAN)])
+        .to_expr()
+        .is_none());
+    assert!(initializer("k").unwrap().to_expr().is_some());
+}
+
+#[test]
+fn unused_variable_lint() {
+    use crate::lint::{LintFix, LintKind, UnusedVariableLint};
+
+    let src = "float g;
+float f(float p) {
+    float a = p;
+    float b;
+    b = 1.0;
+    float _c;
+    int d = 0;
+    d++;
+    for (int i = 0; i < 2; i += 1) {}
+    return a;
+}
+";
+    let tu = ast::TranslationUnit::parse(src).unwrap();
+    let warnings = UnusedVariableLint::check(&tu);
+
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|warning| warning.name.as_str())
+            .collect::<Vec<_>>(),
+        ["b"]
+    );
+    assert_eq!(
+        usize::from(warnings[0].span.range().start()),
+        src.find("b;").unwrap()
+    );
+    assert_eq!(warnings[0].kind, LintKind::UnusedVariable);
+    assert_eq!(warnings[0].to_string(), "unused variable `b`");
+    assert_eq!(
+        warnings[0].fixes,
+        [LintFix::Remove, LintFix::Rename("_b".into())]
+    );
+}
+
+#[test]
+fn inline_constant() {
+    use crate::{
+        rewrite::InlineConstant,
+        transpiler::glsl::{show_translation_unit, FormattingSettings, FormattingState},
+    };
+
+    let src = "const int N = 2;
+const vec2 V = vec2(N);
+uniform float u;
+float f() {
+    const float K = u;
+    float N = 1.0;
+    return V.x + K + N;
+}
+int g() { return N * 3; }
+";
+    let show = |tu: &ast::TranslationUnit| {
+        let mut output = String::new();
+        show_translation_unit(
+            &mut output,
+            tu,
+            FormattingState::from(&FormattingSettings::minifying()),
+        )
+        .unwrap();
+        output
+    };
+
+    let mut tu = ast::TranslationUnit::parse(src).unwrap();
+    assert_eq!(InlineConstant::new().apply(&mut tu), 3);
+    assert!(show(&tu).starts_with("const int N=2;const vec2 V=vec2(2);"));
+
+    // The reference to N in the initializer of V is removed along with it
+    let mut tu = ast::TranslationUnit::parse(src).unwrap();
+    assert_eq!(
+        InlineConstant::new()
+            .with_remove_declarations(true)
+            .apply(&mut tu),
+        2
+    );
+    assert_eq!(
+        show(&tu),
+        "uniform float u;float f(){const float K=u;float N=1.;return vec2(2.,2.).x+K+N;}int g(){return 2*3;}"
+    );
+
+    // Non-finite values have no literal representation, so they are not inlined
+    let mut tu =
+        ast::TranslationUnit::parse("const float INF = 1.0 / 0.0;\nfloat f() { return INF; }\n")
+            .unwrap();
+    assert_eq!(
+        InlineConstant::new()
+            .with_remove_declarations(true)
+            .apply(&mut tu),
+        0
+    );
+    assert_eq!(show(&tu), "const float INF=1./0.;float f(){return INF;}");
+}
+
+#[test]
+fn analysis_across_files() {
+    use crate::{
+        analysis::{ConstValue, ConstantFold},
+        lint::UnusedVariableLint,
+        rewrite::InlineConstant,
+        transpiler::glsl::{show_translation_unit, FormattingSettings, FormattingState},
+    };
+
+    // Spans from both files overlap, as with a translation unit assembled from an #include
+    let parse = |source: &str, source_id| {
+        let opts = ParseOptions::new().with_source_id(lang_util::FileId::new(source_id));
+        let (tu, _, _): (ast::TranslationUnit, _, _) =
+            source.builder().opts(&opts).parse().unwrap();
+        tu
+    };
+
+    let mut tu = parse("const int A = 1;\nfloat h;\n", 1);
+    tu.0.extend(
+        parse(
+            "const int B = 2;\nint f() { float x = 1.0; return A + B; }\n",
+            0,
+        )
+        .0,
+    );
+
+    let fold = ConstantFold::new(&tu);
+    let ast::ExternalDeclarationData::FunctionDefinition(def) = &*tu.0[3] else {
+        panic!("expected a function definition");
+    };
+    let ast::StatementData::Jump(jump) = &*def.statement.statement_list[1] else {
+        panic!("expected a return statement");
+    };
+    let ast::JumpStatementData::Return(Some(expr)) = &**jump else {
+        panic!("expected a return value");
+    };
+    assert_eq!(
+        fold.fold(expr),
+        Ok(ConstValue::from(ast::LiteralValue::Int(3)))
+    );
+
+    // The global from the other file is not reported as a local variable of f
+    let warnings = UnusedVariableLint::check(&tu);
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|warning| (warning.name.as_str(), warning.span.source_id()))
+            .collect::<Vec<_>>(),
+        [("x", lang_util::FileId::new(0))]
+    );
+
+    assert_eq!(InlineConstant::new().apply(&mut tu), 2);
+    let mut output = String::new();
+    show_translation_unit(
+        &mut output,
+        &tu,
+        FormattingState::from(&FormattingSettings::minifying()),
+    )
+    .unwrap();
+    assert!(output.ends_with("return 1+2;}"));
+}
+
+#[test]
+fn remove_dead_code() {
+    use crate::{
+        rewrite::RemoveDeadCode,
+        transpiler::glsl::{show_translation_unit, FormattingSettings, FormattingState},
+    };
+
+    let src = "const bool DEBUG = false;
+int f(int x) {
+    if (DEBUG) { x = 0; }
+    if (!DEBUG) x += 1; else x -= 1;
+    switch (x) {
+    case 0:
+        return 1;
+        x = 2;
+    default:
+        break;
+    }
+    while (x > 0) if (true) x--;
+    return x;
+    x = 3;
+}
+";
+    let tu = ast::TranslationUnit::parse(src).unwrap();
+    let (tu, removed) = RemoveDeadCode.apply(tu);
+
+    let mut output = String::new();
+    show_translation_unit(
+        &mut output,
+        &tu,
+        FormattingState::from(&FormattingSettings::minifying()),
+    )
+    .unwrap();
+
+    assert_eq!(
+        output,
+        "const bool DEBUG=false;int f(int x){x+=1;switch(x){case 0:return 1;default:break;}while(x>0)x--;return x;}"
+    );
+    assert_eq!(
+        removed
+            .iter()
+            .map(|range| &src[std::ops::Range::<usize>::from(*range)])
+            .collect::<Vec<_>>(),
+        ["if (DEBUG) { x = 0; }", "x -= 1;", "x = 2;", "x = 3;"]
+    );
+}
+
+#[test]
+fn strip_comments() {
+    use crate::rewrite::StripComments;
+
+    let src = "// héader\nvoid main() { /* a\nb */ return; } // end";
+    let ctx = ParseContext::new_with_comments();
+    let (tu, ctx, _): (ast::TranslationUnit, _, _) = src.builder().context(&ctx).parse().unwrap();
+
+    let stripped = StripComments::new().apply(src, ctx.data().comments().unwrap());
+    assert_eq!(
+        stripped,
+        "          \nvoid main() {     \n     return; }       "
+    );
+    assert_eq!(stripped.len(), src.len());
+
+    // Spans of the syntax tree are still valid
+    let span = tu.0[0].span.unwrap().range();
+    assert_eq!(&stripped[span], "void main() {     \n     return; }");
+}
@@ -0,0 +1,10 @@
+//! Rewriting passes on parsed syntax trees
+
+mod comments;
+pub use comments::StripComments;
+
+mod dead_code;
+pub use dead_code::RemoveDeadCode;
+
+mod inline_constant;
+pub use inline_constant::InlineConstant;
@@ -0,0 +1,73 @@
+use glsl_lang_lexer::CommentList;
+use lang_util::{FileId, TextRange, TextSize};
+
+/// Pass replacing the comments of a source string with whitespace
+///
+/// Comments are obtained by parsing with a [ParseContext](crate::parse::ParseContext) created by
+/// [new_with_comments](crate::parse::ParseContext::new_with_comments). Every character of a
+/// comment is replaced with as many spaces as its length in bytes, except for line breaks which
+/// are kept. Byte offsets and line numbers of the rest of the source are thus unchanged, so the
+/// spans of the parsed syntax tree remain valid for the stripped text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StripComments {
+    /// Identifier of the source string the comments are stripped from. Comments from other
+    /// sources, e.g. included files, are ignored.
+    pub source_id: FileId,
+}
+
+impl Default for StripComments {
+    fn default() -> Self {
+        Self {
+            source_id: FileId::new(0),
+        }
+    }
+}
+
+impl StripComments {
+    /// Create a new pass, which strips the comments of the main source string
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the identifier of the source string to strip comments from
+    pub fn with_source_id(self, source_id: FileId) -> Self {
+        Self { source_id }
+    }
+
+    /// Return `source` with all its comments replaced by whitespace
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: source string the comments were parsed from
+    /// * `comments`: comments returned by the parse context
+    pub fn apply(&self, source: &str, comments: &CommentList) -> String {
+        let end = TextSize::of(source);
+        let mut result = String::with_capacity(source.len());
+        let mut last = TextSize::default();
+
+        for span in comments
+            .keys()
+            .filter(|span| span.source_id() == self.source_id)
+            .map(|span| span.range())
+        {
+            // Skip overlapping comments and comments from a different source string
+            if span.start() < last || span.end() > end {
+                continue;
+            }
+
+            result.push_str(&source[TextRange::new(last, span.start())]);
+
+            for c in source[span].chars() {
+                match c {
+                    '\n' | '\r' => result.push(c),
+                    c => result.extend(std::iter::repeat(' ').take(c.len_utf8())),
+                }
+            }
+
+            last = span.end();
+        }
+
+        result.push_str(&source[TextRange::new(last, end)]);
+        result
+    }
+}
@@ -0,0 +1,146 @@
+use lang_util::TextRange;
+
+use crate::{
+    analysis::{ConstValue, ConstantFold},
+    ast::{self, LiteralValue},
+    visitor::{HostMut, Visit, VisitorMut},
+};
+
+/// Pass removing unreachable statements
+///
+/// Statements following an unconditional `return`, `break`, `continue` or `discard` are removed,
+/// up to the next `case` label in `switch` bodies. Selection statements whose condition folds to
+/// a constant using [ConstantFold] are replaced by the branch that is taken.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RemoveDeadCode;
+
+/// Result of simplifying a selection statement
+enum Simplified {
+    Keep,
+    Replace(ast::Statement),
+    Remove,
+}
+
+struct Remover {
+    fold: ConstantFold,
+    /// Ranges of the removed statements
+    removed: Vec<TextRange>,
+}
+
+impl Remover {
+    fn record(&mut self, statement: &ast::Statement) {
+        if let Some(span) = statement.span {
+            self.removed.push(span.range());
+        }
+    }
+
+    /// Simplify `statement` if it is a selection statement with a constant condition
+    fn simplify(&mut self, statement: &ast::Statement) -> Simplified {
+        let selection = match &**statement {
+            ast::StatementData::Selection(selection) => selection,
+            _ => return Simplified::Keep,
+        };
+
+        let taken = match self.fold.fold(&selection.cond) {
+            Ok(ConstValue::Scalar(LiteralValue::Bool(taken))) => taken,
+            _ => return Simplified::Keep,
+        };
+
+        match (&*selection.rest, taken) {
+            (ast::SelectionRestStatementData::Statement(body), true) => {
+                Simplified::Replace((**body).clone())
+            }
+            (ast::SelectionRestStatementData::Statement(_), false) => {
+                self.record(statement);
+                Simplified::Remove
+            }
+            (ast::SelectionRestStatementData::Else(body, rest), true) => {
+                self.record(rest);
+                Simplified::Replace((**body).clone())
+            }
+            (ast::SelectionRestStatementData::Else(body, rest), false) => {
+                self.record(body);
+                Simplified::Replace((**rest).clone())
+            }
+        }
+    }
+
+    /// Simplify the statements of a list, and remove the unreachable ones
+    fn statement_list(&mut self, statements: &mut Vec<ast::Statement>) {
+        let mut reachable = true;
+
+        *statements = std::mem::take(statements)
+            .into_iter()
+            .filter_map(|mut statement| {
+                loop {
+                    match self.simplify(&statement) {
+                        Simplified::Keep => break,
+                        Simplified::Replace(replacement) => statement = replacement,
+                        Simplified::Remove => return None,
+                    }
+                }
+
+                match &*statement {
+                    ast::StatementData::CaseLabel(_) => reachable = true,
+                    _ if !reachable => {
+                        self.record(&statement);
+                        return None;
+                    }
+                    ast::StatementData::Jump(_) => reachable = false,
+                    _ => {}
+                }
+
+                Some(statement)
+            })
+            .collect();
+    }
+}
+
+impl VisitorMut for Remover {
+    fn visit_statement(&mut self, statement: &mut ast::Statement) -> Visit {
+        // Statements in lists are handled by statement_list, this only covers single statements
+        // such as loop bodies
+        loop {
+            match self.simplify(statement) {
+                Simplified::Keep => break,
+                Simplified::Replace(replacement) => *statement = replacement,
+                Simplified::Remove => {
+                    *statement =
+                        ast::StatementData::Expression(ast::ExprStatementData(None).into()).into();
+                    break;
+                }
+            }
+        }
+
+        Visit::Children
+    }
+
+    fn visit_compound_statement(&mut self, compound: &mut ast::CompoundStatement) -> Visit {
+        self.statement_list(&mut compound.statement_list);
+        Visit::Children
+    }
+
+    fn visit_switch_statement(&mut self, switch: &mut ast::SwitchStatement) -> Visit {
+        self.statement_list(&mut switch.body);
+        Visit::Children
+    }
+}
+
+impl RemoveDeadCode {
+    /// Remove the unreachable statements of a translation unit
+    ///
+    /// Returns the transformed translation unit, along with the ranges of the removed
+    /// statements in source order.
+    pub fn apply(&self, mut tu: ast::TranslationUnit) -> (ast::TranslationUnit, Vec<TextRange>) {
+        let mut remover = Remover {
+            fold: ConstantFold::new(&tu),
+            removed: Vec::new(),
+        };
+
+        tu.visit_mut(&mut remover);
+
+        let mut removed = remover.removed;
+        removed.sort_by_key(|range| range.start());
+        (tu, removed)
+    }
+}
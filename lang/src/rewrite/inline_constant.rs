@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use lang_util::position::NodeSpan;
+
+use crate::{
+    analysis::{is_const, scope::ScopeTree, ConstantFold},
+    ast,
+    visitor::{Host, HostMut, Visit, Visitor, VisitorMut},
+};
+
+/// Pass replacing references to `const` variables with their value
+///
+/// Only single-variable declarations whose initializer can be evaluated by [ConstantFold] are
+/// inlined. References are replaced by the folded value, e.g. `const float A = 2. * 3.;` is
+/// inlined as `6.`. Values which cannot be written as a literal, such as the infinity resulting
+/// from `1. / 0.`, are not inlined.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InlineConstant {
+    /// Remove the declarations of the inlined variables
+    pub remove_declarations: bool,
+}
+
+/// Collect the folded values of the inlinable `const` variables
+struct Collector<'f> {
+    fold: &'f ConstantFold,
+    /// Folded values, by span of the declared identifier
+    constants: HashMap<NodeSpan, ast::Expr>,
+}
+
+impl Visitor for Collector<'_> {
+    fn visit_init_declarator_list(&mut self, list: &ast::InitDeclaratorList) -> Visit {
+        let head = &list.head;
+
+        if list.tail.is_empty()
+            && head.array_specifier.is_none()
+            && head.ty.ty.array_specifier.is_none()
+            && is_const(&head.ty)
+        {
+            if let (Some(span), Some(initializer)) = (
+                head.name.as_ref().and_then(|name| name.span),
+                head.initializer.as_ref(),
+            ) {
+                if let ast::InitializerData::Simple(expr) = &**initializer {
+                    if let Some(value) = self.fold.fold(expr).ok().and_then(|v| v.to_expr()) {
+                        self.constants.insert(span, value);
+                    }
+                }
+            }
+        }
+
+        Visit::Parent
+    }
+}
+
+/// Replace the references to the collected constants
+struct Inliner<'a> {
+    scopes: &'a ScopeTree,
+    constants: &'a HashMap<NodeSpan, ast::Expr>,
+    remove_declarations: bool,
+    count: usize,
+}
+
+impl Inliner<'_> {
+    /// Return `true` if `declaration` declares an inlined constant
+    fn is_inlined(&self, declaration: &ast::Declaration) -> bool {
+        match &**declaration {
+            ast::DeclarationData::InitDeclaratorList(list) => list
+                .head
+                .name
+                .as_ref()
+                .and_then(|name| name.span)
+                .is_some_and(|span| self.constants.contains_key(&span)),
+            _ => false,
+        }
+    }
+}
+
+impl VisitorMut for Inliner<'_> {
+    fn visit_compound_statement(&mut self, compound: &mut ast::CompoundStatement) -> Visit {
+        if self.remove_declarations {
+            compound
+                .statement_list
+                .retain(|statement| match &**statement {
+                    ast::StatementData::Declaration(declaration) => !self.is_inlined(declaration),
+                    _ => true,
+                });
+        }
+
+        Visit::Children
+    }
+
+    fn visit_expr(&mut self, expr: &mut ast::Expr) -> Visit {
+        if let ast::ExprData::Variable(ident) = &**expr {
+            if let Some(value) = ident
+                .span
+                .and_then(|span| self.scopes.resolve(ident.as_str(), span))
+                .and_then(|entry| self.constants.get(&entry.span))
+            {
+                *expr = ast::Expr::new(value.content.clone(), expr.span);
+                self.count += 1;
+                return Visit::Parent;
+            }
+        }
+
+        Visit::Children
+    }
+}
+
+impl InlineConstant {
+    /// Create a new pass, which keeps the declarations of the inlined variables
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether the declarations of the inlined variables should be removed
+    pub fn with_remove_declarations(self, remove_declarations: bool) -> Self {
+        Self {
+            remove_declarations,
+        }
+    }
+
+    /// Inline the constants of a translation unit, returning the number of replaced references
+    pub fn apply(&self, tu: &mut ast::TranslationUnit) -> usize {
+        let fold = ConstantFold::new(tu);
+        let scopes = ScopeTree::new(tu);
+
+        let mut collector = Collector {
+            fold: &fold,
+            constants: HashMap::new(),
+        };
+        tu.visit(&mut collector);
+
+        let mut inliner = Inliner {
+            scopes: &scopes,
+            constants: &collector.constants,
+            remove_declarations: self.remove_declarations,
+            count: 0,
+        };
+
+        if self.remove_declarations {
+            tu.0.retain(|declaration| match &**declaration {
+                ast::ExternalDeclarationData::Declaration(declaration) => {
+                    !inliner.is_inlined(declaration)
+                }
+                _ => true,
+            });
+        }
+
+        tu.visit_mut(&mut inliner);
+        inliner.count
+    }
+}
@@ -705,159 +705,12 @@ where
     F: Write + ?Sized,
 {
     match **t {
-        ast::TypeSpecifierNonArrayData::Void => f.write_str("void"),
-        ast::TypeSpecifierNonArrayData::Bool => f.write_str("bool"),
-        ast::TypeSpecifierNonArrayData::Int => f.write_str("int"),
-        ast::TypeSpecifierNonArrayData::UInt => f.write_str("uint"),
-        ast::TypeSpecifierNonArrayData::Float => f.write_str("float"),
-        ast::TypeSpecifierNonArrayData::Double => f.write_str("double"),
-        ast::TypeSpecifierNonArrayData::Vec2 => f.write_str("vec2"),
-        ast::TypeSpecifierNonArrayData::Vec3 => f.write_str("vec3"),
-        ast::TypeSpecifierNonArrayData::Vec4 => f.write_str("vec4"),
-        ast::TypeSpecifierNonArrayData::DVec2 => f.write_str("dvec2"),
-        ast::TypeSpecifierNonArrayData::DVec3 => f.write_str("dvec3"),
-        ast::TypeSpecifierNonArrayData::DVec4 => f.write_str("dvec4"),
-        ast::TypeSpecifierNonArrayData::BVec2 => f.write_str("bvec2"),
-        ast::TypeSpecifierNonArrayData::BVec3 => f.write_str("bvec3"),
-        ast::TypeSpecifierNonArrayData::BVec4 => f.write_str("bvec4"),
-        ast::TypeSpecifierNonArrayData::IVec2 => f.write_str("ivec2"),
-        ast::TypeSpecifierNonArrayData::IVec3 => f.write_str("ivec3"),
-        ast::TypeSpecifierNonArrayData::IVec4 => f.write_str("ivec4"),
-        ast::TypeSpecifierNonArrayData::UVec2 => f.write_str("uvec2"),
-        ast::TypeSpecifierNonArrayData::UVec3 => f.write_str("uvec3"),
-        ast::TypeSpecifierNonArrayData::UVec4 => f.write_str("uvec4"),
-        ast::TypeSpecifierNonArrayData::Mat2 => f.write_str("mat2"),
-        ast::TypeSpecifierNonArrayData::Mat3 => f.write_str("mat3"),
-        ast::TypeSpecifierNonArrayData::Mat4 => f.write_str("mat4"),
-        ast::TypeSpecifierNonArrayData::Mat22 => f.write_str("mat2x2"),
-        ast::TypeSpecifierNonArrayData::Mat23 => f.write_str("mat2x3"),
-        ast::TypeSpecifierNonArrayData::Mat24 => f.write_str("mat2x4"),
-        ast::TypeSpecifierNonArrayData::Mat32 => f.write_str("mat3x2"),
-        ast::TypeSpecifierNonArrayData::Mat33 => f.write_str("mat3x3"),
-        ast::TypeSpecifierNonArrayData::Mat34 => f.write_str("mat3x4"),
-        ast::TypeSpecifierNonArrayData::Mat42 => f.write_str("mat4x2"),
-        ast::TypeSpecifierNonArrayData::Mat43 => f.write_str("mat4x3"),
-        ast::TypeSpecifierNonArrayData::Mat44 => f.write_str("mat4x4"),
-        ast::TypeSpecifierNonArrayData::DMat2 => f.write_str("dmat2"),
-        ast::TypeSpecifierNonArrayData::DMat3 => f.write_str("dmat3"),
-        ast::TypeSpecifierNonArrayData::DMat4 => f.write_str("dmat4"),
-        ast::TypeSpecifierNonArrayData::DMat22 => f.write_str("dmat2x2"),
-        ast::TypeSpecifierNonArrayData::DMat23 => f.write_str("dmat2x3"),
-        ast::TypeSpecifierNonArrayData::DMat24 => f.write_str("dmat2x4"),
-        ast::TypeSpecifierNonArrayData::DMat32 => f.write_str("dmat3x2"),
-        ast::TypeSpecifierNonArrayData::DMat33 => f.write_str("dmat3x3"),
-        ast::TypeSpecifierNonArrayData::DMat34 => f.write_str("dmat3x4"),
-        ast::TypeSpecifierNonArrayData::DMat42 => f.write_str("dmat4x2"),
-        ast::TypeSpecifierNonArrayData::DMat43 => f.write_str("dmat4x3"),
-        ast::TypeSpecifierNonArrayData::DMat44 => f.write_str("dmat4x4"),
-        ast::TypeSpecifierNonArrayData::Sampler1D => f.write_str("sampler1D"),
-        ast::TypeSpecifierNonArrayData::Image1D => f.write_str("image1D"),
-        ast::TypeSpecifierNonArrayData::Sampler2D => f.write_str("sampler2D"),
-        ast::TypeSpecifierNonArrayData::Image2D => f.write_str("image2D"),
-        ast::TypeSpecifierNonArrayData::Sampler3D => f.write_str("sampler3D"),
-        ast::TypeSpecifierNonArrayData::Image3D => f.write_str("image3D"),
-        ast::TypeSpecifierNonArrayData::SamplerCube => f.write_str("samplerCube"),
-        ast::TypeSpecifierNonArrayData::ImageCube => f.write_str("imageCube"),
-        ast::TypeSpecifierNonArrayData::Sampler2DRect => f.write_str("sampler2DRect"),
-        ast::TypeSpecifierNonArrayData::Image2DRect => f.write_str("image2DRect"),
-        ast::TypeSpecifierNonArrayData::Sampler1DArray => f.write_str("sampler1DArray"),
-        ast::TypeSpecifierNonArrayData::Image1DArray => f.write_str("image1DArray"),
-        ast::TypeSpecifierNonArrayData::Sampler2DArray => f.write_str("sampler2DArray"),
-        ast::TypeSpecifierNonArrayData::Image2DArray => f.write_str("image2DArray"),
-        ast::TypeSpecifierNonArrayData::SamplerBuffer => f.write_str("samplerBuffer"),
-        ast::TypeSpecifierNonArrayData::ImageBuffer => f.write_str("imageBuffer"),
-        ast::TypeSpecifierNonArrayData::Sampler2DMs => f.write_str("sampler2DMS"),
-        ast::TypeSpecifierNonArrayData::Image2DMs => f.write_str("image2DMS"),
-        ast::TypeSpecifierNonArrayData::Sampler2DMsArray => f.write_str("sampler2DMSArray"),
-        ast::TypeSpecifierNonArrayData::Image2DMsArray => f.write_str("image2DMSArray"),
-        ast::TypeSpecifierNonArrayData::SamplerCubeArray => f.write_str("samplerCubeArray"),
-        ast::TypeSpecifierNonArrayData::ImageCubeArray => f.write_str("imageCubeArray"),
-        ast::TypeSpecifierNonArrayData::Sampler1DShadow => f.write_str("sampler1DShadow"),
-        ast::TypeSpecifierNonArrayData::Sampler2DShadow => f.write_str("sampler2DShadow"),
-        ast::TypeSpecifierNonArrayData::Sampler2DRectShadow => f.write_str("sampler2DRectShadow"),
-        ast::TypeSpecifierNonArrayData::Sampler1DArrayShadow => f.write_str("sampler1DArrayShadow"),
-        ast::TypeSpecifierNonArrayData::Sampler2DArrayShadow => f.write_str("sampler2DArrayShadow"),
-        ast::TypeSpecifierNonArrayData::SamplerCubeShadow => f.write_str("samplerCubeShadow"),
-        ast::TypeSpecifierNonArrayData::SamplerCubeArrayShadow => {
-            f.write_str("samplerCubeArrayShadow")
-        }
-        ast::TypeSpecifierNonArrayData::ISampler1D => f.write_str("isampler1D"),
-        ast::TypeSpecifierNonArrayData::IImage1D => f.write_str("iimage1D"),
-        ast::TypeSpecifierNonArrayData::ISampler2D => f.write_str("isampler2D"),
-        ast::TypeSpecifierNonArrayData::IImage2D => f.write_str("iimage2D"),
-        ast::TypeSpecifierNonArrayData::ISampler3D => f.write_str("isampler3D"),
-        ast::TypeSpecifierNonArrayData::IImage3D => f.write_str("iimage3D"),
-        ast::TypeSpecifierNonArrayData::ISamplerCube => f.write_str("isamplerCube"),
-        ast::TypeSpecifierNonArrayData::IImageCube => f.write_str("iimageCube"),
-        ast::TypeSpecifierNonArrayData::ISampler2DRect => f.write_str("isampler2DRect"),
-        ast::TypeSpecifierNonArrayData::IImage2DRect => f.write_str("iimage2DRect"),
-        ast::TypeSpecifierNonArrayData::ISampler1DArray => f.write_str("isampler1DArray"),
-        ast::TypeSpecifierNonArrayData::IImage1DArray => f.write_str("iimage1DArray"),
-        ast::TypeSpecifierNonArrayData::ISampler2DArray => f.write_str("isampler2DArray"),
-        ast::TypeSpecifierNonArrayData::IImage2DArray => f.write_str("iimage2DArray"),
-        ast::TypeSpecifierNonArrayData::ISamplerBuffer => f.write_str("isamplerBuffer"),
-        ast::TypeSpecifierNonArrayData::IImageBuffer => f.write_str("iimageBuffer"),
-        ast::TypeSpecifierNonArrayData::ISampler2DMs => f.write_str("isampler2MS"),
-        ast::TypeSpecifierNonArrayData::IImage2DMs => f.write_str("iimage2DMS"),
-        ast::TypeSpecifierNonArrayData::ISampler2DMsArray => f.write_str("isampler2DMSArray"),
-        ast::TypeSpecifierNonArrayData::IImage2DMsArray => f.write_str("iimage2DMSArray"),
-        ast::TypeSpecifierNonArrayData::ISamplerCubeArray => f.write_str("isamplerCubeArray"),
-        ast::TypeSpecifierNonArrayData::IImageCubeArray => f.write_str("iimageCubeArray"),
-        ast::TypeSpecifierNonArrayData::AtomicUInt => f.write_str("atomic_uint"),
-        ast::TypeSpecifierNonArrayData::USampler1D => f.write_str("usampler1D"),
-        ast::TypeSpecifierNonArrayData::UImage1D => f.write_str("uimage1D"),
-        ast::TypeSpecifierNonArrayData::USampler2D => f.write_str("usampler2D"),
-        ast::TypeSpecifierNonArrayData::UImage2D => f.write_str("uimage2D"),
-        ast::TypeSpecifierNonArrayData::USampler3D => f.write_str("usampler3D"),
-        ast::TypeSpecifierNonArrayData::UImage3D => f.write_str("uimage3D"),
-        ast::TypeSpecifierNonArrayData::USamplerCube => f.write_str("usamplerCube"),
-        ast::TypeSpecifierNonArrayData::UImageCube => f.write_str("uimageCube"),
-        ast::TypeSpecifierNonArrayData::USampler2DRect => f.write_str("usampler2DRect"),
-        ast::TypeSpecifierNonArrayData::UImage2DRect => f.write_str("uimage2DRect"),
-        ast::TypeSpecifierNonArrayData::USampler1DArray => f.write_str("usampler1DArray"),
-        ast::TypeSpecifierNonArrayData::UImage1DArray => f.write_str("uimage1DArray"),
-        ast::TypeSpecifierNonArrayData::USampler2DArray => f.write_str("usampler2DArray"),
-        ast::TypeSpecifierNonArrayData::UImage2DArray => f.write_str("uimage2DArray"),
-        ast::TypeSpecifierNonArrayData::USamplerBuffer => f.write_str("usamplerBuffer"),
-        ast::TypeSpecifierNonArrayData::UImageBuffer => f.write_str("uimageBuffer"),
-        ast::TypeSpecifierNonArrayData::USampler2DMs => f.write_str("usampler2DMS"),
-        ast::TypeSpecifierNonArrayData::UImage2DMs => f.write_str("uimage2DMS"),
-        ast::TypeSpecifierNonArrayData::USampler2DMsArray => f.write_str("usamplerDMSArray"),
-        ast::TypeSpecifierNonArrayData::UImage2DMsArray => f.write_str("uimage2DMSArray"),
-        ast::TypeSpecifierNonArrayData::USamplerCubeArray => f.write_str("usamplerCubeArray"),
-        ast::TypeSpecifierNonArrayData::UImageCubeArray => f.write_str("uimageCubeArray"),
-        ast::TypeSpecifierNonArrayData::Texture1D => f.write_str("texture1D"),
-        ast::TypeSpecifierNonArrayData::Texture2D => f.write_str("texture2D"),
-        ast::TypeSpecifierNonArrayData::Texture3D => f.write_str("texture3D"),
-        ast::TypeSpecifierNonArrayData::TextureCube => f.write_str("textureCube"),
-        ast::TypeSpecifierNonArrayData::Texture2DRect => f.write_str("texture2DRect"),
-        ast::TypeSpecifierNonArrayData::Texture1DArray => f.write_str("texture1DArray"),
-        ast::TypeSpecifierNonArrayData::Texture2DArray => f.write_str("texture2DArray"),
-        ast::TypeSpecifierNonArrayData::TextureBuffer => f.write_str("textureBuffer"),
-        ast::TypeSpecifierNonArrayData::Texture2DMs => f.write_str("texture2DMS"),
-        ast::TypeSpecifierNonArrayData::Texture2DMsArray => f.write_str("texture2DMSArray"),
-        ast::TypeSpecifierNonArrayData::TextureCubeArray => f.write_str("textureCubeArray"),
-        ast::TypeSpecifierNonArrayData::ITexture1D => f.write_str("itexture1D"),
-        ast::TypeSpecifierNonArrayData::ITexture2D => f.write_str("itexture2D"),
-        ast::TypeSpecifierNonArrayData::ITexture3D => f.write_str("itexture3D"),
-        ast::TypeSpecifierNonArrayData::ITextureCube => f.write_str("itextureCube"),
-        ast::TypeSpecifierNonArrayData::ITexture2DRect => f.write_str("itexture2DRect"),
-        ast::TypeSpecifierNonArrayData::ITexture1DArray => f.write_str("itexture1DArray"),
-        ast::TypeSpecifierNonArrayData::ITexture2DArray => f.write_str("itexture2DArray"),
-        ast::TypeSpecifierNonArrayData::ITextureBuffer => f.write_str("itextureBuffer"),
-        ast::TypeSpecifierNonArrayData::ITexture2DMs => f.write_str("itexture2DMS"),
-        ast::TypeSpecifierNonArrayData::ITexture2DMsArray => f.write_str("itexture2DMSArray"),
-        ast::TypeSpecifierNonArrayData::ITextureCubeArray => f.write_str("itextureCubeArray"),
-        ast::TypeSpecifierNonArrayData::Sampler => f.write_str("sampler"),
-        ast::TypeSpecifierNonArrayData::SamplerShadow => f.write_str("samplerShadow"),
-        ast::TypeSpecifierNonArrayData::SubpassInput => f.write_str("subpassInput"),
-        ast::TypeSpecifierNonArrayData::ISubpassInput => f.write_str("isubpassInput"),
-        ast::TypeSpecifierNonArrayData::USubpassInput => f.write_str("usubpassInput"),
-        ast::TypeSpecifierNonArrayData::SubpassInputMs => f.write_str("subpassInputMS"),
-        ast::TypeSpecifierNonArrayData::ISubpassInputMs => f.write_str("isubpassInputMS"),
-        ast::TypeSpecifierNonArrayData::USubpassInputMs => f.write_str("usubpassInputMS"),
         ast::TypeSpecifierNonArrayData::Struct(ref st) => show_struct_non_declaration(f, st, state),
         ast::TypeSpecifierNonArrayData::TypeName(ref tn) => show_type_name(f, tn, state),
+        ref ty => f.write_str(
+            ty.base_type_name()
+                .expect("built-in types always have a name"),
+        ),
     }
 }
 
@@ -1527,6 +1380,7 @@ where
             show_init_declarator_list(f, list, state)?;
         }
         ast::DeclarationData::Precision(ref qual, ref ty) => {
+            f.write_str("precision ")?;
             show_precision_qualifier(f, qual, state)?;
             f.write_str(" ")?;
             show_type_specifier(f, ty, state)?;
@@ -1752,6 +1606,33 @@ where
     state.write_function_definition_terminator(f)
 }
 
+/// Signature display for function definitions
+///
+/// This is mostly useful for tooling, e.g. to show the signature of a function on hover without
+/// its body.
+pub trait FunctionSignature {
+    /// Return the signature of the function as GLSL, e.g. `vec4 applyFog(vec4 color, float
+    /// dist)`
+    ///
+    /// The signature is formatted using the default [FormattingSettings]. Parameter qualifiers
+    /// are included.
+    fn signature_string(&self) -> String;
+}
+
+impl FunctionSignature for ast::FunctionPrototype {
+    fn signature_string(&self) -> String {
+        let mut s = String::new();
+        show_function_prototype(&mut s, self, &mut FormattingState::default()).unwrap();
+        s
+    }
+}
+
+impl FunctionSignature for ast::FunctionDefinition {
+    fn signature_string(&self) -> String {
+        self.prototype.signature_string()
+    }
+}
+
 /// Transpile a compound_statement to GLSL
 pub fn show_compound_statement<F>(
     f: &mut F,
@@ -2397,6 +2278,17 @@ mod tests {
         })
     }
 
+    fn assert_parse_roundtrip(src: &str, settings: &FormattingSettings) {
+        let tu = ast::TranslationUnit::parse(src).unwrap();
+
+        let mut output = String::new();
+        show_translation_unit(&mut output, &tu, FormattingState::from(settings)).unwrap();
+
+        let back = ast::TranslationUnit::parse(&output)
+            .unwrap_or_else(|err| panic!("failed to parse '{}': {}", output, err));
+        assert_eq!(back, tu, "intermediate source '{}'", output);
+    }
+
     #[test]
     fn unary_parentheses() {
         check_expr("-a", expect![["-a"]]);
@@ -2450,6 +2342,22 @@ mod tests {
         check_expr("arr[0][0]", expect![["arr[0][0]"]]);
     }
 
+    #[test]
+    fn multisample_sampler_types() {
+        for (ty, expected) in [
+            (ast::TypeSpecifierNonArrayData::ISampler2DMs, "isampler2DMS"),
+            (
+                ast::TypeSpecifierNonArrayData::USampler2DMsArray,
+                "usampler2DMSArray",
+            ),
+        ] {
+            let mut s = String::new();
+            show_type_specifier_non_array(&mut s, &ty.into(), &mut FormattingState::default())
+                .unwrap();
+            assert_eq!(s, expected);
+        }
+    }
+
     #[test]
     fn test_single_statement_function_is_not_collapsed() {
         const SRC: &str = r#"vec2 main() {
@@ -2620,6 +2528,45 @@ return vec2(0., 0.);
         assert_eq!(back, input, "intermediate source '{}'", output);
     }
 
+    #[test]
+    fn roundtrip_translation_unit() {
+        let src = r#"
+            precision highp float;
+            struct Light { vec3 pos; float radius[2]; };
+            layout(std140, binding = 0) uniform Globals { mat4 view; Light lights[4]; } globals;
+            in vec2 uv;
+            out vec4 color;
+            const int N = 4;
+
+            float attenuate(in Light l, inout vec3 p, const float k) {
+                return k / (1.0 + dot(l.pos - p, l.pos - p));
+            }
+
+            void main() {
+                vec4 acc = vec4(0.0);
+                for (int i = 0; i < N; ++i) {
+                    if (i == 2) continue; else if (i > 2) { break; }
+                    acc.rgb += vec3(attenuate(globals.lights[i], acc.xyz, 2.0)) * -acc.a;
+                }
+                int j = 0;
+                while (j < 3) j++;
+                do { j -= 1; } while (j > 0);
+                switch (j) { case 0: acc.x = 1.0; break; default: discard; }
+                color = uv.x > 0.5 ? acc : vec4(uv, (1 - j) << 2, ~j & 1);
+            }
+        "#;
+
+        assert_parse_roundtrip(src, &FormattingSettings::default());
+        // Collapsing compound statements changes the AST, disable it for this check
+        assert_parse_roundtrip(
+            src,
+            &FormattingSettings {
+                collapse_single_item_compound_statements: false,
+                ..FormattingSettings::minifying()
+            },
+        );
+    }
+
     #[test]
     fn block_decl_formatting() {
         let src = r#"uniform Global { float param; float param2; };"#;
@@ -2826,4 +2773,30 @@ return vec2(0., 0.);
 
         expected.assert_eq(&s);
     }
+
+    #[test]
+    fn function_signature() {
+        let tu = ast::TranslationUnit::parse(
+            "vec4 applyFog(vec4 color, float dist) { return color * dist; }
+void update(in vec3 a, out float b, inout int c, const float d, precise out vec2 e) {}
+float[2] pair(float) { return float[2](0., 1.); }
+",
+        )
+        .unwrap();
+
+        let signatures: Vec<_> =
+            tu.0.iter()
+                .filter_map(|decl| decl.as_function_definition())
+                .map(|def| def.signature_string())
+                .collect();
+
+        assert_eq!(
+            signatures,
+            [
+                "vec4 applyFog(vec4 color, float dist)",
+                "void update(in vec3 a, out float b, inout int c, const float d, precise out vec2 e)",
+                "float[2] pair(float)",
+            ]
+        );
+    }
 }
@@ -0,0 +1,125 @@
+//! Check that the directives parsed by `glsl-lang-pp` match the ones from `ast::Preprocessor`
+
+#[cfg(all(
+    any(feature = "lexer-v1", feature = "lexer-v2-min"),
+    not(feature = "lexer-v2-full")
+))]
+mod inner {
+    use glsl_lang::{ast, parse::Parsable};
+    use glsl_lang_pp::processor::{
+        event::{DirectiveKind, Event},
+        nodes::ExtensionName,
+        ProcessorState,
+    };
+
+    /// Directive summary which can be compared between both parsers
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Directive {
+        Version(u16),
+        Extension(String),
+        Define(String),
+        /// Directive that could not be parsed by `ast::Preprocessor`
+        Invalid(String),
+    }
+
+    /// Directives of `source` according to `glsl-lang-pp`
+    pub fn pp_directives(source: &str) -> Vec<Directive> {
+        glsl_lang_pp::processor::str::process(source, ProcessorState::default())
+            .filter_map(|event| match event {
+                Ok(Event::Directive { directive, .. }) => match directive.kind() {
+                    DirectiveKind::Version(version) => Some(Directive::Version(version.number)),
+                    DirectiveKind::Extension(extension) => {
+                        Some(Directive::Extension(match &extension.name {
+                            ExtensionName::All => "all".to_owned(),
+                            ExtensionName::Specific(name) => name.to_string(),
+                        }))
+                    }
+                    DirectiveKind::Define(define) => {
+                        Some(Directive::Define(define.name().to_owned()))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Directives of `source` according to `ast::Preprocessor`, parsing each directive line
+    pub fn ast_directives(source: &str) -> Vec<Directive> {
+        let source = source.replace("\\\r\n", "").replace("\\\n", "");
+
+        source
+            .lines()
+            .map(str::trim_start)
+            .filter(|line| {
+                line.strip_prefix('#').is_some_and(|rest| {
+                    let rest = rest.trim_start();
+                    ["version", "extension", "define"]
+                        .iter()
+                        .any(|keyword| rest.starts_with(keyword))
+                })
+            })
+            .map(|line| match ast::Preprocessor::parse(line).as_deref() {
+                Ok(ast::PreprocessorData::Version(version)) => Directive::Version(version.version),
+                Ok(ast::PreprocessorData::Extension(extension)) => {
+                    Directive::Extension(match &*extension.name {
+                        ast::PreprocessorExtensionNameData::All => "all".to_owned(),
+                        ast::PreprocessorExtensionNameData::Specific(name) => name.to_string(),
+                    })
+                }
+                Ok(ast::PreprocessorData::Define(define)) => {
+                    Directive::Define(match &define.content {
+                        ast::PreprocessorDefineData::ObjectLike { ident, .. }
+                        | ast::PreprocessorDefineData::FunctionLike { ident, .. } => {
+                            ident.0.to_string()
+                        }
+                    })
+                }
+                _ => Directive::Invalid(line.to_owned()),
+            })
+            .collect()
+    }
+
+    pub fn check(name: &str, source: &str) {
+        assert_eq!(
+            pp_directives(source),
+            ast_directives(source),
+            "directives of {} do not match",
+            name
+        );
+    }
+}
+
+macro_rules! roundtrip_tests {
+    ($($test:ident => $name:literal),* $(,)?) => {
+        $(
+            #[test]
+            #[cfg_attr(
+                not(all(
+                    any(feature = "lexer-v1", feature = "lexer-v2-min"),
+                    not(feature = "lexer-v2-full")
+                )),
+                ignore = "the lexer-v1 or lexer-v2-min feature is required for this test"
+            )]
+            fn $test() {
+                #[cfg(all(
+                    any(feature = "lexer-v1", feature = "lexer-v2-min"),
+                    not(feature = "lexer-v2-full")
+                ))]
+                inner::check($name, include_str!(concat!("../../data/", $name)));
+            }
+        )*
+    };
+}
+
+roundtrip_tests! {
+    glsl_100_frag => "100.frag",
+    glsl_130_frag => "130.frag",
+    glsl_310_frag => "310.frag",
+    glsl_450_subgroup_frag => "glsl.450.subgroup.frag",
+    glsl_460_subgroup_mesh => "glsl.460.subgroup.mesh",
+    cpp_merge => "cppMerge.frag",
+    spv_int32_frag => "spv.int32.frag",
+    spv_float64_frag => "spv.float64.frag",
+    hlsl_type_conversion => "hlsl.type.type.conversion.all.frag",
+}
@@ -94,7 +94,7 @@ pub fn discover_tests(current_dir: &Path) -> DiscoveredTests {
                     PathBuf::from("..").join(
                         entry
                             .path()
-                            .strip_prefix(&current_dir)
+                            .strip_prefix(current_dir)
                             .expect("failed to strip current dir"),
                     )
                 })